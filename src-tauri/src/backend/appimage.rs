@@ -0,0 +1,130 @@
+//! AppImage backend. Removal moves the file to the FreeDesktop Trash so it
+//! can be restored; AppImages have no central updater, so there is no update.
+
+use std::time::Duration;
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::{self, Scanner};
+use crate::system::{CommandRunner, Runner};
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct AppImageBackend;
+
+impl PackageBackend for AppImageBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::AppImage
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Scan, Capability::Uninstall]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(scanner::appimage::AppImageScanner::new(
+            &runner.dirs(),
+        ))]
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            if operation != Operation::Uninstall {
+                return PlanDraft::unsupported(operation);
+            }
+            PlanDraft::new(
+                AuthMethod::None,
+                vec![PlanStep {
+                    description: format!("Move the AppImage '{}' to Trash.", pkg.package_id),
+                    command_summary: format!("gio trash {}", pkg.package_id),
+                }],
+            )
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            match plan.operation {
+                Operation::Uninstall => appimage_trash(runner.as_ref(), &plan.package_id).await,
+                _ => unsupported(plan),
+            }
+        })
+    }
+}
+
+async fn appimage_trash(runner: &dyn CommandRunner, path: &str) -> OperationResult {
+    // Prefer the FreeDesktop trash via `gio trash` (restorable). Fallback to
+    // moving into ~/.local/share/Trash/files when gio is unavailable.
+    if runner.has_program("gio") {
+        let res = runner
+            .run_step("gio", &["trash", "-f", path], Duration::from_secs(20))
+            .await;
+        if res.success {
+            return res;
+        }
+        // Fall through to manual move if gio failed.
+    }
+    manual_trash(runner, path).await
+}
+
+async fn manual_trash(runner: &dyn CommandRunner, path: &str) -> OperationResult {
+    let Some(home) = runner.dirs().home() else {
+        return OperationResult {
+            success: false,
+            message: "No HOME directory; cannot trash AppImage.".into(),
+            logs: String::new(),
+            exit_code: None,
+        };
+    };
+    let trash_files = home.join(".local/share/Trash/files");
+    if let Some(res) = runner
+        .dry_run_file_change("mv", &[path, &trash_files.to_string_lossy()])
+        .await
+    {
+        return res;
+    }
+    if let Err(e) = tokio::fs::create_dir_all(&trash_files).await {
+        return OperationResult {
+            success: false,
+            message: format!("Could not create trash dir: {e}"),
+            logs: format!("mkdir failed: {e}"),
+            exit_code: None,
+        };
+    }
+    let src = std::path::Path::new(path);
+    let filename = src
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "appimage".into());
+    let dest = trash_files.join(format!("{}.{}", filename, now_ms_debris()));
+    match tokio::fs::rename(src, &dest).await {
+        Ok(_) => OperationResult {
+            success: true,
+            message: "AppImage moved to Trash.".into(),
+            logs: format!("moved {path} -> {}", dest.display()),
+            exit_code: Some(0),
+        },
+        Err(e) => OperationResult {
+            success: false,
+            message: format!("Could not move AppImage to Trash: {e}"),
+            logs: format!("rename failed: {e}"),
+            exit_code: None,
+        },
+    }
+}
+
+fn now_ms_debris() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
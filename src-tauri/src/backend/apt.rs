@@ -0,0 +1,104 @@
+//! APT backend: `apt`/`apt-mark`, run as root by `scope-helper`.
+
+use crate::helper::Request;
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::{self, Scanner};
+use crate::system::Runner;
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct AptBackend;
+
+/// `(program, args)` for an operation, shared by the plan summary and
+/// `scope-helper` so the preview always shows exactly what runs.
+pub(crate) fn command(operation: Operation, pkg: &str) -> Option<(&'static str, Vec<&str>)> {
+    Some(match operation {
+        Operation::Uninstall => ("apt", vec!["remove", "-y", pkg]),
+        Operation::Purge => ("apt", vec!["purge", "-y", pkg]),
+        Operation::Update => ("apt", vec!["install", "-y", pkg]),
+        Operation::Hold => ("apt-mark", vec!["hold", pkg]),
+        Operation::Unhold => ("apt-mark", vec!["unhold", pkg]),
+        Operation::Rollback => return None,
+    })
+}
+
+impl PackageBackend for AptBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Apt
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Scan,
+            Capability::CheckUpdates,
+            Capability::Uninstall,
+            Capability::Update,
+            Capability::Purge,
+            Capability::Hold,
+        ]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(scanner::apt::AptScanner::new(runner.clone()))]
+    }
+
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(scanner::apt::check_updates(runner.as_ref(), packages))
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            if command(operation, &pkg.package_id).is_none() {
+                return PlanDraft::unsupported(operation);
+            }
+            let id = &pkg.package_id;
+            let description = match operation {
+                Operation::Uninstall => format!("Remove the APT package '{id}' via apt."),
+                Operation::Purge => format!(
+                    "Remove the APT package '{id}' and its system-wide configuration files."
+                ),
+                Operation::Update => format!(
+                    "Update APT package '{id}' from {} to {}.",
+                    pkg.version,
+                    pkg.update_version.as_deref().unwrap_or("latest")
+                ),
+                Operation::Hold => format!("Hold '{id}' at {} so upgrades skip it.", pkg.version),
+                Operation::Unhold => format!("Release the hold on '{id}'."),
+                Operation::Rollback => return PlanDraft::unsupported(operation),
+            };
+            PlanDraft::new(
+                AuthMethod::Pkexec,
+                vec![PlanStep {
+                    description,
+                    command_summary: Request::new(operation, PackageSource::Apt, id).summary(),
+                }],
+            )
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            if command(plan.operation, &plan.package_id).is_none() {
+                return unsupported(plan);
+            }
+            let request = Request::new(plan.operation, PackageSource::Apt, &plan.package_id);
+            runner
+                .run_privileged(&request, plan.operation.timeout())
+                .await
+        })
+    }
+}
@@ -0,0 +1,307 @@
+//! Distrobox/Toolbox backend. Packages are changed with the container's own
+//! package manager (`<runtime> exec -u root`, which needs no host privileges),
+//! then the export is removed from the host.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::container::{self, ContainerTool, Export, ExportKind, PackageManager};
+use crate::scanner::Scanner;
+use crate::system::{which, CommandRunner, Runner};
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct ContainerBackend;
+
+impl PackageBackend for ContainerBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Container
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Scan,
+            Capability::CheckUpdates,
+            Capability::Uninstall,
+            Capability::Update,
+        ]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(container::ContainerScanner::new(runner.clone()))]
+    }
+
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(container::check_updates(runner.as_ref(), packages))
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            let Some(export) = container::load_export(Path::new(&pkg.package_id)) else {
+                return PlanDraft::blocked(
+                    "This file is no longer a container export.",
+                    "Blocked: this file is no longer a container export.",
+                );
+            };
+            match operation {
+                Operation::Uninstall => PlanDraft::new(
+                    AuthMethod::None,
+                    vec![
+                        PlanStep {
+                            description: format!(
+                                "Remove the package that provides '{}' inside the {} container '{}'.",
+                                export.target_path(),
+                                export.tool.label(),
+                                export.container
+                            ),
+                            command_summary: format!(
+                                "{} exec -u root {} <package manager> remove <owner of {}>",
+                                container::runtime(),
+                                export.container,
+                                export.target_path()
+                            ),
+                        },
+                        PlanStep {
+                            description: format!(
+                                "Unexport '{}' from the host.",
+                                export.command_name()
+                            ),
+                            command_summary: unexport_summary(&export),
+                        },
+                    ],
+                ),
+                Operation::Update => PlanDraft::new(
+                    AuthMethod::None,
+                    vec![PlanStep {
+                        description: format!(
+                            "Update the package providing '{}' inside container '{}' to {}.",
+                            pkg.name,
+                            export.container,
+                            pkg.update_version.as_deref().unwrap_or("latest")
+                        ),
+                        command_summary: format!(
+                            "{} exec -u root {} <package manager> upgrade <owner of {}>",
+                            container::runtime(),
+                            export.container,
+                            export.target_path()
+                        ),
+                    }],
+                ),
+                _ => PlanDraft::unsupported(operation),
+            }
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            match plan.operation {
+                Operation::Uninstall => container_remove(runner.as_ref(), &plan.package_id).await,
+                Operation::Update => container_update(runner.as_ref(), &plan.package_id).await,
+                _ => unsupported(plan),
+            }
+        })
+    }
+}
+
+fn unexport_summary(export: &Export) -> String {
+    match export.tool {
+        ContainerTool::Distrobox => format!(
+            "distrobox enter -n {} -- distrobox-export {} {} --delete",
+            export.container,
+            export_flag(export.kind),
+            export.target
+        ),
+        ContainerTool::Toolbox => format!("rm {}", export.host_path.display()),
+    }
+}
+
+fn export_flag(kind: ExportKind) -> &'static str {
+    match kind {
+        ExportKind::App => "--app",
+        ExportKind::Binary => "--bin",
+    }
+}
+
+/// Resolve the in-container package that owns a container export, starting
+/// the container first when it is stopped. Shared by the container uninstall
+/// and update flows; the error value is the failed result to report.
+async fn resolve_container_owner(
+    runner: &dyn CommandRunner,
+    export: &Export,
+) -> Result<(PackageManager, String), OperationResult> {
+    if !container::is_running(runner, &export.container).await {
+        let started = runner
+            .run_step(
+                container::runtime(),
+                &["start", &export.container],
+                Duration::from_secs(60),
+            )
+            .await;
+        if !started.success {
+            return Err(started);
+        }
+    }
+    let Some(pm) = container::detect_package_manager(runner, &export.container).await else {
+        return Err(OperationResult {
+            success: false,
+            message: format!(
+                "Could not detect a supported package manager in container '{}'.",
+                export.container
+            ),
+            logs: String::new(),
+            exit_code: None,
+        });
+    };
+    let path = export.target_path();
+    match container::owner_of(runner, &export.container, pm, &path).await {
+        Some((owner, _)) => Ok((pm, owner)),
+        None => Err(OperationResult {
+            success: false,
+            message: format!(
+                "No package inside '{}' owns {path}; nothing to change.",
+                export.container
+            ),
+            logs: String::new(),
+            exit_code: None,
+        }),
+    }
+}
+
+/// `<runtime> exec -u root <container> ...` argv for a package-manager call.
+/// Root inside a rootless container needs no host privileges.
+fn container_exec_args(container: &str, pm: PackageManager, pm_args: &[String]) -> Vec<String> {
+    let mut argv: Vec<String> = vec!["exec".into(), "-u".into(), "root".into()];
+    if pm == PackageManager::Apt {
+        argv.push("-e".into());
+        argv.push("DEBIAN_FRONTEND=noninteractive".into());
+    }
+    argv.push(container.to_string());
+    argv.extend(pm_args.iter().cloned());
+    argv
+}
+
+/// Remove the owning package inside the container, then unexport the app or
+/// binary from the host. An orphaned export (container gone) is only
+/// unexported.
+async fn container_remove(runner: &dyn CommandRunner, host_path: &str) -> OperationResult {
+    let Some(export) = container::load_export(Path::new(host_path)) else {
+        return OperationResult {
+            success: false,
+            message: "This file is no longer a container export.".into(),
+            logs: String::new(),
+            exit_code: None,
+        };
+    };
+
+    let mut logs = String::new();
+    if container::exists(runner, &export.container).await {
+        let (pm, owner) = match resolve_container_owner(runner, &export).await {
+            Ok(found) => found,
+            Err(res) => return res,
+        };
+        let argv = container_exec_args(&export.container, pm, &pm.remove_args(&owner));
+        let argv_refs: Vec<&str> = argv.iter().map(String::as_str).collect();
+        let res = runner
+            .run_step(
+                container::runtime(),
+                &argv_refs,
+                Operation::Uninstall.timeout(),
+            )
+            .await;
+        if !res.success {
+            return res;
+        }
+        logs.push_str(&res.logs);
+    }
+
+    let res = unexport(runner, &export).await;
+    OperationResult {
+        logs: format!("{logs}\n{}", res.logs),
+        ..res
+    }
+}
+
+async fn unexport(runner: &dyn CommandRunner, export: &Export) -> OperationResult {
+    if export.tool == ContainerTool::Distrobox
+        && which("distrobox")
+        && container::exists(runner, &export.container).await
+    {
+        let res = runner
+            .run_step(
+                "distrobox",
+                &[
+                    "enter",
+                    "-n",
+                    &export.container,
+                    "--",
+                    "distrobox-export",
+                    export_flag(export.kind),
+                    &export.target,
+                    "--delete",
+                ],
+                Operation::Uninstall.timeout(),
+            )
+            .await;
+        if res.success && !export.host_path.exists() {
+            return res;
+        }
+        // Fall through: make sure the host-side file is gone either way.
+    }
+    let host_path = export.host_path.to_string_lossy();
+    if let Some(res) = runner.dry_run_file_change("rm", &[&host_path]).await {
+        return res;
+    }
+    match tokio::fs::remove_file(&export.host_path).await {
+        Ok(_) => OperationResult {
+            success: true,
+            message: "Container app removed and unexported.".into(),
+            logs: format!("removed {}", export.host_path.display()),
+            exit_code: Some(0),
+        },
+        Err(e) => OperationResult {
+            success: false,
+            message: format!("Could not remove the exported file: {e}"),
+            logs: format!("remove failed: {e}"),
+            exit_code: None,
+        },
+    }
+}
+
+async fn container_update(runner: &dyn CommandRunner, host_path: &str) -> OperationResult {
+    let Some(export) = container::load_export(Path::new(host_path)) else {
+        return OperationResult {
+            success: false,
+            message: "This file is no longer a container export.".into(),
+            logs: String::new(),
+            exit_code: None,
+        };
+    };
+    let (pm, owner) = match resolve_container_owner(runner, &export).await {
+        Ok(found) => found,
+        Err(res) => return res,
+    };
+    let argv = container_exec_args(&export.container, pm, &pm.upgrade_args(&owner));
+    let argv_refs: Vec<&str> = argv.iter().map(String::as_str).collect();
+    runner
+        .run_step(
+            container::runtime(),
+            &argv_refs,
+            Operation::Update.timeout(),
+        )
+        .await
+}
@@ -0,0 +1,144 @@
+//! Extension backend (VS Code, GNOME Shell). Removal deletes the extension
+//! directory after the path guard; updates stay with the owning app.
+
+use std::time::Duration;
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::safety;
+use crate::scanner::extensions::{self, ExtensionHost};
+use crate::scanner::Scanner;
+use crate::system::{CommandRunner, Runner};
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct ExtensionBackend;
+
+impl PackageBackend for ExtensionBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Extension
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Scan, Capability::Uninstall]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(extensions::ExtensionScanner::new(runner.clone()))]
+    }
+
+    fn plan<'a>(
+        &'a self,
+        runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            if operation != Operation::Uninstall {
+                return PlanDraft::unsupported(operation);
+            }
+            let path = std::path::Path::new(&pkg.package_id);
+            let host = extensions::host_for(path, &runner.dirs());
+            let mut steps = Vec::new();
+            if host == Some(ExtensionHost::GnomeShell) && pkg.enabled == Some(true) {
+                // `extension_remove` disables the directory's UUID, not the
+                // display name.
+                if let Some(uuid) = path.file_name() {
+                    steps.push(PlanStep {
+                        description: format!("Disable the GNOME Shell extension '{}'.", pkg.name),
+                        command_summary: format!(
+                            "gnome-extensions disable {}",
+                            uuid.to_string_lossy()
+                        ),
+                    });
+                }
+            }
+            steps.push(PlanStep {
+                description: format!(
+                    "Delete the {} extension directory '{}'.",
+                    host.map(ExtensionHost::label).unwrap_or("unknown"),
+                    pkg.package_id
+                ),
+                command_summary: format!("rm -r {}", pkg.package_id),
+            });
+            if host == Some(ExtensionHost::VsCode) {
+                steps.push(PlanStep {
+                    description: format!("Remove '{}' from VS Code's extensions.json.", pkg.name),
+                    command_summary: "(edit extensions.json)".into(),
+                });
+            }
+            PlanDraft::new(AuthMethod::None, steps)
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            match plan.operation {
+                Operation::Uninstall => extension_remove(runner.as_ref(), &plan.package_id).await,
+                _ => unsupported(plan),
+            }
+        })
+    }
+}
+
+/// Delete an extension directory. The path guard runs again immediately
+/// before the recursive delete, independent of the earlier revalidation.
+async fn extension_remove(runner: &dyn CommandRunner, dir: &str) -> OperationResult {
+    let guard = safety::check_extension_dir(dir, &runner.dirs());
+    if guard.protected {
+        return OperationResult {
+            success: false,
+            message: guard.reason.unwrap_or_else(|| "Protected path.".into()),
+            logs: String::new(),
+            exit_code: None,
+        };
+    }
+    let path = std::path::Path::new(dir);
+    let host = extensions::host_for(path, &runner.dirs());
+    let mut logs = String::new();
+
+    if host == Some(ExtensionHost::GnomeShell) && runner.has_program("gnome-extensions") {
+        if let Some(uuid) = path.file_name().map(|n| n.to_string_lossy().to_string()) {
+            let res = runner
+                .run_step(
+                    "gnome-extensions",
+                    &["disable", &uuid],
+                    Duration::from_secs(20),
+                )
+                .await;
+            // A failed disable (e.g. already disabled) does not block removal.
+            logs.push_str(&res.logs);
+            logs.push('\n');
+        }
+    }
+
+    if let Some(mut res) = runner.dry_run_file_change("rm", &["-r", dir]).await {
+        res.logs = format!("{logs}{}", res.logs);
+        return res;
+    }
+    if let Err(e) = tokio::fs::remove_dir_all(path).await {
+        return OperationResult {
+            success: false,
+            message: format!("Could not delete the extension directory: {e}"),
+            logs: format!("{logs}remove_dir_all failed: {e}"),
+            exit_code: None,
+        };
+    }
+    logs.push_str(&format!("deleted {dir}\n"));
+
+    if host == Some(ExtensionHost::VsCode) {
+        if let Err(e) = extensions::forget_vscode_extension(path) {
+            logs.push_str(&format!("could not update extensions.json: {e}\n"));
+        }
+    }
+    OperationResult {
+        success: true,
+        message: "Extension removed.".into(),
+        logs,
+        exit_code: Some(0),
+    }
+}
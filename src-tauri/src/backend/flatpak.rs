@@ -0,0 +1,146 @@
+//! Flatpak backend. User installations run as the user; system installations
+//! (and ones of unknown scope) are changed as root by `scope-helper`.
+
+use crate::helper::Request;
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstallScope, InstalledPackage, PackageSource};
+use crate::scanner::{self, Scanner};
+use crate::system::Runner;
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct FlatpakBackend;
+
+fn auth_and_flag(scope: Option<InstallScope>) -> (AuthMethod, &'static str) {
+    match scope {
+        Some(InstallScope::User) => (AuthMethod::None, "--user"),
+        Some(InstallScope::System) | None => (AuthMethod::Pkexec, "--system"),
+    }
+}
+
+/// `flatpak` arguments for an operation, shared by the plan summary, apply
+/// and `scope-helper`.
+pub(crate) fn args(
+    operation: Operation,
+    scope_flag: &'static str,
+    app_id: &str,
+) -> Option<Vec<String>> {
+    let argv: &[&str] = match operation {
+        Operation::Uninstall => &["uninstall", "-y", scope_flag],
+        Operation::Purge => &["uninstall", "-y", "--delete-data", scope_flag],
+        Operation::Update => &["update", "-y", scope_flag],
+        // A mask stops `flatpak update` from touching the app.
+        Operation::Hold => &["mask", scope_flag],
+        Operation::Unhold => &["mask", "--remove", scope_flag],
+        Operation::Rollback => return None,
+    };
+    let mut argv: Vec<String> = argv.iter().map(|a| a.to_string()).collect();
+    argv.push(app_id.to_string());
+    Some(argv)
+}
+
+impl PackageBackend for FlatpakBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Flatpak
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Scan,
+            Capability::CheckUpdates,
+            Capability::Uninstall,
+            Capability::Update,
+            Capability::Purge,
+            Capability::Hold,
+        ]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(scanner::flatpak::FlatpakScanner::new(
+            runner.clone(),
+        ))]
+    }
+
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(scanner::flatpak::check_updates(runner.as_ref(), packages))
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            let (auth, scope_flag) = auth_and_flag(pkg.install_scope);
+            let Some(argv) = args(operation, scope_flag, &pkg.package_id) else {
+                return PlanDraft::unsupported(operation);
+            };
+            let id = &pkg.package_id;
+            let where_label = match pkg.install_scope {
+                Some(InstallScope::User) => "user",
+                Some(InstallScope::System) => "system",
+                None => "system (best-effort)",
+            };
+            let description = match operation {
+                Operation::Uninstall => {
+                    format!("Uninstall the Flatpak '{id}' ({where_label} installation).")
+                }
+                Operation::Purge => format!(
+                    "Uninstall the Flatpak '{id}' ({where_label} installation) and delete its \
+                     data in ~/.var/app."
+                ),
+                Operation::Update => format!(
+                    "Update Flatpak '{id}' to {}.",
+                    pkg.update_version.as_deref().unwrap_or("latest")
+                ),
+                Operation::Hold => format!("Mask '{id}' so updates skip it."),
+                Operation::Unhold => format!("Remove the update mask on '{id}'."),
+                Operation::Rollback => return PlanDraft::unsupported(operation),
+            };
+            let command_summary = match auth {
+                AuthMethod::Pkexec => Request::new(operation, PackageSource::Flatpak, id).summary(),
+                AuthMethod::None => format!("flatpak {}", argv.join(" ")),
+            };
+            PlanDraft::new(
+                auth,
+                vec![PlanStep {
+                    description,
+                    command_summary,
+                }],
+            )
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            let (auth, scope_flag) = auth_and_flag(plan.install_scope);
+            let Some(argv) = args(plan.operation, scope_flag, &plan.package_id) else {
+                return unsupported(plan);
+            };
+            match auth {
+                AuthMethod::Pkexec => {
+                    let request =
+                        Request::new(plan.operation, PackageSource::Flatpak, &plan.package_id);
+                    runner
+                        .run_privileged(&request, plan.operation.timeout())
+                        .await
+                }
+                AuthMethod::None => {
+                    let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+                    runner
+                        .run_step("flatpak", &argv, plan.operation.timeout())
+                        .await
+                }
+            }
+        })
+    }
+}
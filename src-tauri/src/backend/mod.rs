@@ -0,0 +1,261 @@
+//! Per-source package backends.
+//!
+//! Every package source is one [`PackageBackend`] that declares what it can do
+//! ([`Capability`]) and implements those actions. [`registry`] is the single
+//! list of backends: scanning, update checks, previews and applies all
+//! dispatch through it instead of matching on [`PackageSource`].
+//!
+//! Backends only build plan steps and run commands. The shared flow around
+//! them — safety checks, `PlanStore`, revalidation — stays in
+//! [`crate::operations`], so a backend can never skip it.
+
+pub mod appimage;
+pub mod apt;
+pub mod container;
+pub mod extension;
+pub mod flatpak;
+pub mod plugin;
+pub mod snap;
+pub mod steam;
+
+use serde::{Deserialize, Serialize};
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::Scanner;
+use crate::system::Runner;
+
+pub use crate::system::BoxFuture;
+
+/// Something a backend can do. Serialized for the frontend, which uses it to
+/// decide which actions to offer for a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// List installed packages.
+    Scan,
+    /// Mark packages that have an update available.
+    CheckUpdates,
+    /// Remove a package.
+    Uninstall,
+    /// Update a package to its available version.
+    Update,
+    /// Remove a package together with its configuration or user data.
+    Purge,
+    /// Pin a package at its current version (and release that pin).
+    Hold,
+    /// Go back to the previously installed version.
+    Rollback,
+}
+
+/// Source-specific part of an [`OperationPlan`], built by
+/// [`PackageBackend::plan`]. [`crate::operations::preview`] fills in the rest.
+#[derive(Debug, Clone)]
+pub struct PlanDraft {
+    pub auth: AuthMethod,
+    pub requires_auth: bool,
+    pub steps: Vec<PlanStep>,
+    /// Overrides the default target version shown for the operation.
+    pub target_version: Option<String>,
+    /// Set when the backend refuses this package; the plan is then marked
+    /// protected with this reason and can never be applied.
+    pub blocked: Option<String>,
+}
+
+impl PlanDraft {
+    pub fn new(auth: AuthMethod, steps: Vec<PlanStep>) -> Self {
+        Self {
+            auth,
+            requires_auth: matches!(auth, AuthMethod::Pkexec),
+            steps,
+            target_version: None,
+            blocked: None,
+        }
+    }
+
+    /// A plan that cannot be applied, with one explanatory step.
+    pub fn blocked(reason: impl Into<String>, step: impl Into<String>) -> Self {
+        Self {
+            auth: AuthMethod::None,
+            requires_auth: false,
+            steps: vec![PlanStep {
+                description: step.into(),
+                command_summary: "(no command)".into(),
+            }],
+            target_version: None,
+            blocked: Some(reason.into()),
+        }
+    }
+
+    /// Fallback for operations outside a backend's capabilities. Unreachable
+    /// through [`crate::operations::preview`], which checks them first.
+    pub fn unsupported(operation: Operation) -> Self {
+        Self::blocked(
+            format!("'{}' is not supported for this package.", operation.id()),
+            "Blocked: this action is not supported here.",
+        )
+    }
+}
+
+/// One package source: how to scan it, check it for updates, and plan and
+/// apply operations on its packages.
+pub trait PackageBackend: Send + Sync {
+    /// Source this backend handles.
+    fn source(&self) -> PackageSource;
+
+    /// Everything this source can do.
+    fn capabilities(&self) -> &'static [Capability];
+
+    /// Capabilities for one package. Defaults to the source-wide list;
+    /// backends narrow it when some of their packages support less.
+    fn package_capabilities(&self, pkg: &InstalledPackage) -> Vec<Capability> {
+        let _ = pkg;
+        self.capabilities().to_vec()
+    }
+
+    /// Scanners that list this source's packages (plugins have several).
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>>;
+
+    /// Mark packages from this source's scan that have updates available.
+    /// An error when the source could not be asked, so its packages' earlier
+    /// annotations are kept rather than cleared.
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        let _ = (runner, packages);
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Plan an operation on a package. Only called for unprotected packages
+    /// whose capabilities include the operation's.
+    fn plan<'a>(
+        &'a self,
+        runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft>;
+
+    /// Run a plan issued by [`PackageBackend::plan`], capturing logs.
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult>;
+}
+
+/// All backends, in the order sources are listed.
+pub fn registry() -> &'static [&'static dyn PackageBackend] {
+    static REGISTRY: &[&dyn PackageBackend] = &[
+        &apt::AptBackend,
+        &snap::SnapBackend,
+        &flatpak::FlatpakBackend,
+        &appimage::AppImageBackend,
+        &container::ContainerBackend,
+        &steam::SteamBackend,
+        &extension::ExtensionBackend,
+        &plugin::PluginBackend,
+    ];
+    REGISTRY
+}
+
+/// The backend for a source. Every [`PackageSource`] has exactly one.
+pub fn for_source(source: PackageSource) -> &'static dyn PackageBackend {
+    *registry()
+        .iter()
+        .find(|b| b.source() == source)
+        .expect("every package source has a registered backend")
+}
+
+/// Capabilities of one package, as reported to the frontend.
+pub fn capabilities_for(pkg: &InstalledPackage) -> Vec<Capability> {
+    for_source(pkg.source).package_capabilities(pkg)
+}
+
+/// Result for an operation a backend was asked to run but cannot.
+pub fn unsupported(plan: &OperationPlan) -> OperationResult {
+    OperationResult {
+        success: false,
+        message: format!(
+            "{} packages do not support '{}'.",
+            plan.source.label(),
+            plan.operation.id()
+        ),
+        logs: String::new(),
+        exit_code: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_source_has_one_backend() {
+        let sources = [
+            PackageSource::Apt,
+            PackageSource::Snap,
+            PackageSource::Flatpak,
+            PackageSource::AppImage,
+            PackageSource::Container,
+            PackageSource::Steam,
+            PackageSource::Extension,
+            PackageSource::Plugin,
+        ];
+        assert_eq!(registry().len(), sources.len());
+        for source in sources {
+            let backend = for_source(source);
+            assert_eq!(backend.source(), source);
+            assert!(backend.capabilities().contains(&Capability::Scan));
+        }
+    }
+
+    #[tokio::test]
+    async fn preview_rejects_operations_outside_capabilities() {
+        let mut pkg = InstalledPackage::new(PackageSource::AppImage, "/home/u/Apps/Tool.AppImage");
+        pkg.name = "Tool".into();
+        assert_eq!(Operation::Unhold.capability(), Capability::Hold);
+        let runner = crate::system::default_runner();
+        let err = crate::operations::preview(&runner, &pkg, Operation::Hold)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot be held"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn custom_steam_tools_get_a_blocked_plan() {
+        let pkg = InstalledPackage::new(
+            PackageSource::Steam,
+            "/home/u/.steam/root/compatibilitytools.d/GE-Proton9-1",
+        );
+        let runner = crate::system::default_runner();
+        let draft = for_source(PackageSource::Steam)
+            .plan(&runner, &pkg, Operation::Uninstall)
+            .await;
+        assert!(draft.blocked.is_some());
+    }
+
+    #[tokio::test]
+    async fn gnome_extension_plans_disable_the_uuid() {
+        let Some((_, root)) =
+            crate::scanner::extensions::extension_roots(&crate::system::Dirs::Host)
+                .into_iter()
+                .find(|(host, _)| *host == crate::scanner::extensions::ExtensionHost::GnomeShell)
+        else {
+            return;
+        };
+        let dir = root.join("dash-to-dock@micxgx.gmail.com");
+        let mut pkg = InstalledPackage::new(PackageSource::Extension, dir.to_string_lossy());
+        pkg.name = "Dash to Dock".into();
+        pkg.enabled = Some(true);
+        let runner = crate::system::default_runner();
+        let draft = for_source(PackageSource::Extension)
+            .plan(&runner, &pkg, Operation::Uninstall)
+            .await;
+        assert_eq!(
+            draft.steps[0].command_summary,
+            "gnome-extensions disable dash-to-dock@micxgx.gmail.com"
+        );
+    }
+}
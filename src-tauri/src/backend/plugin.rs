@@ -0,0 +1,91 @@
+//! External plugin backend. Plans and applies are delegated to the plugin
+//! that reported the package (see [`crate::scanner::plugin`]).
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::plugin::{self, PluginScanner};
+use crate::scanner::Scanner;
+use crate::system::Runner;
+
+use super::{BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct PluginBackend;
+
+impl PackageBackend for PluginBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Plugin
+    }
+
+    /// Protocol v1 only has `uninstall` and `update`.
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Scan,
+            Capability::CheckUpdates,
+            Capability::Uninstall,
+            Capability::Update,
+        ]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        plugin::discover(&runner.dirs())
+            .into_iter()
+            .map(|p| Box::new(PluginScanner::new(runner.clone(), p)) as Box<dyn Scanner>)
+            .collect()
+    }
+
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(plugin::check_updates(runner.as_ref(), packages))
+    }
+
+    /// The plan comes from the plugin's own `preview` answer. A plugin that
+    /// cannot preview (crash, timeout, bad JSON) yields a blocked plan, so
+    /// nothing is ever applied without a plugin-confirmed plan.
+    fn plan<'a>(
+        &'a self,
+        runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            match plugin::preview(runner.as_ref(), &pkg.package_id, operation).await {
+                Ok(p) => PlanDraft {
+                    // The plugin escalates on its own (if at all); Scope never does.
+                    auth: AuthMethod::None,
+                    requires_auth: p.requires_auth,
+                    steps: p.steps,
+                    target_version: p.target_version,
+                    blocked: p.protected.then(|| {
+                        p.protection_reason
+                            .unwrap_or_else(|| "Protected by plugin.".into())
+                    }),
+                },
+                Err(e) => PlanDraft {
+                    auth: AuthMethod::None,
+                    requires_auth: false,
+                    steps: vec![PlanStep {
+                        description: "Blocked: the plugin could not build a plan.".into(),
+                        command_summary: "(no command — plugin error)".into(),
+                    }],
+                    target_version: None,
+                    blocked: Some(e.to_string()),
+                },
+            }
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(plugin::apply(
+            runner.as_ref(),
+            &plan.package_id,
+            plan.operation,
+        ))
+    }
+}
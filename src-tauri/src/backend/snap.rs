@@ -0,0 +1,105 @@
+//! Snap backend: `snap remove`/`refresh`/`revert`, run as root by
+//! `scope-helper`.
+
+use crate::helper::Request;
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::{self, Scanner};
+use crate::system::Runner;
+
+use super::{BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct SnapBackend;
+
+/// `snap` arguments for an operation, shared by the plan summary and
+/// `scope-helper`.
+pub(crate) fn args(operation: Operation, name: &str) -> Vec<&str> {
+    match operation {
+        Operation::Uninstall => vec!["remove", name],
+        // Skips the automatic snapshot of the snap's data.
+        Operation::Purge => vec!["remove", "--purge", name],
+        Operation::Update => vec!["refresh", name],
+        Operation::Hold => vec!["refresh", "--hold", name],
+        Operation::Unhold => vec!["refresh", "--unhold", name],
+        Operation::Rollback => vec!["revert", name],
+    }
+}
+
+impl PackageBackend for SnapBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Snap
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Scan,
+            Capability::CheckUpdates,
+            Capability::Uninstall,
+            Capability::Update,
+            Capability::Purge,
+            Capability::Hold,
+            Capability::Rollback,
+        ]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(scanner::snap::SnapScanner::new(runner.clone()))]
+    }
+
+    fn check_updates<'a>(
+        &'a self,
+        runner: &'a Runner,
+        packages: &'a mut [InstalledPackage],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(scanner::snap::check_updates(runner.as_ref(), packages))
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            let id = &pkg.package_id;
+            let description = match operation {
+                Operation::Uninstall => format!("Remove the Snap '{id}' via snap remove."),
+                Operation::Purge => {
+                    format!("Remove the Snap '{id}' and its data without keeping a snapshot.")
+                }
+                Operation::Update => format!(
+                    "Update Snap '{id}' to {}.",
+                    pkg.update_version.as_deref().unwrap_or("latest")
+                ),
+                Operation::Hold => format!("Hold '{id}' so automatic refreshes skip it."),
+                Operation::Unhold => format!("Let '{id}' refresh automatically again."),
+                Operation::Rollback => {
+                    format!(
+                        "Revert '{id}' to the revision installed before {}.",
+                        pkg.version
+                    )
+                }
+            };
+            PlanDraft::new(
+                AuthMethod::Pkexec,
+                vec![PlanStep {
+                    description,
+                    command_summary: Request::new(operation, PackageSource::Snap, id).summary(),
+                }],
+            )
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            let request = Request::new(plan.operation, PackageSource::Snap, &plan.package_id);
+            runner
+                .run_privileged(&request, plan.operation.timeout())
+                .await
+        })
+    }
+}
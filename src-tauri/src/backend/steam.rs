@@ -0,0 +1,111 @@
+//! Steam backend. Steam owns its library, so removal hands off to its own
+//! uninstall dialog and updates are left to Steam.
+
+use std::time::Duration;
+
+use crate::operations::{AuthMethod, Operation, OperationPlan, OperationResult, PlanStep};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scanner::steam;
+use crate::scanner::Scanner;
+use crate::system::{which, CommandRunner, Runner};
+
+use super::{unsupported, BoxFuture, Capability, PackageBackend, PlanDraft};
+
+pub struct SteamBackend;
+
+impl PackageBackend for SteamBackend {
+    fn source(&self) -> PackageSource {
+        PackageSource::Steam
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Scan, Capability::Uninstall]
+    }
+
+    fn scanners(&self, runner: &Runner) -> Vec<Box<dyn Scanner>> {
+        vec![Box::new(steam::SteamScanner::new(runner.dirs()))]
+    }
+
+    fn plan<'a>(
+        &'a self,
+        _runner: &'a Runner,
+        pkg: &'a InstalledPackage,
+        operation: Operation,
+    ) -> BoxFuture<'a, PlanDraft> {
+        Box::pin(async move {
+            if operation != Operation::Uninstall {
+                return PlanDraft::unsupported(operation);
+            }
+            if steam::is_custom_tool(&pkg.package_id) {
+                return PlanDraft::blocked(
+                    "Scope does not delete custom compatibility tools.",
+                    format!(
+                        "Manual step: close Steam, then delete '{}'.",
+                        pkg.package_id
+                    ),
+                );
+            }
+            PlanDraft::new(
+                AuthMethod::None,
+                vec![PlanStep {
+                    description: format!(
+                        "Hand off to Steam: it asks for confirmation, then removes '{}' and its files.",
+                        pkg.display_name.as_deref().unwrap_or(&pkg.name)
+                    ),
+                    command_summary: format!("xdg-open steam://uninstall/{}", pkg.package_id),
+                }],
+            )
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        runner: &'a Runner,
+        plan: &'a OperationPlan,
+    ) -> BoxFuture<'a, OperationResult> {
+        Box::pin(async move {
+            match plan.operation {
+                Operation::Uninstall => steam_uninstall(runner.as_ref(), &plan.package_id).await,
+                _ => unsupported(plan),
+            }
+        })
+    }
+}
+
+/// Steam owns its library: open its uninstall dialog instead of deleting files.
+async fn steam_uninstall(runner: &dyn CommandRunner, package_id: &str) -> OperationResult {
+    if steam::is_custom_tool(package_id) {
+        return OperationResult {
+            success: false,
+            message: format!(
+                "Custom compatibility tools must be removed manually: close Steam and delete {package_id}."
+            ),
+            logs: String::new(),
+            exit_code: None,
+        };
+    }
+    if !which("xdg-open") {
+        return OperationResult {
+            success: false,
+            message: format!(
+                "xdg-open is not available. Open Steam and uninstall app {package_id} from your library."
+            ),
+            logs: String::new(),
+            exit_code: None,
+        };
+    }
+    let url = format!("steam://uninstall/{package_id}");
+    let res = runner
+        .run_step("xdg-open", &[&url], Duration::from_secs(20))
+        .await;
+    if res.success {
+        OperationResult {
+            message:
+                "Steam's uninstall dialog was opened. Confirm there to finish removing the game."
+                    .into(),
+            ..res
+        }
+    } else {
+        res
+    }
+}
@@ -0,0 +1,6 @@
+//! `scope-cli`: list, search, inspect, remove and update packages without the
+//! GUI, e.g. over SSH or from scripts. See [`scope_lib::cli`].
+
+fn main() {
+    std::process::exit(scope_lib::cli::run())
+}
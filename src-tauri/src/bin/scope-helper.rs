@@ -0,0 +1,6 @@
+//! `scope-helper`: the privileged half of Scope, run through `pkexec` to
+//! remove, update or hold one package. See [`scope_lib::helper`].
+
+fn main() {
+    std::process::exit(scope_lib::helper::main())
+}
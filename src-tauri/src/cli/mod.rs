@@ -0,0 +1,616 @@
+//! Headless command-line front end, built as the `scope-cli` binary.
+//!
+//! Strategy: the CLI is just another caller of the library the GUI uses. It
+//! scans with [`scan_all`], checks for updates with [`check_packages`] only
+//! where the command needs them, builds plans with [`operations::preview`] (which
+//! runs the [`crate::safety`] checks), asks for confirmation, then revalidates
+//! against a fresh scan and applies exactly like the `apply_operation` command.
+//! Plans never leave the process, so there is no plan store. Arguments are
+//! parsed by hand; the grammar is small enough not to need a parser crate.
+
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::backend;
+use crate::operations::{self, Operation, OperationPlan, OperationResult};
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::{scan_all, ScanAvailability};
+use crate::system::Runner;
+use crate::updates::check_packages;
+
+const USAGE: &str = "\
+Usage: scope-cli <command> [options]
+
+Commands:
+  list                List installed packages
+  search <query>      List installed packages matching a query
+  outdated            List packages with an available update
+  info <package>      Show details of one package
+  remove <package>    Uninstall a package
+  update <package>    Update a package
+
+<package> is a key as shown by `list` (e.g. apt:vlc) or an unambiguous name.
+
+Options:
+  --source <id>       Only apt, snap, flatpak, appimage, container, steam,
+                      extension or plugin packages
+  --kind <kind>       Only gui, cli or unknown packages
+  --json              Print JSON instead of text
+  -y, --yes           Apply without asking for confirmation
+  -h, --help          Print this help
+  -V, --version       Print the version
+";
+
+/// Exit code for failed or declined operations and runtime errors.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid command lines.
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Search(String),
+    Outdated,
+    Info(String),
+    /// `remove` or `update` of the named package.
+    Change(Operation, String),
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    command: Command,
+    json: bool,
+    yes: bool,
+    source: Option<PackageSource>,
+    kind: Option<AppKind>,
+}
+
+/// What `remove`/`update` report with `--json`. `result` is `null` when the
+/// plan was blocked or not confirmed.
+#[derive(Serialize)]
+struct ChangeReport<'a> {
+    plan: &'a OperationPlan,
+    result: Option<&'a OperationResult>,
+}
+
+/// Run the CLI with the process arguments and return its exit code.
+pub fn run() -> i32 {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("scope-cli: {e}\n\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("scope-cli: cannot start the async runtime: {e}");
+            return EXIT_FAILURE;
+        }
+    };
+    match runtime.block_on(execute(&args, &crate::system::default_runner())) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("scope-cli: {e:#}");
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn parse_args(argv: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut json = false;
+    let mut yes = false;
+    let mut help = false;
+    let mut version = false;
+    let mut source = None;
+    let mut kind = None;
+    let mut positional = Vec::new();
+
+    let mut argv = argv.into_iter();
+    while let Some(arg) = argv.next() {
+        // Long options take their value either inline (`--source=apt`) or as
+        // the next argument.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline
+                .map(str::to_string)
+                .or_else(|| argv.next())
+                .ok_or_else(|| format!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--json" => json = true,
+            "-y" | "--yes" => yes = true,
+            "-h" | "--help" => help = true,
+            "-V" | "--version" => version = true,
+            "--source" => {
+                let v = value()?;
+                source = Some(PackageSource::from_id(&v).ok_or(format!("unknown source '{v}'"))?);
+            }
+            "--kind" => {
+                let v = value()?;
+                kind = Some(AppKind::from_id(&v).ok_or(format!("unknown kind '{v}'"))?);
+            }
+            f if f.starts_with('-') && f.len() > 1 => return Err(format!("unknown option '{f}'")),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let name = positional.next();
+    let mut operand = |what: &str| {
+        positional
+            .next()
+            .ok_or_else(|| format!("{} needs {what}", name.as_deref().unwrap_or_default()))
+    };
+    let command = match name.as_deref() {
+        _ if help => Command::Help,
+        _ if version => Command::Version,
+        None => return Err("missing command".into()),
+        Some("list") => Command::List,
+        Some("search") => Command::Search(operand("a query")?),
+        Some("outdated") => Command::Outdated,
+        Some("info") => Command::Info(operand("a package")?),
+        Some("remove") => Command::Change(Operation::Uninstall, operand("a package")?),
+        Some("update") => Command::Change(Operation::Update, operand("a package")?),
+        Some(other) => return Err(format!("unknown command '{other}'")),
+    };
+    if let Some(extra) = positional.next() {
+        if !matches!(command, Command::Help | Command::Version) {
+            return Err(format!("unexpected argument '{extra}'"));
+        }
+    }
+
+    Ok(Args {
+        command,
+        json,
+        yes,
+        source,
+        kind,
+    })
+}
+
+async fn execute(args: &Args, runner: &Runner) -> Result<i32> {
+    match &args.command {
+        Command::Help => print!("{USAGE}"),
+        Command::Version => println!("scope-cli {}", env!("CARGO_PKG_VERSION")),
+        Command::List => {
+            let packages = scan(runner, false).await;
+            print_list(args, select(args, &packages, ""), false)?;
+        }
+        Command::Search(query) => {
+            let packages = scan(runner, false).await;
+            let query = query.trim().to_lowercase();
+            print_list(args, select(args, &packages, &query), false)?;
+        }
+        Command::Outdated => {
+            let packages = scan(runner, true).await;
+            let mut outdated = select(args, &packages, "");
+            outdated.retain(|p| p.has_update);
+            print_list(args, outdated, true)?;
+        }
+        Command::Info(query) => {
+            let packages = scan(runner, true).await;
+            print_info(args, resolve(&packages, query)?)?;
+        }
+        Command::Change(operation, query) => {
+            let packages = scan(runner, *operation == Operation::Update).await;
+            let pkg = resolve(&packages, query)?;
+            return change(args, runner, pkg, *operation).await;
+        }
+    }
+    Ok(0)
+}
+
+/// Full scan, followed by an update check when `updates` is set, with
+/// per-source failures reported on stderr.
+async fn scan(runner: &Runner, updates: bool) -> Vec<InstalledPackage> {
+    let (mut packages, availability) = scan_all(runner).await;
+    let failed = if updates {
+        check_packages(runner, &mut packages).await
+    } else {
+        HashMap::new()
+    };
+    for warning in scan_warnings(&availability) {
+        eprintln!("scope-cli: warning: {warning}");
+    }
+    for (source, error) in failed {
+        eprintln!(
+            "scope-cli: warning: could not check {} for updates: {error}",
+            source.id()
+        );
+    }
+    packages
+}
+
+fn scan_warnings(availability: &ScanAvailability) -> Vec<String> {
+    let sources = [
+        ("apt", &availability.apt_error),
+        ("snap", &availability.snap_error),
+        ("flatpak", &availability.flatpak_error),
+        ("container", &availability.container_error),
+        ("steam", &availability.steam_error),
+        ("extension", &availability.extension_error),
+    ];
+    let plugins = availability
+        .plugins
+        .iter()
+        .map(|p| (p.name.as_str(), &p.error));
+    sources
+        .into_iter()
+        .chain(plugins)
+        .filter_map(|(name, error)| error.as_ref().map(|e| format!("{name}: {e}")))
+        .collect()
+}
+
+/// Packages passing the `--source`/`--kind` filters and the (lowercased)
+/// search query, in scan order.
+fn select<'a>(
+    args: &Args,
+    packages: &'a [InstalledPackage],
+    query: &str,
+) -> Vec<&'a InstalledPackage> {
+    packages
+        .iter()
+        .filter(|p| args.source.is_none() || args.source == Some(p.source))
+        .filter(|p| args.kind.is_none() || args.kind == Some(p.app_kind))
+        .filter(|p| p.matches_query(query))
+        .collect()
+}
+
+/// Find the package a command line names: an exact key first, then a unique
+/// package id, name or display name (case-insensitive).
+fn resolve<'a>(packages: &'a [InstalledPackage], query: &str) -> Result<&'a InstalledPackage> {
+    if let Some(pkg) = packages.iter().find(|p| p.key == query) {
+        return Ok(pkg);
+    }
+    let lowered = query.to_lowercase();
+    let matches: Vec<&InstalledPackage> = packages
+        .iter()
+        .filter(|p| {
+            p.package_id == query
+                || p.name.to_lowercase() == lowered
+                || p.display_name.as_deref().map(str::to_lowercase) == Some(lowered.clone())
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => anyhow::bail!("no installed package matches '{query}'"),
+        [pkg] => Ok(pkg),
+        many => anyhow::bail!(
+            "'{query}' matches several packages; use one of: {}",
+            many.iter()
+                .map(|p| p.key.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Preview, confirm, revalidate and apply one operation.
+async fn change(
+    args: &Args,
+    runner: &Runner,
+    pkg: &InstalledPackage,
+    operation: Operation,
+) -> Result<i32> {
+    let plan = operations::preview(runner, pkg, operation).await?;
+    let report = |result: Option<&OperationResult>| -> Result<()> {
+        if args.json {
+            print_json(&ChangeReport {
+                plan: &plan,
+                result,
+            })?;
+        }
+        Ok(())
+    };
+
+    if plan.protected {
+        report(None)?;
+        eprintln!(
+            "scope-cli: {} is protected: {}",
+            plan.display_name,
+            plan.protection_reason.as_deref().unwrap_or("protected")
+        );
+        return Ok(EXIT_FAILURE);
+    }
+
+    // The plan goes to stdout in text mode; with --json it is part of the
+    // final report, so the copy shown for confirmation goes to stderr.
+    if args.json {
+        if !args.yes {
+            eprint!("{}", describe_plan(&plan));
+        }
+    } else {
+        print!("{}", describe_plan(&plan));
+    }
+    if !args.yes && !confirm()? {
+        report(None)?;
+        eprintln!("scope-cli: not applied");
+        return Ok(EXIT_FAILURE);
+    }
+
+    let (mut fresh, _) = scan_all(runner).await;
+    if operation == Operation::Update {
+        if let Some(error) = check_packages(runner, &mut fresh)
+            .await
+            .remove(&plan.source)
+        {
+            anyhow::bail!("could not check {} for updates: {error}", plan.source.id());
+        }
+    }
+    operations::revalidate(runner, &plan, &fresh)?;
+    operations::revalidate_dependents(runner, &plan, &fresh).await?;
+    let result = operations::apply(runner, &plan).await;
+
+    report(Some(&result))?;
+    if !args.json {
+        println!("{}", result.message);
+        if !result.success {
+            eprintln!("{}", result.logs);
+        }
+    }
+    Ok(if result.success { 0 } else { EXIT_FAILURE })
+}
+
+/// Ask on the terminal. Without one there is nobody to ask, so the answer is
+/// no and scripts have to pass `--yes`.
+fn confirm() -> Result<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        eprintln!("scope-cli: no terminal to confirm on; pass --yes to apply");
+        return Ok(false);
+    }
+    eprint!("Proceed? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn describe_plan(plan: &OperationPlan) -> String {
+    let verb = match plan.operation {
+        Operation::Uninstall => "Remove",
+        Operation::Update => "Update",
+        Operation::Purge => "Purge",
+        Operation::Hold => "Hold",
+        Operation::Unhold => "Release",
+        Operation::Rollback => "Roll back",
+    };
+    let mut out = format!(
+        "{verb} {} ({}) {}",
+        plan.display_name,
+        plan.source.label(),
+        plan.current_version
+    );
+    if !plan.target_version.is_empty() {
+        out.push_str(&format!(" -> {}", plan.target_version));
+    }
+    out.push('\n');
+    for step in &plan.steps {
+        out.push_str(&format!(
+            "  - {}\n      $ {}\n",
+            step.description, step.command_summary
+        ));
+    }
+    for warning in &plan.warnings {
+        out.push_str(&format!("Warning: {warning}\n"));
+    }
+    if plan.requires_auth {
+        out.push_str("Administrator authentication will be requested (pkexec).\n");
+    }
+    out
+}
+
+fn print_list(args: &Args, packages: Vec<&InstalledPackage>, with_update: bool) -> Result<()> {
+    if args.json {
+        return print_json(&packages);
+    }
+    if packages.is_empty() {
+        eprintln!("No matching packages.");
+        return Ok(());
+    }
+    let mut header = vec!["NAME", "VERSION"];
+    if with_update {
+        header.push("AVAILABLE");
+    }
+    header.extend(["SOURCE", "KEY"]);
+    let rows = packages
+        .iter()
+        .map(|p| {
+            let mut version = p.version.clone();
+            if p.held {
+                version.push_str(" (held)");
+            }
+            let mut row = vec![label(p).to_string(), version];
+            if with_update {
+                row.push(p.update_version.clone().unwrap_or_else(|| "yes".into()));
+            }
+            row.extend([p.source.id().to_string(), p.key.clone()]);
+            row
+        })
+        .collect();
+    print_table(&header, rows);
+    Ok(())
+}
+
+fn print_info(args: &Args, pkg: &InstalledPackage) -> Result<()> {
+    if args.json {
+        return print_json(pkg);
+    }
+    let capabilities: Vec<String> = backend::capabilities_for(pkg)
+        .into_iter()
+        .filter_map(|c| serde_json::to_value(c).ok())
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect();
+    let update = match (pkg.has_update, &pkg.update_version) {
+        (true, Some(v)) => v.clone(),
+        (true, None) => "available".into(),
+        (false, _) => "none".into(),
+    };
+    let mut fields = vec![
+        ("Name", label(pkg).to_string()),
+        ("Key", pkg.key.clone()),
+        ("Source", pkg.source.label().to_string()),
+        ("Package id", pkg.package_id.clone()),
+    ];
+    if let Some(scope) = pkg.install_scope {
+        fields.push(("Scope", scope.id().to_string()));
+    }
+    fields.extend([
+        ("Version", pkg.version.clone()),
+        ("Update", update),
+        ("Held", if pkg.held { "yes" } else { "no" }.to_string()),
+        ("Kind", format!("{:?}", pkg.app_kind).to_lowercase()),
+    ]);
+    if pkg.size_bytes > 0 {
+        fields.push(("Size", human_size(pkg.size_bytes)));
+    }
+    if let Some(categories) = &pkg.categories {
+        fields.push(("Categories", categories.clone()));
+    }
+    if let Some(description) = &pkg.description {
+        fields.push(("Description", description.clone()));
+    }
+    fields.push(("Supports", capabilities.join(", ")));
+    for (field, value) in fields {
+        println!("{:<12} {value}", format!("{field}:"));
+    }
+    Ok(())
+}
+
+fn label(pkg: &InstalledPackage) -> &str {
+    pkg.display_name.as_deref().unwrap_or(&pkg.name)
+}
+
+/// Left-aligned columns sized to their widest cell; the last is not padded.
+fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        let mut out = String::new();
+        for (i, cell) in cells.into_iter().enumerate() {
+            if i == last {
+                out.push_str(cell);
+            } else {
+                out.push_str(&format!("{cell:<width$}  ", width = widths[i]));
+            }
+        }
+        println!("{out}");
+    };
+    line(header.to_vec());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("serialize output")?;
+    println!("{json}");
+    Ok(())
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    if unit == "B" {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {unit}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture_runner;
+
+    fn args(argv: &[&str]) -> Result<Args, String> {
+        parse_args(argv.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        let parsed = args(&["update", "apt:curl", "--yes", "--source=apt"]).unwrap();
+        assert_eq!(
+            parsed.command,
+            Command::Change(Operation::Update, "apt:curl".into())
+        );
+        assert!(parsed.yes && !parsed.json);
+        assert_eq!(parsed.source, Some(PackageSource::Apt));
+
+        let parsed = args(&["--json", "list", "--kind", "gui"]).unwrap();
+        assert_eq!(parsed.command, Command::List);
+        assert_eq!(parsed.kind, Some(AppKind::Gui));
+        assert_eq!(
+            args(&["remove", "vlc", "-h"]).unwrap().command,
+            Command::Help
+        );
+
+        assert!(args(&[]).is_err());
+        assert!(args(&["remove"]).is_err());
+        assert!(args(&["list", "extra"]).is_err());
+        assert!(args(&["list", "--source", "rpm"]).is_err());
+        assert!(args(&["list", "--force"]).is_err());
+    }
+
+    #[test]
+    fn resolves_keys_and_unique_names() {
+        let mut apt = InstalledPackage::new(PackageSource::Apt, "firefox");
+        apt.name = "firefox".into();
+        let mut snap = InstalledPackage::new(PackageSource::Snap, "firefox");
+        snap.name = "firefox".into();
+        let mut vlc = InstalledPackage::new(PackageSource::Apt, "vlc");
+        vlc.name = "vlc".into();
+        vlc.display_name = Some("VLC media player".into());
+        let packages = [apt, snap, vlc];
+
+        assert_eq!(
+            resolve(&packages, "snap:firefox").unwrap().key,
+            "snap:firefox"
+        );
+        assert_eq!(
+            resolve(&packages, "VLC Media Player").unwrap().key,
+            "apt:vlc"
+        );
+        let err = resolve(&packages, "firefox").unwrap_err().to_string();
+        assert!(err.contains("apt:firefox, snap:firefox"), "{err}");
+        assert!(resolve(&packages, "gimp").is_err());
+    }
+
+    #[tokio::test]
+    async fn changes_go_through_preview_and_apply() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+
+        let update = args(&["update", "apt:curl", "--yes", "--json"]).unwrap();
+        assert_eq!(execute(&update, &runner).await.unwrap(), 0);
+
+        // The recorded `apt remove vlc` fails, which must surface as a failure.
+        let remove = args(&["remove", "apt:vlc", "--yes"]).unwrap();
+        assert_eq!(execute(&remove, &runner).await.unwrap(), EXIT_FAILURE);
+
+        // No update is recorded for htop, so preview refuses the plan.
+        let stale = args(&["update", "htop", "--yes"]).unwrap();
+        assert!(execute(&stale, &runner).await.is_err());
+    }
+}
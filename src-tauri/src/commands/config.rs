@@ -0,0 +1,10 @@
+//! Configuration status command.
+
+use crate::config::{self, ConfigStatus};
+
+/// Where the user's config file is and what was wrong with it. Problems were
+/// found when it was read at startup; editing the file takes a restart.
+#[tauri::command]
+pub async fn config_status() -> Result<ConfigStatus, String> {
+    Ok(config::status())
+}
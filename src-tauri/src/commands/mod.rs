@@ -3,5 +3,8 @@
 //! Each command is a thin wrapper over backend logic; no scanner/icon/update
 //! business logic lives here.
 
+pub mod config;
+pub mod notify;
 pub mod operations;
 pub mod packages;
+pub mod updates;
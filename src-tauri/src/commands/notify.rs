@@ -0,0 +1,39 @@
+//! Glue between desktop notifications and the window.
+
+use tauri::{App, AppHandle, Emitter, Manager, WindowEvent};
+
+use crate::notify::{DialogTarget, Notifier};
+use crate::tray::{show_main_window, MAIN_WINDOW};
+
+/// Event carrying the [`DialogTarget`] of a clicked notification.
+pub const OPEN_DIALOG_EVENT: &str = "open-dialog";
+
+/// Connect `notifier` to the notification server, keep it told whether the
+/// window has focus, and bring the window up on the dialog a clicked
+/// notification names.
+pub fn attach_notifier(app: &App, notifier: Notifier) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let focus = notifier.clone();
+        window.on_window_event(move |event| {
+            if let WindowEvent::Focused(focused) = event {
+                focus.set_foreground(*focused);
+            }
+        });
+    }
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let connected = notifier
+            .connect(move |target| open_dialog(&handle, &target))
+            .await;
+        if let Err(e) = connected {
+            eprintln!("scope: no desktop notifications: {e:#}");
+        }
+    });
+}
+
+fn open_dialog(app: &AppHandle, target: &DialogTarget) {
+    show_main_window(app);
+    if let Err(e) = app.emit(OPEN_DIALOG_EVENT, target) {
+        eprintln!("scope: could not emit {OPEN_DIALOG_EVENT}: {e}");
+    }
+}
@@ -1,80 +1,172 @@
-//! Tauri command handlers for uninstall/update preview + apply.
+//! Tauri command handlers for operation preview + apply.
 //!
 //! These are thin typed wrappers. Every operation follows the same pattern:
 //! preview looks up the package in the cached scan, issues a plan into the
 //! [`PlanStore`], and apply only accepts a `plan_id` the backend itself issued,
-//! revalidating against a fresh scan before executing.
+//! revalidating against a fresh scan before executing. What each package
+//! supports comes from its [`crate::backend`]; `package_capabilities` exposes
+//! that to the UI.
+
+use std::sync::Arc;
 
 use tauri::State;
 
-use crate::commands::packages::ScanCache;
-use crate::operations::uninstall::{apply as apply_remove, preview as preview_remove, revalidate as revalidate_remove};
-use crate::operations::update::{
-    apply as apply_update_op,
-    preview as preview_update_op,
-    revalidate as revalidate_update_op,
-};
-use crate::operations::{OperationPlan, OperationResult, PlanStore};
+use crate::backend::{self, Capability};
+use crate::dbus::{Bus, Progress};
+use crate::notify::{Event, Notifier};
+use crate::operations::{self, Operation, OperationPlan, OperationResult, PlanStore};
+use crate::scan_cache::ScanCache;
+use crate::system::{PrivilegedSession, Runner};
+
+/// Build (and store) a preview plan for any supported operation on the package
+/// with the given backend key. Protected packages get a blocked plan that is
+/// never stored; unsupported operations are an error.
+#[tauri::command]
+pub async fn preview_operation(
+    runner: State<'_, Runner>,
+    scan_cache: State<'_, ScanCache>,
+    plans: State<'_, PlanStore>,
+    package_key: String,
+    operation: Operation,
+) -> Result<OperationPlan, String> {
+    preview(&runner, &scan_cache, &plans, &package_key, operation).await
+}
+
+/// Apply a previously-issued plan by id, whatever its operation.
+#[tauri::command]
+pub async fn apply_operation(
+    runner: State<'_, Runner>,
+    scan_cache: State<'_, ScanCache>,
+    plans: State<'_, PlanStore>,
+    bus: State<'_, Bus>,
+    notifier: State<'_, Notifier>,
+    plan_id: String,
+) -> Result<OperationResult, String> {
+    apply(
+        &runner,
+        &scan_cache,
+        &plans,
+        &bus,
+        &notifier,
+        &plan_id,
+        None,
+    )
+    .await
+}
+
+/// Which capabilities the package with the given backend key supports, so the
+/// UI only offers actions that can work.
+#[tauri::command]
+pub async fn package_capabilities(
+    scan_cache: State<'_, ScanCache>,
+    package_key: String,
+) -> Result<Vec<Capability>, String> {
+    let pkg = scan_cache
+        .find(&package_key)
+        .await
+        .ok_or_else(|| format!("Package not found in current scan: {package_key}"))?;
+    Ok(backend::capabilities_for(&pkg))
+}
 
 /// Build (and store) a preview plan for uninstalling the package with the given
 /// backend key. Returns the plan for the UI to confirm, or an error if the
 /// package cannot be found / is not uninstallable.
 #[tauri::command]
 pub async fn preview_uninstall(
+    runner: State<'_, Runner>,
     scan_cache: State<'_, ScanCache>,
     plans: State<'_, PlanStore>,
     package_key: String,
 ) -> Result<OperationPlan, String> {
-    let pkg = find_package(&scan_cache, &package_key)
-        .await
-        .ok_or_else(|| format!("Package not found in current scan: {package_key}"))?;
-
-    let plan = preview_remove(&pkg);
-    if plan.protected {
-        return Ok(plan);
-    }
-    plans.issue(plan.clone()).await;
-    Ok(plan)
+    preview(
+        &runner,
+        &scan_cache,
+        &plans,
+        &package_key,
+        Operation::Uninstall,
+    )
+    .await
 }
 
 /// Apply a previously-issued uninstall plan by id.
 #[tauri::command]
 pub async fn apply_uninstall(
+    runner: State<'_, Runner>,
+    scan_cache: State<'_, ScanCache>,
     plans: State<'_, PlanStore>,
+    bus: State<'_, Bus>,
+    notifier: State<'_, Notifier>,
     plan_id: String,
 ) -> Result<OperationResult, String> {
-    let plan = plans
-        .take(&plan_id)
-        .await
-        .ok_or_else(|| "Stale or unknown uninstall plan. Please preview again.".to_string())?;
-
-    let (pkgs, _) = crate::scanner::scan_all().await;
-    revalidate_remove(&plan, &pkgs).await.map_err(|e| e.to_string())?;
-
-    let result = apply_remove(&plan).await;
-    Ok(result)
+    apply(
+        &runner,
+        &scan_cache,
+        &plans,
+        &bus,
+        &notifier,
+        &plan_id,
+        Some(Operation::Uninstall),
+    )
+    .await
 }
 
 /// Build (and store) a preview plan for updating the package with the given
 /// backend key.
 #[tauri::command]
 pub async fn preview_update(
+    runner: State<'_, Runner>,
     scan_cache: State<'_, ScanCache>,
     plans: State<'_, PlanStore>,
     package_key: String,
 ) -> Result<OperationPlan, String> {
-    let pkg = find_package(&scan_cache, &package_key)
+    preview(
+        &runner,
+        &scan_cache,
+        &plans,
+        &package_key,
+        Operation::Update,
+    )
+    .await
+}
+
+/// Apply a previously-issued update plan by id.
+#[tauri::command]
+pub async fn apply_update(
+    runner: State<'_, Runner>,
+    scan_cache: State<'_, ScanCache>,
+    plans: State<'_, PlanStore>,
+    bus: State<'_, Bus>,
+    notifier: State<'_, Notifier>,
+    plan_id: String,
+) -> Result<OperationResult, String> {
+    apply(
+        &runner,
+        &scan_cache,
+        &plans,
+        &bus,
+        &notifier,
+        &plan_id,
+        Some(Operation::Update),
+    )
+    .await
+}
+
+/// Shared by the Tauri commands and the D-Bus service.
+pub async fn preview(
+    runner: &Runner,
+    scan_cache: &ScanCache,
+    plans: &PlanStore,
+    package_key: &str,
+    operation: Operation,
+) -> Result<OperationPlan, String> {
+    let pkg = scan_cache
+        .find(package_key)
         .await
         .ok_or_else(|| format!("Package not found in current scan: {package_key}"))?;
 
-    if !pkg.has_update {
-        return Err(format!(
-            "'{}' has no updates available.",
-            pkg.display_name.unwrap_or(pkg.name)
-        ));
-    }
-
-    let plan = preview_update_op(&pkg);
+    let plan = operations::preview(runner, &pkg, operation)
+        .await
+        .map_err(|e| e.to_string())?;
     if plan.protected {
         return Ok(plan);
     }
@@ -82,27 +174,113 @@ pub async fn preview_update(
     Ok(plan)
 }
 
-/// Apply a previously-issued update plan by id.
-#[tauri::command]
-pub async fn apply_update(
-    plans: State<'_, PlanStore>,
-    plan_id: String,
+/// Take, revalidate and apply a plan, reporting each stage on D-Bus and the
+/// outcome as a desktop notification. Only the plan's source is rescanned,
+/// before (to revalidate) and after (to update the cached scan). `expected`
+/// guards the typed uninstall/update commands against being handed another
+/// operation's plan.
+pub async fn apply(
+    runner: &Runner,
+    scan_cache: &ScanCache,
+    plans: &PlanStore,
+    bus: &Bus,
+    notifier: &Notifier,
+    plan_id: &str,
+    expected: Option<Operation>,
 ) -> Result<OperationResult, String> {
+    let label = expected
+        .map(|op| format!("{} ", op.id()))
+        .unwrap_or_default();
     let plan = plans
-        .take(&plan_id)
+        .take(plan_id)
         .await
-        .ok_or_else(|| "Stale or unknown update plan. Please preview again.".to_string())?;
+        .filter(|p| expected.is_none() || expected == Some(p.operation))
+        .ok_or_else(|| format!("Stale or unknown {label}plan. Please preview again."))?;
 
-    let (pkgs, _) = crate::scanner::scan_all().await;
-    revalidate_update_op(&plan, &pkgs).await.map_err(|e| e.to_string())?;
+    bus.operation_progress(plan_id, Progress::Revalidating, "")
+        .await;
+    let fresh = scan_cache.rescan(runner, &[plan.source]).await;
+    let revalidated = match operations::revalidate(runner, &plan, &fresh.packages) {
+        Ok(()) => operations::revalidate_dependents(runner, &plan, &fresh.packages).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = revalidated {
+        bus.operation_progress(plan_id, Progress::Failed, &e.to_string())
+            .await;
+        return Err(e.to_string());
+    }
 
-    let result = apply_update_op(&plan).await;
+    bus.operation_progress(plan_id, Progress::Applying, "")
+        .await;
+    let result = operations::apply(runner, &plan).await;
+    scan_cache.rescan(runner, &[plan.source]).await;
+    let stage = if result.success {
+        Progress::Succeeded
+    } else {
+        Progress::Failed
+    };
+    bus.operation_progress(plan_id, stage, &result.message)
+        .await;
+    notifier
+        .notify(Event::OperationFinished {
+            plan: Box::new(plan),
+            result: result.clone(),
+        })
+        .await;
     Ok(result)
 }
 
-async fn find_package(
+/// Update every package in the cached scan that has an update, one plan at a
+/// time through [`preview`] and [`apply`], so each gets the same safety checks,
+/// revalidation and progress signals as a single update. Privileged steps
+/// share one [`PrivilegedSession`], so the batch asks for the password once;
+/// the session ends with the batch. Protected packages and those that cannot
+/// be updated are skipped. Returns each applied plan with its result; a plan
+/// that failed revalidation has a failed result.
+pub async fn update_all(
+    runner: &Runner,
     scan_cache: &ScanCache,
-    key: &str,
-) -> Option<crate::package::InstalledPackage> {
-    scan_cache.find(key).await
+    plans: &PlanStore,
+    bus: &Bus,
+    notifier: &Notifier,
+) -> Vec<(OperationPlan, OperationResult)> {
+    let runner: Runner = Arc::new(PrivilegedSession::new(runner.clone()));
+    let runner = &runner;
+    let outdated: Vec<String> = scan_cache
+        .get()
+        .await
+        .map(|scan| scan.packages)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.has_update)
+        .map(|p| p.key)
+        .collect();
+
+    let mut results = Vec::new();
+    for key in outdated {
+        let Ok(plan) = preview(runner, scan_cache, plans, &key, Operation::Update).await else {
+            continue;
+        };
+        if plan.protected {
+            continue;
+        }
+        let result = apply(
+            runner,
+            scan_cache,
+            plans,
+            bus,
+            notifier,
+            &plan.plan_id,
+            Some(Operation::Update),
+        )
+        .await
+        .unwrap_or_else(|message| OperationResult {
+            success: false,
+            message,
+            logs: String::new(),
+            exit_code: None,
+        });
+        results.push((plan, result));
+    }
+    results
 }
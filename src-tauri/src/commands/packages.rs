@@ -1,69 +1,83 @@
 //! Package list commands: scanning, status reporting, and search.
 
-use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
 
-use serde::{Deserialize, Serialize};
-use tauri::State;
+use crate::dbus::Bus;
+use crate::package::{InstalledPackage, PackageSource, ScanStatus};
+use crate::scan_cache::{CachedScan, ScanCache, ScanDiff};
+use crate::system::Runner;
 
-use crate::package::{InstalledPackage, ScanStatus};
-use crate::scanner::{scan_all, ScanAvailability};
+/// Event carrying the [`ScanDiff`] of every change to the cached scan: full
+/// scans, the startup refresh of a stale scan, and incremental rescans.
+pub const SCAN_DIFF_EVENT: &str = "scan-diff";
 
-/// Cache of the latest full scan, shared across commands.
-#[derive(Default)]
-pub struct ScanCache {
-    inner: Arc<tokio::sync::Mutex<Option<CachedScan>>>,
-}
-
-impl ScanCache {
-    /// Look up a single package by its backend key in the cached scan.
-    pub async fn find(&self, key: &str) -> Option<crate::package::InstalledPackage> {
-        let guard = self.inner.lock().await;
-        let cached = guard.as_ref()?;
-        cached.packages.iter().find(|p| p.key == key).cloned()
-    }
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct CachedScan {
-    pub packages: Vec<InstalledPackage>,
-    pub availability: ScanAvailability,
-    pub scanned_at_ms: u64,
-}
-
-/// Run a full scan across APT, Snap, Flatpak, and AppImage and cache it.
+/// Run a full scan across APT, Snap, Flatpak, AppImage, container exports,
+/// Steam libraries, and per-user extensions and cache it.
 ///
 /// This is the only command that touches the package managers. Results are
 /// cached so `search_packages` can filter without re-scanning.
 #[tauri::command]
-pub async fn scan_packages(state: State<'_, ScanCache>) -> Result<CachedScan, String> {
-    let (packages, availability) = scan_all().await;
-    let scanned_at_ms = now_ms();
-    let cached = CachedScan {
-        packages: packages.clone(),
-        availability: availability.clone(),
-        scanned_at_ms,
-    };
-    let mut guard = state.inner.lock().await;
-    *guard = Some(cached.clone());
-    Ok(cached)
+pub async fn scan_packages(
+    runner: State<'_, Runner>,
+    state: State<'_, ScanCache>,
+) -> Result<CachedScan, String> {
+    Ok(state.refresh(&runner).await)
 }
 
-/// Return the most recent cached scan without rescanning.
+/// Return the most recent cached scan without rescanning. Right after startup
+/// this is the previous run's scan, marked `stale` until the background
+/// refresh replaces it.
 #[tauri::command]
 pub async fn get_cached_scan(state: State<'_, ScanCache>) -> Result<Option<CachedScan>, String> {
-    Ok(state.inner.lock().await.clone())
+    Ok(state.get().await)
 }
 
-/// Per-source availability summary (cheap probes; no real scans).
+/// Relay cache changes to the window ([`SCAN_DIFF_EVENT`]) and to D-Bus
+/// (`ScanCompleted`). `diffs` is subscribed before any scan starts, so the
+/// startup refresh is never missed.
+pub async fn forward_scan_diffs(
+    app: AppHandle,
+    cache: ScanCache,
+    bus: Bus,
+    mut diffs: Receiver<ScanDiff>,
+) {
+    loop {
+        let diff = match diffs.recv().await {
+            Ok(diff) => diff,
+            // The window refetches the whole scan on each event, so a
+            // skipped diff loses nothing.
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        if let Err(e) = app.emit(SCAN_DIFF_EVENT, &diff) {
+            eprintln!("scope: could not emit {SCAN_DIFF_EVENT}: {e}");
+        }
+        if let Some(scan) = cache.get().await {
+            bus.scan_completed(&scan).await;
+        }
+    }
+}
+
+/// Per-source availability summary (cheap probes; no real scans). Sources
+/// disabled in the user's config count as unavailable.
 #[tauri::command]
-pub async fn scan_status() -> Result<ScanStatus, String> {
+pub async fn scan_status(runner: State<'_, Runner>) -> Result<ScanStatus, String> {
     use crate::system::which;
-    let appimage_dirs = crate::scanner::appimage::search_directories();
+    let dirs = runner.dirs();
+    let appimage_dirs = crate::scanner::appimage::search_directories(&dirs);
+    let enabled = |source| crate::config::current().source_enabled(source);
     Ok(ScanStatus {
-        apt_available: which("dpkg-query") && which("apt-mark"),
-        snap_available: which("snap"),
-        flatpak_available: which("flatpak"),
-        appimage_available: true,
+        apt_available: enabled(PackageSource::Apt) && which("dpkg-query") && which("apt-mark"),
+        snap_available: enabled(PackageSource::Snap) && which("snap"),
+        flatpak_available: enabled(PackageSource::Flatpak) && which("flatpak"),
+        appimage_available: enabled(PackageSource::AppImage),
+        container_available: enabled(PackageSource::Container)
+            && crate::scanner::container::is_available(),
+        steam_available: enabled(PackageSource::Steam)
+            && crate::scanner::steam::is_available(&dirs),
+        extension_available: enabled(PackageSource::Extension)
+            && crate::scanner::extensions::is_available(&dirs),
         appimage_dirs,
     })
 }
@@ -78,61 +92,7 @@ pub async fn search_packages(
     source: Option<String>,
     app_kind: Option<String>,
 ) -> Result<Vec<InstalledPackage>, String> {
-    let guard = state.inner.lock().await;
-    let Some(cached) = guard.as_ref() else {
-        return Ok(Vec::new());
-    };
-
-    let q = query.map(|s| s.trim().to_lowercase());
-    let source_filter = source.and_then(|s| match s.to_lowercase().as_str() {
-        "apt" => Some(crate::package::PackageSource::Apt),
-        "snap" => Some(crate::package::PackageSource::Snap),
-        "flatpak" => Some(crate::package::PackageSource::Flatpak),
-        "appimage" => Some(crate::package::PackageSource::AppImage),
-        _ => None,
-    });
-    let kind_filter = app_kind.and_then(|s| match s.to_lowercase().as_str() {
-        "gui" => Some(crate::package::AppKind::Gui),
-        "cli" => Some(crate::package::AppKind::Cli),
-        "unknown" => Some(crate::package::AppKind::Unknown),
-        _ => None,
-    });
-
-    let needle = |p: &InstalledPackage| -> String {
-        format!(
-            "{} {} {} {} {} {} {}",
-            p.name,
-            p.display_name.as_deref().unwrap_or(""),
-            p.description.as_deref().unwrap_or(""),
-            p.package_id,
-            p.install_scope.map(|s| s.id()).unwrap_or(""),
-            p.categories.as_deref().unwrap_or(""),
-            p.version
-        )
-        .to_lowercase()
-    };
-
-    let results = cached
-        .packages
-        .iter()
-        .filter(|p| match (source_filter, kind_filter) {
-            (Some(s), Some(k)) => p.source == s && p.app_kind == k,
-            (Some(s), None) => p.source == s,
-            (None, Some(k)) => p.app_kind == k,
-            (None, None) => true,
-        })
-        .filter(|p| match &q {
-            Some(needle_q) if !needle_q.is_empty() => needle(p).contains(needle_q),
-            _ => true,
-        })
-        .cloned()
-        .collect();
-    Ok(results)
-}
-
-fn now_ms() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0)
+    Ok(state
+        .search(query.as_deref(), source.as_deref(), app_kind.as_deref())
+        .await)
 }
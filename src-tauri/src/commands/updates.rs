@@ -0,0 +1,34 @@
+//! Update-check commands.
+
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::updates::{UpdateCheck, UpdateChecker};
+
+/// Event carrying the [`UpdateCheck`] of every finished update check, on
+/// request or scheduled.
+pub const UPDATES_CHECKED_EVENT: &str = "updates-checked";
+
+/// Check every package in the cached scan for updates now. The annotated
+/// packages arrive as a `scan-diff`; `null` before the first scan.
+#[tauri::command]
+pub async fn check_updates(
+    checker: State<'_, UpdateChecker>,
+) -> Result<Option<UpdateCheck>, String> {
+    Ok(checker.check().await)
+}
+
+/// Relay finished checks to the window ([`UPDATES_CHECKED_EVENT`]).
+pub async fn forward_update_checks(app: AppHandle, mut checks: Receiver<UpdateCheck>) {
+    loop {
+        let check = match checks.recv().await {
+            Ok(check) => check,
+            // Only the latest count matters.
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        if let Err(e) = app.emit(UPDATES_CHECKED_EVENT, &check) {
+            eprintln!("scope: could not emit {UPDATES_CHECKED_EVENT}: {e}");
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! User configuration from `~/.config/scope/config.toml`.
+//!
+//! Strategy: the file is read once per process, on first use, and every
+//! setting is optional; a missing file means the built-in behaviour. Settings
+//! only ever add to Scope's safety rules: extra AppImage directories are
+//! scanned (and their AppImages become removable) and extra protected
+//! packages are blocked on top of the built-in lists in [`crate::safety`],
+//! which no setting can shrink. A file that does not parse is ignored as a
+//! whole; a value that parses but fails validation is dropped on its own.
+//! Either way the problem is kept in [`status`] for the UI and printed once.
+//!
+//! ```toml
+//! [appimage]
+//! extra_dirs = ["~/Games", "/data/AppImages"]
+//!
+//! [protected]          # per source, by package id
+//! apt = ["my-vpn-client"]
+//! flatpak = ["org.example.Tool"]
+//!
+//! [timeouts]           # seconds
+//! scan = 30
+//! uninstall = 180      # also purge
+//! update = 300         # also rollback
+//! hold = 60            # also unhold
+//! plan = 300           # how long a previewed plan can be applied
+//!
+//! [updates]
+//! interval = 360       # minutes between scheduled checks; 0 turns them off
+//!
+//! [sources]            # every source is enabled unless set to false
+//! steam = false
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::package::PackageSource;
+
+/// Name of the file in [`crate::system::config_dir`].
+pub const FILE_NAME: &str = "config.toml";
+
+/// Directories an AppImage directory may not be in: AppImages are removed by
+/// moving them to the trash, which must never reach system files.
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/proc", "/sbin", "/sys", "/usr",
+    "/var",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub appimage: AppImageConfig,
+    /// Package ids protected in addition to the built-in lists, by source.
+    pub protected: HashMap<PackageSource, Vec<String>>,
+    pub timeouts: Timeouts,
+    pub updates: UpdatesConfig,
+    /// Sources set to `false` are not scanned.
+    pub sources: HashMap<PackageSource, bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppImageConfig {
+    /// Searched for AppImages after the built-in directories. `~/` expands to
+    /// the home directory.
+    pub extra_dirs: Vec<PathBuf>,
+}
+
+/// Timeouts in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub scan: u64,
+    pub uninstall: u64,
+    pub update: u64,
+    pub hold: u64,
+    pub plan: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            scan: crate::system::SCAN_TIMEOUT.as_secs(),
+            uninstall: 180,
+            // Updates and rollbacks may download.
+            update: 300,
+            hold: 60,
+            plan: crate::operations::PLAN_TTL.as_secs(),
+        }
+    }
+}
+
+impl Timeouts {
+    /// Per-command timeout for package scans.
+    pub fn scan(&self) -> Duration {
+        Duration::from_secs(self.scan)
+    }
+
+    pub fn uninstall(&self) -> Duration {
+        Duration::from_secs(self.uninstall)
+    }
+
+    pub fn update(&self) -> Duration {
+        Duration::from_secs(self.update)
+    }
+
+    pub fn hold(&self) -> Duration {
+        Duration::from_secs(self.hold)
+    }
+
+    /// How long a previewed plan stays valid.
+    pub fn plan(&self) -> Duration {
+        Duration::from_secs(self.plan)
+    }
+
+    /// Each timeout with the range it must be in.
+    fn checked(&mut self) -> [(&'static str, &mut u64, u64, std::ops::RangeInclusive<u64>); 5] {
+        let default = Timeouts::default();
+        [
+            ("scan", &mut self.scan, default.scan, 1..=600),
+            (
+                "uninstall",
+                &mut self.uninstall,
+                default.uninstall,
+                10..=3600,
+            ),
+            ("update", &mut self.update, default.update, 10..=7200),
+            ("hold", &mut self.hold, default.hold, 10..=3600),
+            ("plan", &mut self.plan, default.plan, 30..=3600),
+        ]
+    }
+}
+
+/// Scheduled update checks.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdatesConfig {
+    /// Minutes between scheduled checks; `0` turns them off.
+    pub interval: u64,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            interval: crate::updates::DEFAULT_INTERVAL.as_secs() / 60,
+        }
+    }
+}
+
+impl UpdatesConfig {
+    /// Minutes a non-zero interval must be in: from a quarter hour to a week.
+    const INTERVAL_RANGE: std::ops::RangeInclusive<u64> = 15..=7 * 24 * 60;
+
+    /// Time between scheduled checks; `None` when they are off.
+    pub fn interval(&self) -> Option<Duration> {
+        (self.interval > 0).then(|| Duration::from_secs(self.interval * 60))
+    }
+}
+
+impl Config {
+    /// Whether `source` is scanned.
+    pub fn source_enabled(&self, source: PackageSource) -> bool {
+        self.sources.get(&source).copied().unwrap_or(true)
+    }
+
+    /// Whether `package_id` of `source` is protected by the config. Ids
+    /// compare case-insensitively, APT ones without their `:arch` suffix.
+    pub fn is_protected(&self, source: PackageSource, package_id: &str) -> bool {
+        let Some(ids) = self.protected.get(&source) else {
+            return false;
+        };
+        let id = match source {
+            PackageSource::Apt => package_id.split(':').next().unwrap_or(package_id),
+            _ => package_id,
+        };
+        ids.iter().any(|p| p.eq_ignore_ascii_case(id))
+    }
+
+    /// Drop the values that fail validation, returning a message for each.
+    fn validate(&mut self, home: Option<&Path>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let dirs = std::mem::take(&mut self.appimage.extra_dirs);
+        for dir in dirs {
+            match appimage_dir(&dir, home) {
+                Ok(dir) => self.appimage.extra_dirs.push(dir),
+                Err(e) => errors.push(format!("appimage.extra_dirs: {}: {e}", dir.display())),
+            }
+        }
+
+        for (source, ids) in self.protected.iter_mut() {
+            ids.retain(|id| {
+                let valid = !id.is_empty() && !id.chars().any(char::is_whitespace);
+                if !valid {
+                    errors.push(format!(
+                        "protected.{}: {id:?} is not a package id",
+                        source.id()
+                    ));
+                }
+                valid
+            });
+        }
+
+        for (name, value, default, range) in self.timeouts.checked() {
+            if !range.contains(value) {
+                errors.push(format!(
+                    "timeouts.{name}: {value} is outside {}..={} seconds; using {default}",
+                    range.start(),
+                    range.end()
+                ));
+                *value = default;
+            }
+        }
+
+        let interval = &mut self.updates.interval;
+        let range = UpdatesConfig::INTERVAL_RANGE;
+        if *interval != 0 && !range.contains(interval) {
+            let default = UpdatesConfig::default().interval;
+            errors.push(format!(
+                "updates.interval: {interval} is outside {}..={} minutes (or 0 for off); using {default}",
+                range.start(),
+                range.end()
+            ));
+            *interval = default;
+        }
+        errors
+    }
+}
+
+/// An extra AppImage directory, made absolute.
+fn appimage_dir(dir: &Path, home: Option<&Path>) -> Result<PathBuf, String> {
+    let dir = match dir.strip_prefix("~") {
+        Ok(rest) => home.ok_or("HOME is not set")?.join(rest),
+        Err(_) => dir.to_path_buf(),
+    };
+    if !dir.is_absolute() {
+        return Err("must be absolute or start with ~/".into());
+    }
+    if dir == Path::new("/") || SYSTEM_DIRS.iter().any(|s| dir.starts_with(s)) {
+        return Err("is a system directory".into());
+    }
+    Ok(dir)
+}
+
+/// Where the config was read from and what was wrong with it, for the UI.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigStatus {
+    /// The config file, whether or not it exists; `None` without a home.
+    pub path: Option<String>,
+    /// Whether the file exists.
+    pub loaded: bool,
+    pub errors: Vec<String>,
+}
+
+struct Loaded {
+    config: Config,
+    status: ConfigStatus,
+}
+
+fn loaded() -> &'static Loaded {
+    static LOADED: OnceLock<Loaded> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        let loaded = load(path().as_deref());
+        for error in &loaded.status.errors {
+            eprintln!("scope: {FILE_NAME}: {error}");
+        }
+        loaded
+    })
+}
+
+/// The configuration in effect.
+pub fn current() -> &'static Config {
+    &loaded().config
+}
+
+/// Where the configuration came from and the problems found in it.
+pub fn status() -> ConfigStatus {
+    loaded().status.clone()
+}
+
+fn path() -> Option<PathBuf> {
+    // Tests run against the built-in behaviour, whatever the machine has.
+    if cfg!(test) {
+        return None;
+    }
+    crate::system::config_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn load(path: Option<&Path>) -> Loaded {
+    let mut status = ConfigStatus {
+        path: path.map(|p| p.display().to_string()),
+        ..Default::default()
+    };
+    let text = match path.map(std::fs::read_to_string) {
+        None => None,
+        Some(Ok(text)) => Some(text),
+        Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Some(Err(e)) => {
+            status.errors.push(format!("could not read it: {e}"));
+            None
+        }
+    };
+    let Some(text) = text else {
+        return Loaded {
+            config: Config::default(),
+            status,
+        };
+    };
+    status.loaded = true;
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let (config, errors) = parse(&text, home.as_deref());
+    status.errors.extend(errors);
+    Loaded { config, status }
+}
+
+/// Parse and validate the file's contents. A file that does not parse gives
+/// the default config and the parse error.
+fn parse(text: &str, home: Option<&Path>) -> (Config, Vec<String>) {
+    match toml::from_str::<Config>(text) {
+        Ok(mut config) => {
+            let errors = config.validate(home);
+            (config, errors)
+        }
+        Err(e) => (
+            Config::default(),
+            vec![format!("ignored: {}", e.to_string().trim_end())],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_validates_settings() {
+        let text = r#"
+            [appimage]
+            extra_dirs = ["~/Games", "/data/AppImages", "relative", "/usr/share"]
+
+            [protected]
+            apt = ["my-vpn", "two words"]
+
+            [timeouts]
+            scan = 45
+            update = 0
+
+            [updates]
+            interval = 5
+
+            [sources]
+            steam = false
+        "#;
+        let (config, errors) = parse(text, Some(Path::new("/home/user")));
+        assert_eq!(
+            config.appimage.extra_dirs,
+            [
+                PathBuf::from("/home/user/Games"),
+                PathBuf::from("/data/AppImages")
+            ]
+        );
+        assert!(config.is_protected(PackageSource::Apt, "My-VPN:amd64"));
+        assert!(!config.is_protected(PackageSource::Snap, "my-vpn"));
+        assert_eq!(config.timeouts.scan(), Duration::from_secs(45));
+        assert_eq!(config.timeouts.update, Timeouts::default().update);
+        assert!(!config.source_enabled(PackageSource::Steam));
+        assert!(config.source_enabled(PackageSource::Apt));
+        assert_eq!(
+            config.updates.interval(),
+            Some(Duration::from_secs(6 * 3600))
+        );
+        assert_eq!(errors.len(), 5, "{errors:?}");
+        assert!(errors[0].contains("relative"));
+        assert!(errors[1].contains("system directory"));
+        assert!(errors[2].contains("\"two words\""));
+        assert!(errors[3].starts_with("timeouts.update"));
+        assert!(errors[4].starts_with("updates.interval"));
+
+        let (config, errors) = parse("[updates]\ninterval = 0\n", None);
+        assert!(errors.is_empty());
+        assert_eq!(config.updates.interval(), None);
+    }
+
+    #[test]
+    fn broken_files_fall_back_to_the_defaults() {
+        let (config, errors) = parse("[sources]\nnope = false\n", None);
+        assert!(config.sources.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("nope"), "{errors:?}");
+
+        let (_, errors) = parse("[timeouts]\nscan = \"soon\"\n", None);
+        assert_eq!(errors.len(), 1);
+
+        let missing = load(Some(Path::new("/nonexistent/scope/config.toml")));
+        assert!(!missing.status.loaded);
+        assert!(missing.status.errors.is_empty());
+        assert_eq!(missing.config.timeouts.plan(), Duration::from_secs(300));
+    }
+}
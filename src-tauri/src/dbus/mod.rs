@@ -0,0 +1,215 @@
+//! Session-bus service `com.khurram.scope`.
+//!
+//! Lets other desktop components (a shell extension, a tray indicator, shell
+//! scripts via `busctl`) query Scope while the app runs. The methods mirror
+//! the Tauri commands and share their [`ScanCache`], [`PlanStore`] and
+//! preview/apply code, so a plan previewed over D-Bus gets the same safety
+//! checks and revalidation as one previewed in the window.
+//!
+//! Strategy: payloads are the same JSON documents the frontend receives,
+//! passed as strings, so the interface does not need a D-Bus type for every
+//! DTO and stays in step with them. [`Bus`] is the handle both sides emit the
+//! `ScanCompleted` and `OperationProgress` signals through; it is a no-op until
+//! the service owns its name, e.g. when another instance already runs.
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+use zbus::Connection;
+
+use crate::commands::operations::{apply, preview};
+use crate::notify::Notifier;
+use crate::operations::{Operation, PlanStore};
+use crate::scan_cache::{CachedScan, ScanCache};
+use crate::system::Runner;
+
+/// Well-known name the service owns on the session bus.
+pub const BUS_NAME: &str = "com.khurram.scope";
+/// Object path of the [`ScopeService`] interface.
+pub const OBJECT_PATH: &str = "/com/khurram/scope";
+
+/// Stage of an operation, reported by the `OperationProgress` signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Checking the plan against a fresh scan.
+    Revalidating,
+    /// The package manager command is running.
+    Applying,
+    Succeeded,
+    Failed,
+}
+
+impl Progress {
+    /// Identifier sent in the signal.
+    pub fn id(self) -> &'static str {
+        match self {
+            Progress::Revalidating => "revalidating",
+            Progress::Applying => "applying",
+            Progress::Succeeded => "succeeded",
+            Progress::Failed => "failed",
+        }
+    }
+}
+
+/// Shared handle to the session-bus connection, managed as Tauri state.
+#[derive(Clone, Default)]
+pub struct Bus {
+    connection: Arc<OnceLock<Connection>>,
+}
+
+impl Bus {
+    /// Connect to the session bus, export `service` and claim [`BUS_NAME`].
+    pub async fn serve(&self, service: ScopeService) -> Result<()> {
+        let connection = zbus::connection::Builder::session()?
+            .serve_at(OBJECT_PATH, service)?
+            .name(BUS_NAME)?
+            .build()
+            .await
+            .with_context(|| format!("claim {BUS_NAME} on the session bus"))?;
+        let _ = self.connection.set(connection);
+        Ok(())
+    }
+
+    /// Emit `ScanCompleted` for a scan that just replaced the cached one, in
+    /// full or for some sources.
+    pub async fn scan_completed(&self, scan: &CachedScan) {
+        if let Some(emitter) = self.emitter() {
+            let count = u32::try_from(scan.packages.len()).unwrap_or(u32::MAX);
+            let _ = ScopeService::scan_completed(&emitter, count, scan.scanned_at_ms).await;
+        }
+    }
+
+    /// Emit `OperationProgress` for an applied plan. `message` is empty for
+    /// intermediate stages.
+    pub async fn operation_progress(&self, plan_id: &str, stage: Progress, message: &str) {
+        if let Some(emitter) = self.emitter() {
+            let _ = ScopeService::operation_progress(&emitter, plan_id, stage.id(), message).await;
+        }
+    }
+
+    fn emitter(&self) -> Option<SignalEmitter<'static>> {
+        SignalEmitter::new(self.connection.get()?, OBJECT_PATH).ok()
+    }
+}
+
+/// The `com.khurram.scope` interface.
+pub struct ScopeService {
+    runner: Runner,
+    scan_cache: ScanCache,
+    plans: PlanStore,
+    bus: Bus,
+    notifier: Notifier,
+}
+
+impl ScopeService {
+    pub fn new(
+        runner: Runner,
+        scan_cache: ScanCache,
+        plans: PlanStore,
+        bus: Bus,
+        notifier: Notifier,
+    ) -> Self {
+        Self {
+            runner,
+            scan_cache,
+            plans,
+            bus,
+            notifier,
+        }
+    }
+}
+
+#[zbus::interface(name = "com.khurram.scope")]
+impl ScopeService {
+    /// Full rescan. Returns the `CachedScan` JSON; `ScanCompleted` follows.
+    async fn scan_packages(&self) -> fdo::Result<String> {
+        to_json(&self.scan_cache.refresh(&self.runner).await)
+    }
+
+    /// Filter the cached scan. Empty strings mean "no filter". Returns a JSON
+    /// array of packages, empty before the first scan.
+    async fn search_packages(
+        &self,
+        query: &str,
+        source: &str,
+        app_kind: &str,
+    ) -> fdo::Result<String> {
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        let results = self
+            .scan_cache
+            .search(
+                non_empty(query).as_deref(),
+                non_empty(source).as_deref(),
+                non_empty(app_kind).as_deref(),
+            )
+            .await;
+        to_json(&results)
+    }
+
+    /// Preview uninstalling a package from the cached scan. Returns the
+    /// `OperationPlan` JSON; its `plan_id` is what `Apply` takes.
+    async fn preview_uninstall(&self, package_key: &str) -> fdo::Result<String> {
+        self.preview(package_key, Operation::Uninstall).await
+    }
+
+    /// Preview updating a package from the cached scan.
+    async fn preview_update(&self, package_key: &str) -> fdo::Result<String> {
+        self.preview(package_key, Operation::Update).await
+    }
+
+    /// Apply a previewed plan. Returns the `OperationResult` JSON; progress is
+    /// reported with `OperationProgress`.
+    async fn apply(&self, plan_id: &str) -> fdo::Result<String> {
+        let result = apply(
+            &self.runner,
+            &self.scan_cache,
+            &self.plans,
+            &self.bus,
+            &self.notifier,
+            plan_id,
+            None,
+        )
+        .await
+        .map_err(fdo::Error::Failed)?;
+        to_json(&result)
+    }
+
+    /// The cached scan changed: a full scan or an incremental rescan finished.
+    #[zbus(signal)]
+    async fn scan_completed(
+        emitter: &SignalEmitter<'_>,
+        package_count: u32,
+        scanned_at_ms: u64,
+    ) -> zbus::Result<()>;
+
+    /// An applied plan moved to `stage` (see [`Progress::id`]).
+    #[zbus(signal)]
+    async fn operation_progress(
+        emitter: &SignalEmitter<'_>,
+        plan_id: &str,
+        stage: &str,
+        message: &str,
+    ) -> zbus::Result<()>;
+}
+
+impl ScopeService {
+    async fn preview(&self, package_key: &str, operation: Operation) -> fdo::Result<String> {
+        let plan = preview(
+            &self.runner,
+            &self.scan_cache,
+            &self.plans,
+            package_key,
+            operation,
+        )
+        .await
+        .map_err(fdo::Error::Failed)?;
+        to_json(&plan)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> fdo::Result<String> {
+    serde_json::to_string(value).map_err(|e| fdo::Error::Failed(e.to_string()))
+}
@@ -48,7 +48,8 @@ pub fn discover_desktop_apps() -> Vec<DesktopApp> {
     apps
 }
 
-fn application_dirs() -> Vec<PathBuf> {
+/// Directories searched for `.desktop` entries, most specific first.
+pub fn application_dirs() -> Vec<PathBuf> {
     let mut dirs = Vec::new();
 
     if let Some(data_home) = env::var_os("XDG_DATA_HOME") {
@@ -190,6 +191,22 @@ impl DesktopIndex {
                     .or_else(|| self.by_id.get(package_id))
                     .or_else(|| self.by_exec.get(&package_id.to_lowercase()))
             }
+            // Steam writes `<Game Name>.desktop` launchers; match on the name.
+            crate::package::PackageSource::Steam => self.by_name_lower.get(&name.to_lowercase()),
+            // Extensions carry their own metadata and icons.
+            crate::package::PackageSource::Extension => None,
+            // Plugins report their own display metadata and icons.
+            crate::package::PackageSource::Plugin => None,
+            crate::package::PackageSource::Container => {
+                // Container exports are keyed by their host-side file; exported
+                // apps match the `.desktop` entry with the same id, binaries
+                // have no entry.
+                let path = Path::new(package_id);
+                if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+                    return None;
+                }
+                self.by_id.get(path.file_stem()?.to_str()?)
+            }
             crate::package::PackageSource::Apt | crate::package::PackageSource::AppImage => {
                 let lc = package_id.to_lowercase();
                 self.by_id
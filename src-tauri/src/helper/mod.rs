@@ -0,0 +1,480 @@
+//! `scope-helper`, the one program Scope runs as root.
+//!
+//! Strategy: privileged changes (APT, Snap and system Flatpak packages) are
+//! not run as `pkexec apt ...`, which would have Polkit authorize any apt
+//! invocation behind a generic "run a program as root" prompt. Scope runs
+//! `pkexec scope-helper <action> <source> <package-id> [flag]` instead. The
+//! shipped policy (`src-tauri/polkit/com.khurram.scope.policy`) defines one
+//! Polkit action per `<action>` (`com.khurram.scope.remove-package`,
+//! `update-package`, `hold-package`), matched on the helper's path and first
+//! argument, so the prompt says what is about to happen. As root the helper
+//! accepts nothing but that typed [`Request`]: it checks the package id's
+//! syntax, re-runs [`safety::check_package`] (and, for APT removals, dpkg's
+//! base-system rule) and builds the package-manager command itself, from the
+//! same backend functions the preview shows, so a compromised frontend or
+//! unprivileged backend cannot widen what runs as root.
+//!
+//! A batch of changes (Update all) runs `pkexec scope-helper session` once
+//! instead, under its own Polkit action, and streams one JSON request per
+//! line through it; see [`crate::system::PrivilegedSession`]. The helper
+//! prints [`SESSION_READY`] once it runs, i.e. once Polkit has authenticated
+//! the user, answers each request with the command's output and exits when
+//! Scope closes its stdin or after [`SESSION_IDLE`] without a request.
+
+use std::io::{BufRead, Write};
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::backend;
+use crate::operations::Operation;
+use crate::package::{BaseSystem, PackageSource};
+use crate::safety;
+use crate::system::{abs, Dirs};
+
+/// Where packages install the helper; the policy's `exec.path` names it.
+pub const HELPER_PATH: &str = "/usr/libexec/scope/scope-helper";
+/// Environment variable pointing Scope at another helper binary, e.g. a
+/// development build. Polkit only shows the specific prompts for
+/// [`HELPER_PATH`]; others get its generic one.
+pub const HELPER_ENV: &str = "SCOPE_HELPER";
+
+/// The helper's argument for a session; the policy's `exec.argv1` for it.
+pub const SESSION_ARG: &str = "session";
+/// The line a session prints before its first answer.
+pub const SESSION_READY: &str = "ready";
+/// How long a session waits for its next request before it exits.
+pub const SESSION_IDLE: Duration = Duration::from_secs(120);
+
+/// Exit status for requests the helper refuses.
+pub const REFUSED: i32 = 2;
+/// The only `PATH` package managers see as root.
+const ROOT_PATH: &str = "/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// One privileged operation on one package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub operation: Operation,
+    pub source: PackageSource,
+    pub package_id: String,
+}
+
+/// The helper's first argument (the Polkit action) and flag for `operation`.
+fn action(operation: Operation) -> (&'static str, Option<&'static str>) {
+    match operation {
+        Operation::Uninstall => ("remove", None),
+        Operation::Purge => ("remove", Some("--purge")),
+        Operation::Update => ("update", None),
+        Operation::Rollback => ("update", Some("--rollback")),
+        Operation::Hold => ("hold", None),
+        Operation::Unhold => ("hold", Some("--release")),
+    }
+}
+
+const OPERATIONS: [Operation; 6] = [
+    Operation::Uninstall,
+    Operation::Purge,
+    Operation::Update,
+    Operation::Rollback,
+    Operation::Hold,
+    Operation::Unhold,
+];
+
+/// Sources whose changes need root.
+const SOURCES: [PackageSource; 3] = [
+    PackageSource::Apt,
+    PackageSource::Snap,
+    PackageSource::Flatpak,
+];
+
+impl Request {
+    pub fn new(operation: Operation, source: PackageSource, package_id: &str) -> Self {
+        Self {
+            operation,
+            source,
+            package_id: package_id.to_string(),
+        }
+    }
+
+    /// The helper's arguments for this request.
+    pub fn args(&self) -> Vec<String> {
+        let (action, flag) = action(self.operation);
+        let mut args = vec![
+            action.to_string(),
+            self.source.id().to_string(),
+            self.package_id.clone(),
+        ];
+        args.extend(flag.map(str::to_string));
+        args
+    }
+
+    /// Read a request back from the helper's arguments, refusing anything
+    /// [`args`](Self::args) would not have produced.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let (action_arg, source, package_id, flag) = match args {
+            [a, s, p] => (a, s, p, None),
+            [a, s, p, f] => (a, s, p, Some(f.as_str())),
+            _ => bail!("usage: scope-helper remove|update|hold <source> <package-id> [flag]"),
+        };
+        let Some(operation) = OPERATIONS
+            .into_iter()
+            .find(|op| action(*op) == (action_arg.as_str(), flag))
+        else {
+            bail!("unknown request: {action_arg} {}", flag.unwrap_or_default());
+        };
+        let Some(source) = SOURCES.into_iter().find(|s| s.id() == source) else {
+            bail!("'{source}' packages are not changed as root");
+        };
+        if !valid_package_id(source, package_id) {
+            bail!(
+                "'{package_id}' is not a valid {} package id",
+                source.label()
+            );
+        }
+        Ok(Self::new(operation, source, package_id))
+    }
+
+    /// The package-manager command the helper runs for this request: the same
+    /// one the plan's preview shows.
+    pub fn command(&self) -> Result<(&'static str, Vec<String>)> {
+        let id = self.package_id.as_str();
+        let owned = |args: Vec<&str>| args.into_iter().map(str::to_string).collect();
+        let command = match self.source {
+            PackageSource::Apt => {
+                backend::apt::command(self.operation, id).map(|(p, args)| (p, owned(args)))
+            }
+            PackageSource::Snap => Some(("snap", owned(backend::snap::args(self.operation, id)))),
+            // User installations never need root.
+            PackageSource::Flatpak => {
+                backend::flatpak::args(self.operation, "--system", id).map(|a| ("flatpak", a))
+            }
+            _ => None,
+        };
+        command.with_context(|| {
+            format!(
+                "{} packages do not support '{}'",
+                self.source.label(),
+                self.operation.id()
+            )
+        })
+    }
+
+    /// Display-only summary for plan steps: the helper call and what it runs.
+    pub fn summary(&self) -> String {
+        let call = format!("pkexec scope-helper {}", self.args().join(" "));
+        match self.command() {
+            Ok((program, args)) => format!("{call} (as root: {program} {})", args.join(" ")),
+            Err(_) => call,
+        }
+    }
+}
+
+/// The helper's answer to one session request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    /// Exit code, `None` when killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Response {
+    fn refused(e: anyhow::Error) -> Self {
+        Self {
+            code: Some(REFUSED),
+            stdout: String::new(),
+            stderr: format!("scope-helper: {e:#}"),
+        }
+    }
+}
+
+/// Package ids as the package managers name them. Nothing may start with `-`,
+/// so an id can never turn into an option.
+fn valid_package_id(source: PackageSource, id: &str) -> bool {
+    let allowed = |c: char| match source {
+        // Debian names, optionally with an `:arch` qualifier.
+        PackageSource::Apt => c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.:".contains(c),
+        // Snap names, optionally with an `_instance` key.
+        PackageSource::Snap => c.is_ascii_lowercase() || c.is_ascii_digit() || "-_".contains(c),
+        // Reverse-DNS application ids.
+        PackageSource::Flatpak => c.is_ascii_alphanumeric() || "_-.".contains(c),
+        _ => false,
+    };
+    id.len() <= 255
+        && id.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && id.chars().all(allowed)
+}
+
+/// The helper to run: [`HELPER_ENV`] when set, else [`HELPER_PATH`].
+pub fn path() -> String {
+    std::env::var(HELPER_ENV).unwrap_or_else(|_| HELPER_PATH.to_string())
+}
+
+/// Entry point of the `scope-helper` binary. Returns the exit status: the
+/// package manager's, or [`REFUSED`] for a request that was not run.
+pub fn main() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("scope-helper: {e:#}");
+            REFUSED
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<i32> {
+    // pkexec sets this; anything else is not Scope asking.
+    if std::env::var_os("PKEXEC_UID").is_none() {
+        bail!("scope-helper is run by Scope through pkexec");
+    }
+    if args == [SESSION_ARG] {
+        let stdout = std::io::stdout();
+        serve(
+            std::io::BufReader::new(std::io::stdin()),
+            stdout.lock(),
+            SESSION_IDLE,
+            execute,
+        )?;
+        return Ok(0);
+    }
+    let (program, mut command) = checked_command(&Request::parse(args)?)?;
+    let status = command
+        .status()
+        .with_context(|| format!("could not run {program}"))?;
+    Ok(status.code().unwrap_or(REFUSED))
+}
+
+/// The package-manager command for `request`, after the safety check, with a
+/// clean environment.
+fn checked_command(request: &Request) -> Result<(&'static str, std::process::Command)> {
+    check(request, dpkg_base_system)?;
+    let (program, args) = request.command()?;
+    let mut command = std::process::Command::new(abs(program));
+    command
+        .args(&args)
+        .env_clear()
+        .env("PATH", ROOT_PATH)
+        .env("DEBIAN_FRONTEND", "noninteractive")
+        .stdin(Stdio::null());
+    Ok((program, command))
+}
+
+/// Refuse `request` when the safety rules protect its package: the static
+/// ones, and for APT removals the base-system mark `base_system` reads from
+/// dpkg, so a caller that skipped the preview cannot remove `Essential`
+/// packages either.
+fn check(
+    request: &Request,
+    base_system: impl FnOnce(&str) -> Result<Option<BaseSystem>>,
+) -> Result<()> {
+    let mut protection = safety::check_package(request.source, &request.package_id, &Dirs::Host);
+    if !protection.protected
+        && request.source == PackageSource::Apt
+        && matches!(request.operation, Operation::Uninstall | Operation::Purge)
+    {
+        let mark = base_system(&request.package_id)?;
+        protection = safety::check_base_system(&request.package_id, mark);
+    }
+    if protection.protected {
+        bail!(
+            "refusing to change a protected package: {}",
+            protection.reason.unwrap_or_else(|| "protected".into())
+        );
+    }
+    Ok(())
+}
+
+/// The base-system mark dpkg has for an installed APT package. A package
+/// dpkg does not know is refused rather than assumed unmarked.
+fn dpkg_base_system(package_id: &str) -> Result<Option<BaseSystem>> {
+    let out = std::process::Command::new(abs("dpkg-query"))
+        .args([
+            "-W",
+            "-f=${Essential}\t${Protected}\t${Priority}\n",
+            package_id,
+        ])
+        .env_clear()
+        .env("PATH", ROOT_PATH)
+        .stdin(Stdio::null())
+        .output()
+        .context("could not run dpkg-query")?;
+    if !out.status.success() {
+        bail!("dpkg does not know '{package_id}'");
+    }
+    Ok(parse_base_system(&String::from_utf8_lossy(&out.stdout)))
+}
+
+/// The mark in `dpkg-query` output, one line per installed architecture.
+fn parse_base_system(output: &str) -> Option<BaseSystem> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split('\t');
+        let mut field = || fields.next().unwrap_or_default();
+        BaseSystem::from_fields(field(), field(), field())
+    })
+}
+
+/// Run one session request, capturing its output for the response.
+fn execute(request: &Request) -> Result<Response> {
+    let (program, mut command) = checked_command(request)?;
+    let out = command
+        .output()
+        .with_context(|| format!("could not run {program}"))?;
+    Ok(Response {
+        code: out.status.code(),
+        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
+    })
+}
+
+/// Print [`SESSION_READY`], then answer requests from `input`, one JSON array
+/// of helper arguments per line, with one JSON [`Response`] per line on
+/// `output`, until `input` closes or
+/// stays silent for `idle`. Requests go through [`Request::parse`] exactly
+/// like the arguments of a single call.
+fn serve(
+    input: impl BufRead + Send + 'static,
+    mut output: impl Write,
+    idle: Duration,
+    mut execute: impl FnMut(&Request) -> Result<Response>,
+) -> Result<()> {
+    // Lines are read on their own thread so waiting for one can time out.
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in input.lines() {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    writeln!(output, "{SESSION_READY}")?;
+    output.flush()?;
+    while let Ok(line) = rx.recv_timeout(idle) {
+        let line = line.context("could not read the request")?;
+        let response = serde_json::from_str::<Vec<String>>(&line)
+            .context("requests are JSON arrays of helper arguments")
+            .and_then(|args| Request::parse(&args))
+            .and_then(|request| execute(&request))
+            .unwrap_or_else(Response::refused);
+        serde_json::to_writer(&mut output, &response)?;
+        output.write_all(b"\n")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn requests_round_trip_through_the_arguments() {
+        for operation in OPERATIONS {
+            for (source, id) in [
+                (PackageSource::Apt, "libreoffice-writer:amd64"),
+                (PackageSource::Snap, "firefox"),
+                (PackageSource::Flatpak, "org.mozilla.firefox"),
+            ] {
+                let request = Request::new(operation, source, id);
+                assert_eq!(Request::parse(&request.args()).unwrap(), request);
+            }
+        }
+        let purge = Request::new(Operation::Purge, PackageSource::Apt, "vlc");
+        assert_eq!(purge.args(), ["remove", "apt", "vlc", "--purge"]);
+        assert_eq!(
+            purge.summary(),
+            "pkexec scope-helper remove apt vlc --purge (as root: apt purge -y vlc)"
+        );
+    }
+
+    #[test]
+    fn refuses_anything_else() {
+        for args in [
+            &["remove", "apt"][..],
+            &["install", "apt", "vlc"],
+            &["remove", "apt", "vlc", "--rollback"],
+            &["remove", "appimage", "/home/u/x.AppImage"],
+            &["remove", "apt", "-oAPT::Get::Assume-Yes=1"],
+            &["remove", "apt", "vlc firefox"],
+            &["update", "snap", "../core"],
+        ] {
+            assert!(Request::parse(&strings(args)).is_err(), "{args:?}");
+        }
+        // Apt cannot roll back.
+        let rollback = Request::parse(&strings(&["update", "apt", "vlc", "--rollback"])).unwrap();
+        assert!(rollback.command().is_err());
+    }
+
+    #[test]
+    fn refuses_to_remove_base_system_packages() {
+        let essential = |_: &str| Ok(Some(BaseSystem::Essential));
+        let remove = Request::new(Operation::Uninstall, PackageSource::Apt, "mawk");
+        let err = check(&remove, essential).unwrap_err();
+        assert!(err.to_string().contains("Essential: yes"), "{err}");
+        let purge = Request::new(Operation::Purge, PackageSource::Apt, "mawk");
+        assert!(check(&purge, essential).is_err());
+        // Updating stays allowed, and unmarked packages can go.
+        let update = Request::new(Operation::Update, PackageSource::Apt, "mawk");
+        check(&update, essential).unwrap();
+        check(&remove, |_| Ok(None)).unwrap();
+        // dpkg could not say: refused.
+        assert!(check(&remove, |_| bail!("dpkg does not know 'mawk'")).is_err());
+
+        assert_eq!(
+            parse_base_system("\t\toptional\nyes\t\trequired\n"),
+            Some(BaseSystem::Essential)
+        );
+        assert_eq!(parse_base_system("no\t\toptional\n"), None);
+    }
+
+    #[test]
+    fn sessions_answer_each_line_until_input_closes() {
+        let input = concat!(
+            r#"["update","apt","curl"]"#,
+            "\n",
+            r#"["install","apt","curl"]"#,
+            "\n",
+            "not json\n",
+            r#"["hold","snap","firefox","--release"]"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        let mut seen = Vec::new();
+        serve(
+            std::io::Cursor::new(input),
+            &mut output,
+            Duration::from_secs(5),
+            |request| {
+                seen.push(request.clone());
+                Ok(Response {
+                    code: Some(0),
+                    stdout: request.args().join(" "),
+                    stderr: String::new(),
+                })
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            seen,
+            [
+                Request::new(Operation::Update, PackageSource::Apt, "curl"),
+                Request::new(Operation::Unhold, PackageSource::Snap, "firefox"),
+            ]
+        );
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some(SESSION_READY));
+        let responses: Vec<Response> = lines.map(|l| serde_json::from_str(l).unwrap()).collect();
+        let codes: Vec<_> = responses.iter().map(|r| r.code).collect();
+        assert_eq!(codes, [Some(0), Some(REFUSED), Some(REFUSED), Some(0)]);
+        assert_eq!(responses[0].stdout, "update apt curl");
+        assert!(responses[1].stderr.contains("unknown request"));
+    }
+}
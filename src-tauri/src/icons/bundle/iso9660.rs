@@ -0,0 +1,309 @@
+//! Just enough ISO 9660 to read one small file from a type 1 AppImage.
+//!
+//! Strategy: a type 1 AppImage is an ISO 9660 image whose system area holds
+//! the ELF runtime. The primary volume descriptor (sector 16) gives the block
+//! size and root directory; directories are lists of records. Plain ISO 9660
+//! names are upper-case `NAME.EXT;1`, so names and symlinks come from the
+//! Rock Ridge `NM` and `SL` entries in each record's system use area, which
+//! `genisoimage -R` writes; plain names are matched case-insensitively.
+
+use std::fs::File;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use super::{read_at, Entry, Tree, MAX_ICON};
+
+const SECTOR: u64 = 2048;
+/// Largest directory read; AppImage roots hold a handful of entries.
+const MAX_DIRECTORY: u32 = 1 << 20;
+
+/// A directory record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Record {
+    extent: u32,
+    size: u32,
+    dir: bool,
+    /// The Rock Ridge name, else the ISO 9660 one without its version.
+    name: String,
+    /// The Rock Ridge symlink target.
+    link: Option<String>,
+}
+
+pub struct Iso<'a> {
+    file: &'a File,
+    block: u64,
+    root: Record,
+}
+
+impl<'a> Iso<'a> {
+    pub fn open(file: &'a File) -> Result<Self> {
+        let pvd =
+            read_at(file, 16 * SECTOR, SECTOR as usize).context("reading the volume descriptor")?;
+        ensure!(
+            pvd[0] == 1 && &pvd[1..6] == b"CD001",
+            "no ISO 9660 primary volume descriptor"
+        );
+        let block = u64::from(u16::from_le_bytes([pvd[128], pvd[129]]));
+        ensure!(block.is_power_of_two(), "bad ISO 9660 block size {block}");
+        let root = parse_record(&pvd[156..190]).context("bad root directory record")?;
+        Ok(Self { file, block, root })
+    }
+
+    /// The records of directory `dir`, without `.` and `..`.
+    fn records(&self, dir: &Record) -> Result<Vec<Record>> {
+        ensure!(dir.size <= MAX_DIRECTORY, "directory too large");
+        let data = read_at(
+            self.file,
+            u64::from(dir.extent) * self.block,
+            dir.size as usize,
+        )?;
+        let mut records = Vec::new();
+        let mut at = 0;
+        while at < data.len() {
+            let len = usize::from(data[at]);
+            if len == 0 {
+                // Records never span blocks: the rest of this one is padding.
+                at = (at / self.block as usize + 1) * self.block as usize;
+                continue;
+            }
+            let bytes = data
+                .get(at..at + len)
+                .context("truncated directory record")?;
+            if let Some(record) = parse_record(bytes).filter(|r| r.name != "." && r.name != "..") {
+                records.push(record);
+            }
+            at += len;
+        }
+        Ok(records)
+    }
+}
+
+/// A directory record, or `None` when malformed. `.` and `..` are named so.
+fn parse_record(bytes: &[u8]) -> Option<Record> {
+    let len = usize::from(*bytes.first()?);
+    let bytes = bytes.get(..len)?;
+    let name_len = usize::from(*bytes.get(32)?);
+    let iso_name = bytes.get(33..33 + name_len)?;
+    let extent = u32::from_le_bytes(bytes[2..6].try_into().ok()?);
+    let size = u32::from_le_bytes(bytes[10..14].try_into().ok()?);
+    let dir = bytes[25] & 0x02 != 0;
+    // The system use area follows the name, padded to an even offset.
+    let system_use = bytes
+        .get(33 + name_len + (1 - name_len % 2)..)
+        .unwrap_or_default();
+    let (rr_name, link) = rock_ridge(system_use);
+    let name = match iso_name {
+        [0] => Some(".".to_string()),
+        [1] => Some("..".to_string()),
+        _ => rr_name,
+    };
+    let name = name.unwrap_or_else(|| {
+        let name = String::from_utf8_lossy(iso_name);
+        let name = name.split(';').next().unwrap_or_default();
+        name.strip_suffix('.').unwrap_or(name).to_string()
+    });
+    Some(Record {
+        extent,
+        size,
+        dir,
+        name,
+        link,
+    })
+}
+
+/// The `NM` name and `SL` symlink target in a system use area.
+fn rock_ridge(mut area: &[u8]) -> (Option<String>, Option<String>) {
+    let mut name: Option<String> = None;
+    let mut link: Option<String> = None;
+    // Whether the last symlink component continues in the next one.
+    let mut continued = false;
+    while area.len() >= 4 {
+        let len = usize::from(area[2]);
+        if len < 4 || len > area.len() {
+            break;
+        }
+        let (signature, data) = (&area[..2], &area[4..len]);
+        match signature {
+            b"NM" if !data.is_empty() => {
+                name.get_or_insert_with(String::new)
+                    .push_str(&String::from_utf8_lossy(&data[1..]));
+            }
+            b"SL" if !data.is_empty() => {
+                let target = link.get_or_insert_with(String::new);
+                let mut components = &data[1..];
+                while components.len() >= 2 {
+                    let (flags, clen) = (components[0], usize::from(components[1]));
+                    let Some(content) = components.get(2..2 + clen) else {
+                        break;
+                    };
+                    let part = match flags & 0x06 {
+                        0x02 => ".".to_string(),
+                        0x04 => "..".to_string(),
+                        _ => String::from_utf8_lossy(content).into_owned(),
+                    };
+                    if flags & 0x08 != 0 {
+                        target.push('/');
+                    } else {
+                        if !continued && !target.is_empty() && !target.ends_with('/') {
+                            target.push('/');
+                        }
+                        target.push_str(&part);
+                    }
+                    continued = flags & 0x01 != 0;
+                    components = &components[2 + clen..];
+                }
+            }
+            _ => {}
+        }
+        area = &area[len..];
+    }
+    (name, link)
+}
+
+impl Tree for Iso<'_> {
+    type Node = Record;
+
+    fn root(&self) -> Result<Record> {
+        Ok(self.root.clone())
+    }
+
+    fn entry(&self, node: &Record) -> Entry {
+        match (&node.link, node.dir) {
+            (Some(target), _) => Entry::Symlink(target.clone()),
+            (None, true) => Entry::Dir,
+            (None, false) => Entry::File,
+        }
+    }
+
+    fn child(&self, dir: &Record, name: &str) -> Result<Option<Record>> {
+        Ok(self.records(dir)?.into_iter().find(|r| {
+            r.name == name
+                || (r.name.eq_ignore_ascii_case(name) && !r.name.contains(char::is_lowercase))
+        }))
+    }
+
+    fn read(&self, file: &Record) -> Result<Vec<u8>> {
+        if u64::from(file.size) > MAX_ICON {
+            bail!("file larger than {MAX_ICON} bytes");
+        }
+        read_at(
+            self.file,
+            u64::from(file.extent) * self.block,
+            file.size as usize,
+        )
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::super::tests::{elf_runtime, temp_dir, PNG};
+    use super::super::{read_dir_icon, read_path};
+    use super::*;
+
+    fn record(extent: u32, size: u32, dir: bool, iso_name: &[u8], system_use: &[u8]) -> Vec<u8> {
+        let mut r = vec![0u8; 33];
+        r[2..6].copy_from_slice(&extent.to_le_bytes());
+        r[6..10].copy_from_slice(&extent.to_be_bytes());
+        r[10..14].copy_from_slice(&size.to_le_bytes());
+        r[14..18].copy_from_slice(&size.to_be_bytes());
+        r[25] = if dir { 2 } else { 0 };
+        r[32] = iso_name.len() as u8;
+        r.extend(iso_name);
+        if iso_name.len().is_multiple_of(2) {
+            r.push(0);
+        }
+        r.extend(system_use);
+        if r.len() % 2 == 1 {
+            r.push(0);
+        }
+        r[0] = r.len() as u8;
+        r
+    }
+
+    fn nm(name: &str) -> Vec<u8> {
+        let mut e = vec![b'N', b'M', 5 + name.len() as u8, 1, 0];
+        e.extend(name.as_bytes());
+        e
+    }
+
+    /// `SL` with plain components.
+    fn sl(components: &[&str]) -> Vec<u8> {
+        let mut data = vec![0u8];
+        for c in components {
+            data.extend([0, c.len() as u8]);
+            data.extend(c.as_bytes());
+        }
+        let mut e = vec![b'S', b'L', 4 + data.len() as u8, 1];
+        e.extend(data);
+        e
+    }
+
+    fn sector(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut s = records.concat();
+        s.resize(SECTOR as usize, 0);
+        s
+    }
+
+    /// A type 1 AppImage: `/.DirIcon -> icons/app.png` (Rock Ridge) and
+    /// `/ICONS/APP.PNG;1` named `app.png`.
+    pub fn image() -> Vec<u8> {
+        let (root, icons, data) = (18u32, 19u32, 20u32);
+        let mut image = elf_runtime(1);
+        image.resize(16 * SECTOR as usize, 0);
+
+        let root_self = record(root, SECTOR as u32, true, &[0], &[]);
+        let mut pvd = vec![0u8; SECTOR as usize];
+        pvd[0] = 1;
+        pvd[1..6].copy_from_slice(b"CD001");
+        pvd[6] = 1;
+        pvd[128..130].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+        pvd[130..132].copy_from_slice(&(SECTOR as u16).to_be_bytes());
+        pvd[156..156 + root_self.len()].copy_from_slice(&root_self);
+        image.extend(pvd);
+        let mut terminator = vec![0u8; SECTOR as usize];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+        image.extend(terminator);
+
+        image.extend(sector(&[
+            root_self.clone(),
+            record(root, SECTOR as u32, true, &[1], &[]),
+            record(
+                0,
+                0,
+                false,
+                b"DIRICON.;1",
+                &[nm(".DirIcon"), sl(&["icons", "app.png"])].concat(),
+            ),
+            record(icons, SECTOR as u32, true, b"ICONS", &nm("icons")),
+        ]));
+        image.extend(sector(&[
+            record(icons, SECTOR as u32, true, &[0], &[]),
+            record(root, SECTOR as u32, true, &[1], &[]),
+            record(data, PNG.len() as u32, false, b"APP.PNG;1", &nm("app.png")),
+            record(data, PNG.len() as u32, false, b"LEGACY.TXT;1", &[]),
+        ]));
+        image.extend(PNG);
+        image
+    }
+
+    #[test]
+    fn reads_rock_ridge_names_and_symlinks() {
+        let dir = temp_dir("iso9660");
+        let path = dir.join("Editor.AppImage");
+        std::fs::write(&path, image()).unwrap();
+
+        assert_eq!(read_dir_icon(&path).unwrap(), PNG);
+        let file = File::open(&path).unwrap();
+        let iso = Iso::open(&file).unwrap();
+        assert_eq!(read_path(&iso, "icons/app.png").unwrap(), PNG);
+        // Without Rock Ridge, the ISO name matches in any case.
+        assert_eq!(read_path(&iso, "icons/legacy.txt").unwrap(), PNG);
+        assert!(read_path(&iso, "ICONS/APP.PNG").is_err());
+        assert_eq!(
+            rock_ridge(&[b"SL".as_slice(), &[12, 1, 0, 8, 0, 0, 3], b"usr"].concat()).1,
+            Some("/usr".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+//! Icons shipped inside AppImages, snaps and flatpaks themselves.
+//!
+//! Strategy: the theme lookup only finds an icon when a `.desktop` entry names
+//! it, which AppImages rarely have. [`icon`] asks the package's own bundle:
+//!
+//! - AppImage: the `.DirIcon` at the root of the embedded filesystem, read
+//!   straight from the file and never executed: the squashfs image after the
+//!   ELF runtime (type 2, see [`squashfs`]) or the ISO 9660 image the whole
+//!   file is (type 1, see [`iso9660`]). `.DirIcon` is usually a symlink into
+//!   the image, which is followed. The icon is written to
+//!   `$XDG_CACHE_HOME/scope/icons/bundles`, keyed by the AppImage's path and
+//!   mtime, so it is extracted once per version.
+//! - Snap: the icon under `/snap/<name>/current/meta/gui/`.
+//! - Flatpak: the app's exported `hicolor` icons
+//!   (`<installation>/app/<id>/current/active/export/share/icons`), the one
+//!   closest to [`super::ICON_SIZE`].
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::package::{InstallScope, InstalledPackage, PackageSource};
+
+mod iso9660;
+mod squashfs;
+
+/// Largest icon read out of a bundle.
+const MAX_ICON: u64 = 4 << 20;
+
+/// The bundle's own icon for `pkg`, as a file the protocol can serve.
+pub fn icon(pkg: &InstalledPackage, scale: u32) -> Option<PathBuf> {
+    match pkg.source {
+        PackageSource::AppImage => {
+            let dir = crate::system::cache_dir()?.join("icons/bundles");
+            appimage_icon(Path::new(&pkg.package_id), &dir).ok()
+        }
+        PackageSource::Snap => snap_icon(&snap_root()?.join(&pkg.package_id).join("current")),
+        PackageSource::Flatpak => {
+            let app = flatpak_installation(pkg.install_scope)?
+                .join("app")
+                .join(&pkg.package_id)
+                .join("current/active");
+            flatpak_icon(&app, &pkg.package_id, scale)
+        }
+        _ => None,
+    }
+}
+
+/// The AppImage's `.DirIcon`, extracted into `cache` unless already there.
+fn appimage_icon(appimage: &Path, cache: &Path) -> Result<PathBuf> {
+    let key = super::thumbnail::file_key(appimage)?;
+    for ext in ["png", "svg"] {
+        let cached = cache.join(format!("{key}.{ext}"));
+        if cached.is_file() {
+            return Ok(cached);
+        }
+    }
+
+    let data = read_dir_icon(appimage)?;
+    let ext = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if String::from_utf8_lossy(&data[..data.len().min(1024)]).contains("<svg") {
+        "svg"
+    } else {
+        bail!("{}: .DirIcon is neither PNG nor SVG", appimage.display());
+    };
+    fs::create_dir_all(cache)?;
+    // Icons of the AppImage's earlier versions at this path.
+    super::thumbnail::prune(cache, &key);
+    let path = cache.join(format!("{key}.{ext}"));
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+/// The bytes of `.DirIcon` in the AppImage's embedded filesystem.
+fn read_dir_icon(appimage: &Path) -> Result<Vec<u8>> {
+    let file = File::open(appimage)?;
+    let mut magic = [0u8; 11];
+    file.read_exact_at(&mut magic, 0)?;
+    if &magic[..4] != b"\x7fELF" || &magic[8..10] != b"AI" {
+        bail!("{} is not an AppImage", appimage.display());
+    }
+    match magic[10] {
+        1 => read_path(&iso9660::Iso::open(&file)?, ".DirIcon"),
+        2 => read_path(
+            &squashfs::Squashfs::open(&file, elf_size(&file)?)?,
+            ".DirIcon",
+        ),
+        other => bail!("unknown AppImage type {other}"),
+    }
+}
+
+/// Where the ELF runtime ends: the end of its section header table, which
+/// `appimagetool` places last.
+fn elf_size(file: &File) -> Result<u64> {
+    let mut header = [0u8; 64];
+    file.read_exact_at(&mut header, 0)?;
+    let u16_at = |at: usize| u64::from(u16::from_le_bytes([header[at], header[at + 1]]));
+    let (shoff, entsize, count) = match header[4] {
+        1 => {
+            let shoff = u32::from_le_bytes(header[0x20..0x24].try_into()?);
+            (u64::from(shoff), u16_at(0x2e), u16_at(0x30))
+        }
+        2 => {
+            let shoff = u64::from_le_bytes(header[0x28..0x30].try_into()?);
+            (shoff, u16_at(0x3a), u16_at(0x3c))
+        }
+        class => bail!("unknown ELF class {class}"),
+    };
+    if header[5] != 1 {
+        bail!("big-endian ELF runtime");
+    }
+    shoff
+        .checked_add(entsize * count)
+        .context("ELF section header table out of range")
+}
+
+/// What a path in an embedded filesystem names.
+enum Entry {
+    Dir,
+    File,
+    Symlink(String),
+}
+
+/// A read-only filesystem image.
+trait Tree {
+    type Node: Clone;
+    fn root(&self) -> Result<Self::Node>;
+    fn entry(&self, node: &Self::Node) -> Entry;
+    /// The child `name` of the directory `dir`, if there is one.
+    fn child(&self, dir: &Self::Node, name: &str) -> Result<Option<Self::Node>>;
+    /// The contents of a file, at most [`MAX_ICON`] bytes.
+    fn read(&self, file: &Self::Node) -> Result<Vec<u8>>;
+}
+
+/// The file at `path` from the root of `tree`, following symlinks as long as
+/// they stay inside the image.
+fn read_path<T: Tree>(tree: &T, path: &str) -> Result<Vec<u8>> {
+    let mut ancestry = vec![tree.root()?];
+    let mut pending: VecDeque<String> = path.split('/').map(str::to_string).collect();
+    let mut links = 0;
+    while let Some(name) = pending.pop_front() {
+        match name.as_str() {
+            "" | "." => continue,
+            ".." => {
+                if ancestry.len() > 1 {
+                    ancestry.pop();
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let dir = ancestry.last().expect("the root is never popped");
+        if !matches!(tree.entry(dir), Entry::Dir) {
+            bail!("{path}: not a directory");
+        }
+        let node = tree
+            .child(dir, &name)?
+            .with_context(|| format!("{path}: '{name}' not found"))?;
+        match tree.entry(&node) {
+            Entry::Symlink(target) => {
+                links += 1;
+                if links > 8 {
+                    bail!("{path}: too many symlinks");
+                }
+                if target.starts_with('/') {
+                    ancestry.truncate(1);
+                }
+                for part in target.split('/').rev() {
+                    pending.push_front(part.to_string());
+                }
+            }
+            Entry::Dir | Entry::File => ancestry.push(node),
+        }
+    }
+    let file = ancestry.last().expect("the root is never popped");
+    match tree.entry(file) {
+        Entry::File => tree.read(file),
+        _ => bail!("{path}: not a file"),
+    }
+}
+
+/// `/snap`, or `/var/lib/snapd/snap` where distributions mount snaps there.
+fn snap_root() -> Option<PathBuf> {
+    ["/snap", "/var/lib/snapd/snap"]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|p| p.is_dir())
+}
+
+/// The icon in a snap's `meta/gui/`: `icon.svg`/`icon.png` as `snapcraft`
+/// writes them, else the first image there.
+fn snap_icon(snap: &Path) -> Option<PathBuf> {
+    let gui = snap.join("meta/gui");
+    ["icon.svg", "icon.png"]
+        .iter()
+        .map(|name| gui.join(name))
+        .find(|p| p.is_file())
+        .or_else(|| {
+            let mut images: Vec<PathBuf> = fs::read_dir(&gui)
+                .ok()?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| matches!(extension(p).as_str(), "svg" | "png"))
+                .collect();
+            images.sort();
+            images.into_iter().next()
+        })
+}
+
+/// The system or per-user flatpak installation.
+fn flatpak_installation(scope: Option<InstallScope>) -> Option<PathBuf> {
+    match scope {
+        Some(InstallScope::User) => {
+            let data = std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share"))
+                })?;
+            Some(data.join("flatpak"))
+        }
+        Some(InstallScope::System) | None => Some(PathBuf::from("/var/lib/flatpak")),
+    }
+}
+
+/// `<id>.svg` or `<id>.png` in the app's exported `hicolor` directories: a
+/// scalable one, or the size closest to [`super::ICON_SIZE`] at `scale`.
+fn flatpak_icon(app: &Path, id: &str, scale: u32) -> Option<PathBuf> {
+    let hicolor = app.join("export/share/icons/hicolor");
+    let wanted = super::ICON_SIZE * scale;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&hicolor)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    dirs.sort();
+    dirs.into_iter()
+        .filter_map(|dir| {
+            let name = dir.file_name()?.to_str()?.to_string();
+            let (distance, ext) = if name == "scalable" {
+                (0, "svg")
+            } else {
+                let (size, dir_scale) = name.split_once('@').unwrap_or((&name, "1"));
+                let (width, _) = size.split_once('x')?;
+                let pixels = width.parse::<u32>().ok()? * dir_scale.parse::<u32>().ok()?;
+                (pixels.abs_diff(wanted), "png")
+            };
+            let icon = dir.join("apps").join(format!("{id}.{ext}"));
+            icon.is_file().then_some((distance, icon))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, icon)| icon)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// `len` bytes at `offset` of `file`.
+fn read_at(file: &File, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    file.read_exact_at(&mut buf, offset)
+        .with_context(|| format!("reading {len} bytes at {offset}"))?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR fixture icon";
+    pub(super) const SVG: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
+
+    pub(super) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scope-bundle-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// An ELF64 header whose section table ends at byte 64, marked as an
+    /// AppImage of `kind`.
+    pub(super) fn elf_runtime(kind: u8) -> Vec<u8> {
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[8..11].copy_from_slice(&[b'A', b'I', kind]);
+        elf[0x28..0x30].copy_from_slice(&64u64.to_le_bytes());
+        elf
+    }
+
+    #[test]
+    fn extracts_and_caches_appimage_icons() {
+        let dir = temp_dir("appimage");
+        let cache = dir.join("cache");
+        let appimage = dir.join("Editor-1.0-x86_64.AppImage");
+        let mut image = elf_runtime(2);
+        image.extend(squashfs::tests::image());
+        fs::write(&appimage, &image).unwrap();
+
+        let icon = appimage_icon(&appimage, &cache).unwrap();
+        assert_eq!(icon.extension().unwrap(), "png");
+        assert_eq!(fs::read(&icon).unwrap(), PNG);
+        // Cached: found again without reading the AppImage.
+        fs::write(&icon, b"cached").unwrap();
+        assert_eq!(appimage_icon(&appimage, &cache).unwrap(), icon);
+
+        let broken = dir.join("Broken.AppImage");
+        fs::write(&broken, elf_runtime(2)).unwrap();
+        assert!(appimage_icon(&broken, &cache).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn survives_corrupt_headers() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("Corrupt.AppImage");
+        let mut type2 = elf_runtime(2);
+        type2.extend(squashfs::tests::image());
+        let type1 = iso9660::tests::image();
+        // Every header word of the ELF runtime and squashfs superblock, and
+        // the ISO 9660 volume descriptor and root directory, set to extremes.
+        let fields = (0..160)
+            .step_by(2)
+            .map(|at| (&type2, at))
+            .chain(
+                (16 * 2048..16 * 2048 + 256)
+                    .step_by(2)
+                    .map(|at| (&type1, at)),
+            )
+            .chain(
+                (18 * 2048..18 * 2048 + 128)
+                    .step_by(2)
+                    .map(|at| (&type1, at)),
+            );
+        for (image, at) in fields {
+            for value in [
+                [0xff; 8],
+                [0; 8],
+                [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+            ] {
+                let mut corrupt = image.clone();
+                let end = (at + 8).min(corrupt.len());
+                corrupt[at..end].copy_from_slice(&value[..end - at]);
+                fs::write(&path, &corrupt).unwrap();
+                // Either answer will do, as long as there is one.
+                let _ = read_dir_icon(&path);
+            }
+        }
+        // A section header table past the end of the address space.
+        let mut elf = type2.clone();
+        elf[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        elf[0x3a..0x3e].copy_from_slice(&[64, 0, 1, 0]);
+        fs::write(&path, &elf).unwrap();
+        assert!(read_dir_icon(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_snap_and_flatpak_icons() {
+        let dir = temp_dir("stores");
+        let gui = dir.join("snap/meta/gui");
+        fs::create_dir_all(&gui).unwrap();
+        fs::write(gui.join("editor.desktop"), "").unwrap();
+        fs::write(gui.join("editor.png"), PNG).unwrap();
+        assert_eq!(snap_icon(&dir.join("snap")), Some(gui.join("editor.png")));
+        fs::write(gui.join("icon.svg"), SVG).unwrap();
+        assert_eq!(snap_icon(&dir.join("snap")), Some(gui.join("icon.svg")));
+
+        let app = dir.join("flatpak");
+        let hicolor = app.join("export/share/icons/hicolor");
+        for size in ["32x32", "128x128", "64x64@2", "256x256"] {
+            fs::create_dir_all(hicolor.join(size).join("apps")).unwrap();
+            fs::write(hicolor.join(size).join("apps/org.example.Editor.png"), PNG).unwrap();
+        }
+        let found = |scale| flatpak_icon(&app, "org.example.Editor", scale);
+        assert_eq!(
+            found(1),
+            Some(hicolor.join("32x32/apps/org.example.Editor.png"))
+        );
+        assert_eq!(
+            found(2),
+            Some(hicolor.join("128x128/apps/org.example.Editor.png"))
+        );
+        fs::create_dir_all(hicolor.join("scalable/apps")).unwrap();
+        fs::write(hicolor.join("scalable/apps/org.example.Editor.svg"), SVG).unwrap();
+        assert_eq!(
+            found(1),
+            Some(hicolor.join("scalable/apps/org.example.Editor.svg"))
+        );
+        assert_eq!(flatpak_icon(&app, "org.example.Other", 1), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
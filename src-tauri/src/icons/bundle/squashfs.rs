@@ -0,0 +1,535 @@
+//! Just enough squashfs 4.0 to read one small file from a type 2 AppImage.
+//!
+//! Strategy: the image starts at a byte offset inside the AppImage, so
+//! everything is read with positioned reads relative to it. Metadata (inodes,
+//! directory listings, the fragment table) lives in 8 KiB blocks that are
+//! compressed unless their header says otherwise; file contents are whole
+//! blocks plus an optional tail packed into a shared fragment block. gzip
+//! (zlib) and zstd, what `appimagetool` writes, are supported; other
+//! compressors are an error. Every offset and size comes from the file, so
+//! they are bounded and added with overflow checks: a corrupt image is an
+//! error, never a panic.
+
+use std::fs::File;
+use std::io::Read;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use super::{read_at, Entry, Tree, MAX_ICON};
+
+const MAGIC: &[u8; 4] = b"hsqs";
+const METADATA_BLOCK: usize = 8192;
+/// Set on a metadata block header when the block is stored uncompressed.
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+/// Set on a data block or fragment size when it is stored uncompressed.
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+const NO_FRAGMENT: u32 = 0xffff_ffff;
+/// Data block sizes squashfs allows.
+const BLOCK_SIZES: std::ops::RangeInclusive<u32> = 4096..=1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// An inode, as far as reading files is concerned.
+#[derive(Debug, Clone)]
+pub(super) enum Inode {
+    Dir {
+        /// Listing start, relative to the directory table.
+        block: u64,
+        offset: usize,
+        /// Listing size in bytes.
+        size: usize,
+    },
+    File {
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: usize,
+        block_sizes: Vec<u32>,
+    },
+    Symlink(String),
+    Other,
+}
+
+pub struct Squashfs<'a> {
+    file: &'a File,
+    /// Where the image starts in `file`.
+    base: u64,
+    compression: Compression,
+    block_size: u32,
+    root: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+impl<'a> Squashfs<'a> {
+    pub fn open(file: &'a File, base: u64) -> Result<Self> {
+        let sb = read_at(file, base, 96).context("reading the squashfs superblock")?;
+        ensure!(&sb[..4] == MAGIC, "no squashfs image at offset {base}");
+        let u16_at = |at: usize| u16::from_le_bytes([sb[at], sb[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(sb[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(sb[at..at + 8].try_into().unwrap());
+        ensure!(
+            u16_at(28) == 4,
+            "squashfs version {} unsupported",
+            u16_at(28)
+        );
+        let compression = match u16_at(20) {
+            1 => Compression::Gzip,
+            6 => Compression::Zstd,
+            other => bail!("squashfs compression {other} unsupported"),
+        };
+        let block_size = u32_at(12);
+        ensure!(
+            block_size.is_power_of_two() && BLOCK_SIZES.contains(&block_size),
+            "bad squashfs block size {block_size}"
+        );
+        Ok(Self {
+            file,
+            base,
+            compression,
+            block_size,
+            root: u64_at(32),
+            inode_table: u64_at(64),
+            directory_table: u64_at(72),
+            fragment_table: u64_at(80),
+        })
+    }
+
+    fn read(&self, pos: u64, len: usize) -> Result<Vec<u8>> {
+        read_at(self.file, add(self.base, pos)?, len)
+    }
+
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let limit = limit as u64 + 1;
+        match self.compression {
+            Compression::Gzip => flate2::read::ZlibDecoder::new(data)
+                .take(limit)
+                .read_to_end(&mut out)?,
+            Compression::Zstd => ruzstd::decoding::StreamingDecoder::new(data)
+                .map_err(|e| anyhow::anyhow!("zstd: {e}"))?
+                .take(limit)
+                .read_to_end(&mut out)?,
+        };
+        ensure!(
+            out.len() < limit as usize,
+            "block larger than {}",
+            limit - 1
+        );
+        Ok(out)
+    }
+
+    /// A reader over the metadata stream starting `offset` bytes into the
+    /// block at `pos`.
+    fn metadata(&self, pos: u64, offset: usize) -> Result<Metadata<'_, 'a>> {
+        let mut meta = Metadata {
+            fs: self,
+            next: pos,
+            buf: Vec::new(),
+            at: offset,
+        };
+        meta.load()?;
+        ensure!(offset <= meta.buf.len(), "metadata offset out of range");
+        Ok(meta)
+    }
+
+    /// The inode an inode reference points at: the metadata block in the
+    /// upper bits, the offset into it in the lower 16.
+    fn inode(&self, reference: u64) -> Result<Inode> {
+        let mut m = self.metadata(
+            add(self.inode_table, reference >> 16)?,
+            (reference & 0xffff) as usize,
+        )?;
+        let kind = m.u16()?;
+        m.skip(14)?;
+        Ok(match kind {
+            1 => {
+                let block = u64::from(m.u32()?);
+                m.skip(4)?;
+                let size = usize::from(m.u16()?);
+                let offset = usize::from(m.u16()?);
+                Inode::Dir {
+                    block,
+                    offset,
+                    size: size.saturating_sub(3),
+                }
+            }
+            8 => {
+                m.skip(4)?;
+                let size = m.u32()? as usize;
+                let block = u64::from(m.u32()?);
+                m.skip(6)?;
+                let offset = usize::from(m.u16()?);
+                Inode::Dir {
+                    block,
+                    offset,
+                    size: size.saturating_sub(3),
+                }
+            }
+            2 => {
+                let blocks_start = u64::from(m.u32()?);
+                let fragment = m.u32()?;
+                let fragment_offset = m.u32()? as usize;
+                let size = u64::from(m.u32()?);
+                self.file_inode(&mut m, blocks_start, size, fragment, fragment_offset)?
+            }
+            9 => {
+                let blocks_start = m.u64()?;
+                let size = m.u64()?;
+                m.skip(12)?;
+                let fragment = m.u32()?;
+                let fragment_offset = m.u32()? as usize;
+                m.skip(4)?;
+                self.file_inode(&mut m, blocks_start, size, fragment, fragment_offset)?
+            }
+            3 | 10 => {
+                m.skip(4)?;
+                let len = m.u32()? as usize;
+                ensure!(len <= 4096, "symlink target too long");
+                Inode::Symlink(String::from_utf8_lossy(&m.take(len)?).into_owned())
+            }
+            _ => Inode::Other,
+        })
+    }
+
+    fn file_inode(
+        &self,
+        m: &mut Metadata,
+        blocks_start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: usize,
+    ) -> Result<Inode> {
+        ensure!(size <= MAX_ICON, "file larger than {MAX_ICON} bytes");
+        let block_size = u64::from(self.block_size.max(1));
+        let blocks = if fragment == NO_FRAGMENT {
+            size.div_ceil(block_size)
+        } else {
+            size / block_size
+        };
+        let block_sizes = (0..blocks).map(|_| m.u32()).collect::<Result<_>>()?;
+        Ok(Inode::File {
+            blocks_start,
+            size,
+            fragment,
+            fragment_offset,
+            block_sizes,
+        })
+    }
+
+    /// A data block or fragment of `size_word` at `pos`, uncompressed.
+    fn data_block(&self, pos: u64, size_word: u32) -> Result<Vec<u8>> {
+        let stored = (size_word & (DATA_UNCOMPRESSED - 1)) as usize;
+        ensure!(stored <= self.block_size as usize, "data block too large");
+        let data = self.read(pos, stored)?;
+        if size_word & DATA_UNCOMPRESSED != 0 {
+            Ok(data)
+        } else {
+            self.decompress(&data, self.block_size as usize)
+        }
+    }
+
+    /// The start and size word of fragment block `index`.
+    fn fragment(&self, index: u32) -> Result<(u64, u32)> {
+        // The table is a list of pointers to metadata blocks of 512 entries.
+        let pointer = self.read(add(self.fragment_table, 8 * u64::from(index / 512))?, 8)?;
+        let block = u64::from_le_bytes(pointer.try_into().unwrap());
+        let mut m = self.metadata(block, (index % 512) as usize * 16)?;
+        let start = m.u64()?;
+        let size = m.u32()?;
+        Ok((start, size))
+    }
+}
+
+impl Tree for Squashfs<'_> {
+    type Node = Inode;
+
+    fn root(&self) -> Result<Inode> {
+        self.inode(self.root)
+    }
+
+    fn entry(&self, node: &Inode) -> Entry {
+        match node {
+            Inode::Dir { .. } => Entry::Dir,
+            Inode::Symlink(target) => Entry::Symlink(target.clone()),
+            Inode::File { .. } | Inode::Other => Entry::File,
+        }
+    }
+
+    fn child(&self, dir: &Inode, name: &str) -> Result<Option<Inode>> {
+        let Inode::Dir {
+            block,
+            offset,
+            size,
+        } = dir
+        else {
+            bail!("not a directory");
+        };
+        let mut m = self.metadata(add(self.directory_table, *block)?, *offset)?;
+        let mut left = *size;
+        // Headers of up to 256 entries, each sharing an inode metadata block.
+        while left >= 12 {
+            let count = m.u32()? as usize + 1;
+            let start = u64::from(m.u32()?);
+            m.skip(4)?;
+            left -= 12;
+            for _ in 0..count {
+                let offset = u64::from(m.u16()?);
+                m.skip(4)?;
+                let len = usize::from(m.u16()?) + 1;
+                let entry = m.take(len)?;
+                left = left.saturating_sub(8 + len);
+                if entry == name.as_bytes() {
+                    return self.inode((start << 16) | offset).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn read(&self, file: &Inode) -> Result<Vec<u8>> {
+        let Inode::File {
+            blocks_start,
+            size,
+            fragment,
+            fragment_offset,
+            block_sizes,
+        } = file
+        else {
+            bail!("not a regular file");
+        };
+        let mut out = Vec::with_capacity(*size as usize);
+        let mut pos = *blocks_start;
+        for word in block_sizes {
+            let want = (*size - out.len() as u64).min(u64::from(self.block_size)) as usize;
+            if *word == 0 {
+                // Sparse: a block of zeros.
+                out.resize(out.len() + want, 0);
+                continue;
+            }
+            let block = self.data_block(pos, *word)?;
+            out.extend_from_slice(&block[..want.min(block.len())]);
+            pos = add(pos, u64::from(word & (DATA_UNCOMPRESSED - 1)))?;
+        }
+        if *fragment != NO_FRAGMENT {
+            let (start, word) = self.fragment(*fragment)?;
+            let block = self.data_block(start, word)?;
+            let tail = (*size - out.len() as u64) as usize;
+            let data = block
+                .get(*fragment_offset..*fragment_offset + tail)
+                .context("fragment out of range")?;
+            out.extend_from_slice(data);
+        }
+        ensure!(out.len() as u64 == *size, "truncated file");
+        Ok(out)
+    }
+}
+
+/// `a + b`, for positions read from the image.
+fn add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b).context("offset out of range")
+}
+
+/// Sequential reads through consecutive metadata blocks.
+struct Metadata<'s, 'a> {
+    fs: &'s Squashfs<'a>,
+    /// Position of the next block to load.
+    next: u64,
+    buf: Vec<u8>,
+    at: usize,
+}
+
+impl Metadata<'_, '_> {
+    fn load(&mut self) -> Result<()> {
+        let header = self.fs.read(self.next, 2)?;
+        let header = u16::from_le_bytes([header[0], header[1]]);
+        let stored = usize::from(header & !METADATA_UNCOMPRESSED);
+        let data = self.fs.read(add(self.next, 2)?, stored)?;
+        let data = if header & METADATA_UNCOMPRESSED != 0 {
+            data
+        } else {
+            self.fs.decompress(&data, METADATA_BLOCK)?
+        };
+        // Reads are a few entries long: keeping earlier blocks is cheap.
+        self.buf.extend(data);
+        self.next = add(self.next, 2 + stored as u64)?;
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> Result<Vec<u8>> {
+        while self.buf.len() < self.at + len {
+            self.load()?;
+        }
+        let out = self.buf[self.at..self.at + len].to_vec();
+        self.at += len;
+        Ok(out)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(drop)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::io::Write;
+
+    use super::super::read_path;
+    use super::super::tests::{temp_dir, PNG, SVG};
+    use super::*;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn inode_header(kind: u16, number: u32) -> Vec<u8> {
+        let mut h = Vec::new();
+        h.extend(kind.to_le_bytes());
+        h.extend([0o755u16, 0, 0].iter().flat_map(|v| v.to_le_bytes()));
+        h.extend(0u32.to_le_bytes());
+        h.extend(number.to_le_bytes());
+        h
+    }
+
+    fn dir_inode(number: u32, listing_offset: usize, listing_len: usize) -> Vec<u8> {
+        let mut i = inode_header(1, number);
+        i.extend(0u32.to_le_bytes());
+        i.extend(2u32.to_le_bytes());
+        i.extend((listing_len as u16 + 3).to_le_bytes());
+        i.extend((listing_offset as u16).to_le_bytes());
+        i.extend(1u32.to_le_bytes());
+        i
+    }
+
+    /// A listing of `(name, inode offset, type)` in the single inode block.
+    fn listing(entries: &[(&str, usize, u16)]) -> Vec<u8> {
+        let mut l = Vec::new();
+        l.extend((entries.len() as u32 - 1).to_le_bytes());
+        l.extend(0u32.to_le_bytes());
+        l.extend(1u32.to_le_bytes());
+        for (name, offset, kind) in entries {
+            l.extend((*offset as u16).to_le_bytes());
+            l.extend(0i16.to_le_bytes());
+            l.extend(kind.to_le_bytes());
+            l.extend((name.len() as u16 - 1).to_le_bytes());
+            l.extend(name.as_bytes());
+        }
+        l
+    }
+
+    /// A gzip squashfs image: `/.DirIcon -> icons/app.png`, with
+    /// `icons/app.png` in a compressed data block and `icons/app.svg` in an
+    /// uncompressed fragment. The directory table is compressed, the inode
+    /// table is not.
+    pub fn image() -> Vec<u8> {
+        let mut image = vec![0u8; 96];
+
+        let png_at = image.len() as u64;
+        let png_block = zlib(PNG);
+        image.extend(&png_block);
+        let fragment_at = image.len() as u64;
+        image.extend(SVG);
+
+        // Inodes, in this order, at offsets fixed by their sizes.
+        let png_inode = 0;
+        let svg_inode = png_inode + 36;
+        let icons_inode = svg_inode + 32;
+        let link_inode = icons_inode + 32;
+        let link_target = "icons/app.png";
+        let root_inode = link_inode + 24 + link_target.len();
+
+        let icons_listing = listing(&[("app.png", png_inode, 2), ("app.svg", svg_inode, 2)]);
+        let root_listing = listing(&[(".DirIcon", link_inode, 3), ("icons", icons_inode, 1)]);
+
+        let mut inodes = inode_header(2, 1);
+        inodes.extend((png_at as u32).to_le_bytes());
+        inodes.extend(NO_FRAGMENT.to_le_bytes());
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend((PNG.len() as u32).to_le_bytes());
+        inodes.extend((png_block.len() as u32).to_le_bytes());
+        inodes.extend(inode_header(2, 2));
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend((SVG.len() as u32).to_le_bytes());
+        inodes.extend(dir_inode(3, 0, icons_listing.len()));
+        inodes.extend(inode_header(3, 4));
+        inodes.extend(1u32.to_le_bytes());
+        inodes.extend((link_target.len() as u32).to_le_bytes());
+        inodes.extend(link_target.as_bytes());
+        assert_eq!(inodes.len(), root_inode);
+        inodes.extend(dir_inode(5, icons_listing.len(), root_listing.len()));
+
+        let inode_table = image.len() as u64;
+        image.extend((inodes.len() as u16 | METADATA_UNCOMPRESSED).to_le_bytes());
+        image.extend(&inodes);
+
+        let directory_table = image.len() as u64;
+        let listings = zlib(&[icons_listing, root_listing].concat());
+        image.extend((listings.len() as u16).to_le_bytes());
+        image.extend(&listings);
+
+        let fragment_block = image.len() as u64;
+        let mut entry = fragment_at.to_le_bytes().to_vec();
+        entry.extend((SVG.len() as u32 | DATA_UNCOMPRESSED).to_le_bytes());
+        entry.extend(0u32.to_le_bytes());
+        image.extend((entry.len() as u16 | METADATA_UNCOMPRESSED).to_le_bytes());
+        image.extend(&entry);
+        let fragment_table = image.len() as u64;
+        image.extend(fragment_block.to_le_bytes());
+
+        let sb = &mut image[..96];
+        sb[..4].copy_from_slice(MAGIC);
+        sb[4..8].copy_from_slice(&5u32.to_le_bytes());
+        sb[12..16].copy_from_slice(&4096u32.to_le_bytes());
+        sb[16..20].copy_from_slice(&1u32.to_le_bytes());
+        sb[20..22].copy_from_slice(&1u16.to_le_bytes());
+        sb[22..24].copy_from_slice(&12u16.to_le_bytes());
+        sb[28..30].copy_from_slice(&4u16.to_le_bytes());
+        sb[32..40].copy_from_slice(&(root_inode as u64).to_le_bytes());
+        sb[64..72].copy_from_slice(&inode_table.to_le_bytes());
+        sb[72..80].copy_from_slice(&directory_table.to_le_bytes());
+        sb[80..88].copy_from_slice(&fragment_table.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn reads_files_through_directories_symlinks_and_fragments() {
+        let dir = temp_dir("squashfs");
+        let path = dir.join("image.squashfs");
+        // At an offset, as after an AppImage runtime.
+        let mut data = vec![0xee; 100];
+        data.extend(image());
+        std::fs::write(&path, data).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let fs = Squashfs::open(&file, 100).unwrap();
+        assert_eq!(read_path(&fs, ".DirIcon").unwrap(), PNG);
+        assert_eq!(read_path(&fs, "icons/app.svg").unwrap(), SVG);
+        assert_eq!(read_path(&fs, "icons/../icons/./app.png").unwrap(), PNG);
+        assert!(read_path(&fs, "icons/missing.png").is_err());
+        assert!(read_path(&fs, "icons").is_err());
+        assert!(Squashfs::open(&file, 0).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -7,98 +7,230 @@
 //! enough for desktop use:
 //!
 //! 1. If the value is an absolute path, use it directly (with extension fallback).
-//! 2. Otherwise search the current GTK icon theme, then `hicolor`, walking
-//!    `Inherits=` parent chains declared in each theme's `index.theme`.
+//! 2. Otherwise look the name up in the current GTK icon theme, its
+//!    `Inherits=` chain and `hicolor`, by each theme's `index.theme`
+//!    directories, at [`ICON_SIZE`] and the display's scale (see [`theme`]).
 //! 3. Finally fall back to `/usr/share/pixmaps/<name>.<ext>`.
 //!
+//! Packages without a `.desktop` icon get the one in their own bundle through
+//! [`resolve_bundled`] (see [`bundle`]).
+//!
 //! Architecture borrowed from the local `klauncher` reference
 //! (`src-tauri/src/platform/linux/icon_resolver.rs`), adapted for Scope and
 //! renamed so the module path matches the project layout in `AGENTS.md`.
 //!
 //! The frontend never receives broad filesystem access. It only ever gets
-//! `scope-icon://localhost/<absolute-path>` URLs produced here, and the
-//! [`crate::lib`] URI-scheme protocol serves exactly those resolved paths.
-
-use std::collections::HashMap;
+//! `scope-icon://localhost/<absolute-path>` URLs produced here, and every path
+//! handed out through [`icon_url`] is recorded. The `scope-icon://` protocol
+//! goes through [`serve`], which answers only for recorded paths with an
+//! image MIME type and refuses everything else, so a crafted URL cannot read
+//! other files. A `?size=N` query gets a PNG thumbnail from [`thumbnail`]
+//! rather than the original file.
+
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::package::InstalledPackage;
 
-/// Size directories searched, largest-useful first. `scalable` covers SVGs.
-const ICON_SIZES: &[&str] = &[
-    "512x512", "256x256", "128x128", "64x64", "48x48", "32x32", "24x24", "22x22", "16x16",
-    "scalable",
-];
+mod bundle;
+mod theme;
+mod thumbnail;
+pub mod watch;
 
-/// File extensions tried, in priority order. SVG renders crisply at any size.
+/// Icon size looked up in themes, in logical pixels: the largest the UI shows
+/// (56px in the detail view) rounded up to a common theme size.
+pub const ICON_SIZE: u32 = 64;
+
+/// File extensions tried for absolute and `pixmaps` icons, in priority order.
+/// SVG renders crisply at any size.
 const ICON_EXTENSIONS: &[&str] = &["svg", "png", "xpm"];
 
-static RESOLVED_CACHE: OnceLock<Mutex<HashMap<String, Option<PathBuf>>>> = OnceLock::new();
-static THEME_NAME: OnceLock<Option<String>> = OnceLock::new();
-static BASE_DIRS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+/// Everything derived from the current icon theme and scale. [`reload`]
+/// replaces it when either changes, dropping the memoized lookups with it.
+struct Lookup {
+    theme: Option<String>,
+    scale: u32,
+    themes: theme::Themes,
+    /// [`resolve`] results by `Icon=` value.
+    resolved: Mutex<HashMap<String, Option<PathBuf>>>,
+    /// [`resolve_bundled`] results by package key.
+    bundled: Mutex<HashMap<String, Option<PathBuf>>>,
+}
+
+impl Lookup {
+    fn new(base_dirs: Vec<PathBuf>, theme: Option<String>, scale: u32) -> Self {
+        Self {
+            theme,
+            scale,
+            themes: theme::Themes::new(base_dirs),
+            resolved: Mutex::new(HashMap::new()),
+            bundled: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn detect() -> Self {
+        let base_dirs = collect_base_dirs();
+        let theme = detect_icon_theme(&base_dirs);
+        Self::new(base_dirs, theme, detect_scale())
+    }
+}
+
+/// Where a resolved icon came from, so [`reresolve`] can ask again.
+#[derive(Clone)]
+enum Origin {
+    /// An `Icon=` name looked up in the theme (or `pixmaps`).
+    Name(String),
+    /// The package's own bundle, which may have icons for several scales.
+    Bundle,
+}
+
+static LOOKUP: Mutex<Option<Arc<Lookup>>> = Mutex::new(None);
+/// Paths handed out through [`icon_url`]: the only files [`serve`] reads.
+static SERVED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+/// Paths that depend on the theme or scale, by what resolved them, in any
+/// lookup so far.
+static ORIGINS: OnceLock<Mutex<HashMap<PathBuf, Origin>>> = OnceLock::new();
+
+/// URL prefix of every icon URL; the path follows.
+const URL_PREFIX: &str = "scope-icon://localhost";
+
+fn lookup() -> Arc<Lookup> {
+    LOOKUP
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Lookup::detect()))
+        .clone()
+}
+
+fn origins() -> &'static Mutex<HashMap<PathBuf, Origin>> {
+    ORIGINS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-fn cache() -> &'static Mutex<HashMap<String, Option<PathBuf>>> {
-    RESOLVED_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+/// Detect the icon theme and scale again. When either changed, later
+/// lookups start over in the new theme and `true` is returned; the caller
+/// hands out the new icons through [`reresolve`]. URLs handed out before
+/// stay servable.
+pub fn reload() -> bool {
+    install(Lookup::detect())
 }
 
-fn theme_name() -> &'static Option<String> {
-    THEME_NAME.get_or_init(detect_icon_theme)
+/// Look icons up in `theme` at `scale` from now on, searching `base_dirs`
+/// (user directories first), until the returned guard puts the previous
+/// lookup back.
+#[cfg(test)]
+pub(crate) fn use_theme(base_dirs: Vec<PathBuf>, theme: Option<String>, scale: u32) -> ThemeGuard {
+    let fresh = Arc::new(Lookup::new(base_dirs, theme, scale));
+    ThemeGuard(LOOKUP.lock().unwrap().replace(fresh))
 }
 
-fn base_dirs() -> &'static Vec<PathBuf> {
-    BASE_DIRS.get_or_init(collect_base_dirs)
+/// The lookup [`use_theme`] replaced, restored on drop so the theme does not
+/// leak into other tests.
+#[cfg(test)]
+pub(crate) struct ThemeGuard(Option<Arc<Lookup>>);
+
+#[cfg(test)]
+impl Drop for ThemeGuard {
+    fn drop(&mut self) {
+        if let Ok(mut current) = LOOKUP.lock() {
+            *current = self.0.take();
+        }
+    }
+}
+
+fn install(fresh: Lookup) -> bool {
+    let mut current = LOOKUP.lock().unwrap();
+    let changed = current
+        .as_ref()
+        .is_none_or(|l| l.theme != fresh.theme || l.scale != fresh.scale);
+    if changed {
+        *current = Some(Arc::new(fresh));
+    }
+    changed
 }
 
 /// Resolve an `Icon=` value to an absolute file path, or `None` if not found.
 ///
-/// Results are memoized in a process-global cache; repeated lookups for the
-/// same icon name are O(1).
+/// Results are memoized per icon theme; repeated lookups for the same icon
+/// name are O(1) until [`reload`] sees the theme change.
 pub fn resolve(icon_value: &str) -> Option<PathBuf> {
     if icon_value.is_empty() {
         return None;
     }
 
-    if let Some(cached) = cache().lock().unwrap().get(icon_value) {
+    let lookup = lookup();
+    if let Some(cached) = lookup.resolved.lock().unwrap().get(icon_value) {
         return cached.clone();
     }
 
-    let result = resolve_uncached(icon_value);
-    cache()
+    let result = resolve_uncached(&lookup, icon_value);
+    if let Some(path) = result
+        .as_ref()
+        .filter(|_| !Path::new(icon_value).is_absolute())
+    {
+        let origin = Origin::Name(icon_value.to_string());
+        origins().lock().unwrap().insert(path.clone(), origin);
+    }
+    lookup
+        .resolved
         .lock()
         .unwrap()
         .insert(icon_value.to_string(), result.clone());
     result
 }
 
-fn resolve_uncached(icon_value: &str) -> Option<PathBuf> {
+/// The icon `pkg` ships in its own bundle: an AppImage's `.DirIcon`, a snap's
+/// `meta/gui` icon, a flatpak's exported icon. Memoized per package like
+/// [`resolve`].
+pub fn resolve_bundled(pkg: &InstalledPackage) -> Option<PathBuf> {
+    let lookup = lookup();
+    if let Some(cached) = lookup.bundled.lock().unwrap().get(&pkg.key) {
+        return cached.clone();
+    }
+    let result = bundle::icon(pkg, lookup.scale);
+    if let Some(path) = &result {
+        origins()
+            .lock()
+            .unwrap()
+            .insert(path.clone(), Origin::Bundle);
+    }
+    lookup
+        .bundled
+        .lock()
+        .unwrap()
+        .insert(pkg.key.clone(), result.clone());
+    result
+}
+
+/// The icon URL of `pkg` resolved again after [`reload`], when its icon
+/// came from the theme or its bundle: the new URL, or `Some(None)` when the
+/// current theme has no such icon. `None` for icons that do not depend on
+/// the theme, such as a scanner's own files, which stay as they are.
+pub fn reresolve(pkg: &InstalledPackage) -> Option<Option<String>> {
+    let url = pkg.icon.as_deref()?;
+    let path = PathBuf::from(url.strip_prefix(URL_PREFIX).map(percent_decode_path)?);
+    let origin = origins().lock().unwrap().get(&path).cloned()?;
+    let fresh = match origin {
+        Origin::Name(name) => resolve(&name),
+        Origin::Bundle => resolve_bundled(pkg),
+    };
+    Some(fresh.map(|path| icon_url(&path)))
+}
+
+fn resolve_uncached(lookup: &Lookup, icon_value: &str) -> Option<PathBuf> {
     let path = Path::new(icon_value);
     if path.is_absolute() {
         return resolve_absolute_icon(path);
     }
 
-    // Search the active theme first, then the universal `hicolor` fallback.
-    // Parent themes declared via `Inherits=` are walked recursively inside
-    // `lookup_in_theme`.
-    let mut themes = Vec::new();
-    if let Some(current) = theme_name() {
-        themes.push(current.as_str());
-    }
-    themes.push("hicolor");
-
-    let basedirs = base_dirs();
-    for theme in &themes {
-        for basedir in basedirs {
-            let theme_root = basedir.join(theme);
-            if !theme_root.is_dir() {
-                continue;
-            }
-            if let Some(found) =
-                lookup_in_theme(&theme_root, icon_value, basedirs, &mut HashMap::new())
-            {
-                return Some(found);
-            }
-        }
+    if let Some(found) =
+        lookup
+            .themes
+            .find(icon_value, ICON_SIZE, lookup.scale, lookup.theme.as_deref())
+    {
+        return Some(found);
     }
 
     lookup_in_pixmaps(icon_value)
@@ -152,7 +284,7 @@ fn collect_base_dirs() -> Vec<PathBuf> {
 
 /// Detect the active GTK icon theme name. Best-effort: falls back to none
 /// (which leaves `hicolor` as the only searched theme).
-fn detect_icon_theme() -> Option<String> {
+fn detect_icon_theme(base_dirs: &[PathBuf]) -> Option<String> {
     if let Ok(output) = std::process::Command::new("gsettings")
         .args(["get", "org.gnome.desktop.interface", "icon-theme"])
         .output()
@@ -162,119 +294,63 @@ fn detect_icon_theme() -> Option<String> {
                 .trim()
                 .trim_matches('\'')
                 .to_string();
-            if !value.is_empty() && base_dirs().iter().any(|dir| dir.join(&value).is_dir()) {
+            if !value.is_empty() && base_dirs.iter().any(|dir| dir.join(&value).is_dir()) {
                 return Some(value);
             }
         }
     }
 
-    if let Ok(home) = env::var("HOME") {
-        let settings_ini = PathBuf::from(&home).join(".config/gtk-3.0/settings.ini");
-        if let Ok(content) = fs::read_to_string(&settings_ini) {
-            for line in content.lines() {
-                if let Some(value) = line.strip_prefix("gtk-icon-theme-name=") {
-                    let value = value.trim();
-                    if !value.is_empty() {
-                        return Some(value.to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    None
+    settings_ini_dirs()
+        .iter()
+        .filter_map(|dir| fs::read_to_string(dir.join("settings.ini")).ok())
+        .find_map(|content| settings_ini_theme(&content))
 }
 
-/// Search one theme directory for `icon_name`, then recurse into inherited
-/// parent themes. `parent_cache` avoids re-parsing `index.theme` for the same
-/// theme across sibling searches.
-fn lookup_in_theme(
-    theme_root: &Path,
-    icon_name: &str,
-    basedirs: &[PathBuf],
-    parent_cache: &mut HashMap<PathBuf, Vec<String>>,
-) -> Option<PathBuf> {
-    let parents = if let Some(cached) = parent_cache.get(theme_root) {
-        cached.clone()
-    } else {
-        let parents = parse_theme_parents(&theme_root.join("index.theme"));
-        parent_cache.insert(theme_root.to_path_buf(), parents.clone());
-        parents
-    };
-
-    // Prefer the `apps` subdirectory (where application icons live), then a
-    // bare size-directory match for themes that don't categorize by context.
-    for size in ICON_SIZES {
-        for context in ["apps", ""] {
-            let dir = if context.is_empty() {
-                theme_root.join(size)
-            } else {
-                theme_root.join(size).join(context)
-            };
-            if !dir.is_dir() {
-                continue;
-            }
-            for ext in ICON_EXTENSIONS {
-                let path = dir.join(format!("{icon_name}.{ext}"));
-                if path.is_file() {
-                    return Some(path);
-                }
-            }
-        }
-    }
-
-    for parent_name in &parents {
-        for basedir in basedirs {
-            let parent_root = basedir.join(parent_name);
-            if parent_root.is_dir() && parent_root != theme_root {
-                if let Some(found) =
-                    lookup_in_theme(&parent_root, icon_name, basedirs, parent_cache)
-                {
-                    return Some(found);
-                }
-            }
-        }
-    }
-
-    None
+/// GTK's `settings.ini` directories, GTK 3 first: where desktops other
+/// than GNOME (and GNOME's settings daemon) record the icon theme.
+fn settings_ini_dirs() -> Vec<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    config
+        .map(|config| vec![config.join("gtk-3.0"), config.join("gtk-4.0")])
+        .unwrap_or_default()
 }
 
-/// Parse the `Inherits=` line from a theme's `index.theme` `[Icon Theme]` table.
-fn parse_theme_parents(index_theme_path: &Path) -> Vec<String> {
-    let Ok(content) = fs::read_to_string(index_theme_path) else {
-        return Vec::new();
-    };
+/// The `gtk-icon-theme-name` in a `settings.ini`.
+fn settings_ini_theme(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        (key.trim() == "gtk-icon-theme-name" && !value.is_empty()).then(|| value.to_string())
+    })
+}
 
-    let mut in_icon_theme = false;
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        if line.eq_ignore_ascii_case("[Icon Theme]") {
-            in_icon_theme = true;
-            continue;
-        }
-        if line.starts_with('[') && line.ends_with(']') {
-            // A new section ends the [Icon Theme] table.
-            if in_icon_theme {
-                break;
-            }
-            continue;
-        }
-        if !in_icon_theme {
-            continue;
-        }
-        if let Some(value) = line.strip_prefix("Inherits=") {
-            return value
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-        }
+/// The display's integer scale factor, for HiDPI icon directories:
+/// `GDK_SCALE`, else GNOME's `scaling-factor` setting, else 1.
+fn detect_scale() -> u32 {
+    if let Some(scale) = env::var("GDK_SCALE")
+        .ok()
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .filter(|s| *s > 0)
+    {
+        return scale;
     }
-
-    Vec::new()
+    std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "scaling-factor"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            // `uint32 2`; 0 means "automatic", which we cannot know here.
+            String::from_utf8_lossy(&o.stdout)
+                .split_whitespace()
+                .last()?
+                .parse::<u32>()
+                .ok()
+        })
+        .filter(|s| *s > 0)
+        .unwrap_or(1)
 }
 
 /// Last-resort fallback: legacy `/usr/share/pixmaps/<name>.<ext>` icons.
@@ -320,14 +396,97 @@ pub fn mime_for_path(path: &str) -> &'static str {
     }
 }
 
-/// Build the webview-facing URL for a resolved icon path.
+/// Build the webview-facing URL for a resolved icon path, and allow
+/// [`serve`] to read that path.
 ///
 /// Returns `scope-icon://localhost/<percent-encoded-path>`. The path is
 /// percent-encoded so spaces / non-ASCII never break URL parsing; the protocol
 /// handler decodes it back to a filesystem path before reading.
 pub fn icon_url(path: &Path) -> String {
+    allow(path.to_path_buf());
     let encoded = percent_encode_path(&path.to_string_lossy());
-    format!("scope-icon://localhost{encoded}")
+    format!("{URL_PREFIX}{encoded}")
+}
+
+fn served() -> &'static Mutex<HashSet<PathBuf>> {
+    SERVED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+fn allow(path: PathBuf) {
+    if let Ok(mut served) = served().lock() {
+        served.insert(path);
+    }
+}
+
+/// Allow an icon URL from an earlier run (the persisted scan cache) again,
+/// when it names an existing image file.
+pub fn readmit(url: &str) {
+    let Some(path) = url.strip_prefix(URL_PREFIX).map(percent_decode_path) else {
+        return;
+    };
+    let path = PathBuf::from(path);
+    if servable(&path).is_some() && path.is_file() {
+        allow(path);
+    }
+}
+
+/// The image MIME type of an absolute, `..`-free path, or `None` when it may
+/// not be served whatever the registry says.
+fn servable(path: &Path) -> Option<&'static str> {
+    let plain = path.is_absolute()
+        && path.components().all(|c| {
+            matches!(
+                c,
+                std::path::Component::RootDir | std::path::Component::Normal(_)
+            )
+        });
+    let mime = mime_for_path(&path.to_string_lossy());
+    (plain && mime.starts_with("image/")).then_some(mime)
+}
+
+/// Answer of the `scope-icon://` protocol.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IconResponse {
+    /// The file's bytes and MIME type.
+    Image(Vec<u8>, &'static str),
+    /// The path was never handed out, or is not an image.
+    Forbidden,
+    /// A handed-out path that can no longer be read.
+    NotFound,
+}
+
+/// Serve the (percent-encoded) path of a `scope-icon://` request: only paths
+/// recorded by [`icon_url`] (or [`readmit`]), only as images. With a
+/// `size=N` query, SVG, PNG and XPM icons are served as an N×N PNG.
+pub fn serve(uri_path: &str, query: Option<&str>) -> IconResponse {
+    let path = PathBuf::from(percent_decode_path(uri_path));
+    let Some(mime) = servable(&path) else {
+        return IconResponse::Forbidden;
+    };
+    let allowed = served()
+        .lock()
+        .map(|served| served.contains(&path))
+        .unwrap_or(false);
+    if !allowed {
+        return IconResponse::Forbidden;
+    }
+    let size = query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|pair| pair.strip_prefix("size="))
+        .and_then(|s| s.parse::<u32>().ok());
+    if let Some(size) = size.filter(|_| thumbnail::supported(&path)) {
+        match thumbnail::thumbnail(&path, size) {
+            Ok(png) => return IconResponse::Image(png, "image/png"),
+            Err(_) if !path.is_file() => return IconResponse::NotFound,
+            // Unrenderable: the original file is still worth a try.
+            Err(e) => eprintln!("scope: icon thumbnail for {}: {e:#}", path.display()),
+        }
+    }
+    match fs::read(&path) {
+        Ok(bytes) => IconResponse::Image(bytes, mime),
+        Err(_) => IconResponse::NotFound,
+    }
 }
 
 /// Minimal percent-encoder for a path component, sufficient for icon paths.
@@ -349,6 +508,34 @@ fn percent_encode_path(input: &str) -> String {
     out
 }
 
+/// Decode the percent-escaped path produced by [`icon_url`].
+fn percent_decode_path(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(h), Some(l)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((h << 4) | l);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -363,6 +550,20 @@ mod tests {
         assert!(resolve("/this/does/not/exist/anywhere.png").is_none());
     }
 
+    #[test]
+    fn reads_the_icon_theme_from_settings_ini() {
+        let ini = "[Settings]\ngtk-theme-name=Adwaita\ngtk-icon-theme-name = \"Papirus-Dark\"\n";
+        assert_eq!(settings_ini_theme(ini).as_deref(), Some("Papirus-Dark"));
+        assert_eq!(
+            settings_ini_theme("gtk-icon-theme-name=Yaru").as_deref(),
+            Some("Yaru")
+        );
+        assert_eq!(
+            settings_ini_theme("[Settings]\ngtk-icon-theme-name=\n"),
+            None
+        );
+    }
+
     #[test]
     fn url_encodes_spaces_and_keeps_slashes() {
         let url = icon_url(Path::new("/usr/share/icons/a b/firefox.svg"));
@@ -379,4 +580,74 @@ mod tests {
         assert_eq!(mime_for_path("icon.xpm"), "image/x-xpixmap");
         assert_eq!(mime_for_path("icon.unknown"), "application/octet-stream");
     }
+
+    fn request(url: &str) -> IconResponse {
+        serve(url.strip_prefix(URL_PREFIX).unwrap(), None)
+    }
+
+    #[test]
+    fn serves_only_handed_out_images() {
+        let dir = std::env::temp_dir().join(format!("scope-icons-{}", std::process::id()));
+        fs::create_dir_all(dir.join("theme")).unwrap();
+        let icon = dir.join("theme/app icon.png");
+        fs::write(&icon, b"png").unwrap();
+        let secret = dir.join("id_rsa");
+        fs::write(&secret, b"key").unwrap();
+
+        let url = icon_url(&icon);
+        assert_eq!(
+            request(&url),
+            IconResponse::Image(b"png".to_vec(), "image/png")
+        );
+        // Never handed out.
+        assert_eq!(
+            request(&icon_url_unregistered(&secret)),
+            IconResponse::Forbidden
+        );
+        // Handed out, but not an image.
+        assert_eq!(request(&icon_url(&secret)), IconResponse::Forbidden);
+
+        fs::remove_file(&icon).unwrap();
+        assert_eq!(request(&url), IconResponse::NotFound);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_traversal_out_of_handed_out_paths() {
+        let dir = std::env::temp_dir().join(format!("scope-icons-dots-{}", std::process::id()));
+        fs::create_dir_all(dir.join("theme")).unwrap();
+        let icon = dir.join("theme/app.png");
+        fs::write(&icon, b"png").unwrap();
+        fs::write(dir.join("secret.png"), b"not an icon").unwrap();
+        let url = icon_url(&icon);
+
+        for path in [
+            format!("{}/theme/../secret.png", dir.display()),
+            format!("{}/theme/%2E%2E/secret.png", dir.display()),
+            "theme/app.png".to_string(),
+        ] {
+            assert_eq!(serve(&path, None), IconResponse::Forbidden, "{path}");
+        }
+        // Cached URLs are re-admitted only as existing images.
+        readmit(&icon_url_unregistered(&dir.join("secret.png")));
+        assert!(matches!(
+            serve(&format!("{}/secret.png", dir.display()), None),
+            IconResponse::Image(..)
+        ));
+        readmit(&format!("{URL_PREFIX}{}/theme/../app.png", dir.display()));
+        assert_eq!(
+            serve(&format!("{}/theme/../app.png", dir.display()), None),
+            IconResponse::Forbidden
+        );
+        assert!(matches!(request(&url), IconResponse::Image(..)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// An icon URL for `path` that was not handed out through [`icon_url`].
+    fn icon_url_unregistered(path: &Path) -> String {
+        format!(
+            "{URL_PREFIX}{}",
+            percent_encode_path(&path.to_string_lossy())
+        )
+    }
 }
@@ -0,0 +1,587 @@
+//! Icon theme lookup following the freedesktop Icon Theme Specification.
+//!
+//! Strategy: a theme's `index.theme` lists its `Directories=` (and
+//! `ScaledDirectories=`), each with a `Size`, `Scale`, `Type`
+//! (`Fixed`/`Scalable`/`Threshold`) and `Context`. [`Themes::find`] runs the
+//! spec's `FindIcon`: the user's theme and its `Inherits=` chain, then
+//! `hicolor`; within a theme, a directory whose size matches the request
+//! exactly wins, otherwise the file in the directory with the smallest
+//! `DirectorySizeDistance`. `Applications` directories are searched first,
+//! since every name Scope resolves is an application icon.
+//!
+//! A theme may be spread over several base directories (`~/.local/share/icons`,
+//! `/usr/share/icons`, ...). Where one has an up-to-date `icon-theme.cache`
+//! (written by `gtk-update-icon-cache`), the files an icon has are read from
+//! it instead of probing every directory and extension on disk.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Extensions in the order the spec looks them up, with the matching
+/// `icon-theme.cache` flag.
+const EXTENSIONS: &[(&str, u16)] = &[("png", 4), ("svg", 2), ("xpm", 1)];
+
+/// Loads themes from a list of base directories, once each.
+pub struct Themes {
+    basedirs: Vec<PathBuf>,
+    loaded: Mutex<HashMap<String, Option<Arc<Theme>>>>,
+}
+
+impl Themes {
+    pub fn new(basedirs: Vec<PathBuf>) -> Self {
+        Self {
+            basedirs,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The spec's `FindIcon`: `name` at `size`×`scale` in `theme` (and what it
+    /// inherits), then in `hicolor`.
+    pub fn find(&self, name: &str, size: u32, scale: u32, theme: Option<&str>) -> Option<PathBuf> {
+        let mut visited = HashSet::new();
+        theme
+            .into_iter()
+            .chain(["hicolor"])
+            .find_map(|theme| self.find_helper(name, size, scale, theme, &mut visited))
+    }
+
+    fn find_helper(
+        &self,
+        name: &str,
+        size: u32,
+        scale: u32,
+        theme: &str,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(theme.to_string()) {
+            return None;
+        }
+        let theme = self.load(theme)?;
+        if let Some(found) = theme.lookup(name, size, scale) {
+            return Some(found);
+        }
+        theme
+            .parents
+            .iter()
+            .find_map(|parent| self.find_helper(name, size, scale, parent, visited))
+    }
+
+    fn load(&self, name: &str) -> Option<Arc<Theme>> {
+        let mut loaded = self.loaded.lock().ok()?;
+        loaded
+            .entry(name.to_string())
+            .or_insert_with(|| Theme::load(&self.basedirs, name).map(Arc::new))
+            .clone()
+    }
+}
+
+/// How a theme directory's icons may be scaled, from its `Type=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fixed,
+    Scalable { min: u32, max: u32 },
+    Threshold(u32),
+}
+
+/// One entry of a theme's `Directories=`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directory {
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: Kind,
+    context: Option<String>,
+}
+
+impl Directory {
+    /// The spec's `DirectoryMatchesSize`.
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            Kind::Fixed => self.size == size,
+            Kind::Scalable { min, max } => (min..=max).contains(&size),
+            Kind::Threshold(t) => (self.size.saturating_sub(t)..=self.size + t).contains(&size),
+        }
+    }
+
+    /// The spec's `DirectorySizeDistance`, in device pixels.
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            Kind::Fixed => (self.size, self.size),
+            Kind::Scalable { min, max } => (min, max),
+            Kind::Threshold(t) => (self.size.saturating_sub(t), self.size + t),
+        };
+        if wanted < min * self.scale {
+            min * self.scale - wanted
+        } else {
+            wanted.saturating_sub(max * self.scale)
+        }
+    }
+}
+
+/// A loaded theme: its directories, parents, and where it lives.
+#[derive(Debug, Default)]
+struct Theme {
+    /// `<basedir>/<theme>` for every base directory that has the theme, with
+    /// its icon cache when one is current.
+    roots: Vec<(PathBuf, Option<IconCache>)>,
+    parents: Vec<String>,
+    directories: Vec<Directory>,
+}
+
+impl Theme {
+    fn load(basedirs: &[PathBuf], name: &str) -> Option<Self> {
+        let roots: Vec<PathBuf> = basedirs
+            .iter()
+            .map(|b| b.join(name))
+            .filter(|r| r.is_dir())
+            .collect();
+        // The first index.theme found describes the theme.
+        let index = roots
+            .iter()
+            .find_map(|r| fs::read_to_string(r.join("index.theme")).ok())?;
+        let mut theme = Self::parse(&index);
+        theme.roots = roots
+            .into_iter()
+            .map(|root| {
+                let cache = IconCache::open(&root);
+                (root, cache)
+            })
+            .collect();
+        Some(theme)
+    }
+
+    fn parse(index: &str) -> Self {
+        let sections = parse_ini(index);
+        let Some(main) = sections.get("Icon Theme") else {
+            return Self::default();
+        };
+        let list = |key: &str| -> Vec<String> {
+            main.get(key)
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut seen = HashSet::new();
+        let mut directories: Vec<Directory> = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter(|path| seen.insert(path.clone()))
+            .filter_map(|path| {
+                let keys = sections.get(path.as_str())?;
+                let number = |key: &str| keys.get(key).and_then(|v| v.trim().parse::<u32>().ok());
+                let size = number("Size")?;
+                let kind = match keys.get("Type").map(|t| t.trim()) {
+                    Some("Fixed") => Kind::Fixed,
+                    Some("Scalable") => Kind::Scalable {
+                        min: number("MinSize").unwrap_or(size),
+                        max: number("MaxSize").unwrap_or(size),
+                    },
+                    _ => Kind::Threshold(number("Threshold").unwrap_or(2)),
+                };
+                Some(Directory {
+                    size,
+                    scale: number("Scale").unwrap_or(1).max(1),
+                    kind,
+                    context: keys.get("Context").map(|c| c.trim().to_string()),
+                    path,
+                })
+            })
+            .collect();
+        // Stable: the theme's own order still decides among the rest.
+        directories.sort_by_key(|d| d.context.as_deref() != Some("Applications"));
+
+        Self {
+            roots: Vec::new(),
+            parents: list("Inherits"),
+            directories,
+        }
+    }
+
+    /// The spec's `LookupIcon`: the first file in a directory matching the
+    /// size, else the one in the closest directory.
+    fn lookup(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        // Per root: the directories and extensions the cache lists for `name`.
+        let cached: Vec<Option<HashMap<&str, u16>>> = self
+            .roots
+            .iter()
+            .map(|(_, cache)| cache.as_ref().map(|c| c.lookup(name)))
+            .collect();
+
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for dir in &self.directories {
+            let matches = dir.matches(size, scale);
+            let distance = dir.distance(size, scale);
+            if !matches && closest.as_ref().is_some_and(|(d, _)| *d <= distance) {
+                continue;
+            }
+            for ((root, _), cached) in self.roots.iter().zip(&cached) {
+                let Some(path) = find_file(root, dir, name, cached.as_ref()) else {
+                    continue;
+                };
+                if matches {
+                    return Some(path);
+                }
+                closest = Some((distance, path));
+                break;
+            }
+        }
+        closest.map(|(_, path)| path)
+    }
+}
+
+/// `<root>/<dir>/<name>.<ext>` for the first extension that exists, asking
+/// the cache instead of the filesystem when the root has one.
+fn find_file(
+    root: &Path,
+    dir: &Directory,
+    name: &str,
+    cached: Option<&HashMap<&str, u16>>,
+) -> Option<PathBuf> {
+    let flags = match cached {
+        Some(cached) => Some(*cached.get(dir.path.as_str())?),
+        None => None,
+    };
+    EXTENSIONS.iter().find_map(|(ext, flag)| {
+        if flags.is_some_and(|f| f & flag == 0) {
+            return None;
+        }
+        let path = root.join(&dir.path).join(format!("{name}.{ext}"));
+        path.is_file().then_some(path)
+    })
+}
+
+/// `[section]` → key → value, ignoring comments and localized keys.
+fn parse_ini(content: &str) -> HashMap<&str, HashMap<&str, &str>> {
+    let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+    let mut current = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name);
+            continue;
+        }
+        let (Some(section), Some((key, value))) = (current, line.split_once('=')) else {
+            continue;
+        };
+        let key = key.trim();
+        if !key.contains('[') {
+            sections
+                .entry(section)
+                .or_default()
+                .insert(key, value.trim());
+        }
+    }
+    sections
+}
+
+/// GTK's `icon-theme.cache`: a hash table from icon name to the directories
+/// (and extensions) that have it. All numbers are big-endian. Offsets come
+/// from the file, so arithmetic on them is checked: a corrupt cache reads as
+/// no cache.
+#[derive(Debug)]
+struct IconCache {
+    data: Vec<u8>,
+    directories: Vec<String>,
+}
+
+impl IconCache {
+    /// The cache of the theme directory `root`, unless it is missing, of an
+    /// unknown version, or older than the directory (icons were added since).
+    fn open(root: &Path) -> Option<Self> {
+        let path = root.join("icon-theme.cache");
+        let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        if modified(&path)? < modified(root)? {
+            return None;
+        }
+        Self::parse(fs::read(path).ok()?)
+    }
+
+    fn parse(data: Vec<u8>) -> Option<Self> {
+        let mut cache = Self {
+            data,
+            directories: Vec::new(),
+        };
+        if cache.u16(0)? != 1 {
+            return None;
+        }
+        let list = cache.u32(8)?;
+        let count = cache.u32(list)?;
+        cache.directories = (0..count)
+            .map(|i| {
+                let offset = cache.u32(element(list, 4, i)?)?;
+                cache.string(offset).map(str::to_string)
+            })
+            .collect::<Option<_>>()?;
+        Some(cache)
+    }
+
+    /// Directory → extension flags for every directory that has `name`.
+    fn lookup(&self, name: &str) -> HashMap<&str, u16> {
+        self.images(name).unwrap_or_default()
+    }
+
+    fn images(&self, name: &str) -> Option<HashMap<&str, u16>> {
+        let hash = self.u32(4)?;
+        let buckets = self.u32(hash)?;
+        if buckets == 0 {
+            return None;
+        }
+        let mut icon = self.u32(element(hash, 4, name_hash(name) % buckets)?)?;
+        // Bounded: a corrupt chain must not loop forever.
+        for _ in 0..self.data.len() {
+            if icon == u32::MAX {
+                return None;
+            }
+            if self.string(self.u32(icon.checked_add(4)?)?)? == name {
+                let list = self.u32(icon.checked_add(8)?)?;
+                let mut images = HashMap::new();
+                for i in 0..self.u32(list)? {
+                    let image = element(list, 8, i)?;
+                    let dir = self.directories.get(usize::from(self.u16(image)?))?;
+                    images.insert(dir.as_str(), self.u16(image.checked_add(2)?)?);
+                }
+                return Some(images);
+            }
+            icon = self.u32(icon)?;
+        }
+        None
+    }
+
+    fn u16(&self, offset: u32) -> Option<u16> {
+        let at = offset as usize;
+        let bytes = self.data.get(at..at + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: u32) -> Option<u32> {
+        let at = offset as usize;
+        let bytes = self.data.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&self, offset: u32) -> Option<&str> {
+        let rest = self.data.get(offset as usize..)?;
+        let end = rest.iter().position(|b| *b == 0)?;
+        std::str::from_utf8(&rest[..end]).ok()
+    }
+}
+
+/// GTK's `icon_name_hash` (over signed chars).
+/// Offset of element `index` of `size` bytes in the counted list at `list`.
+fn element(list: u32, size: u32, index: u32) -> Option<u32> {
+    list.checked_add(4)?.checked_add(size.checked_mul(index)?)
+}
+
+fn name_hash(name: &str) -> u32 {
+    let mut bytes = name.bytes().map(|b| b as i8 as u32);
+    let Some(first) = bytes.next() else {
+        return 0;
+    };
+    bytes.fold(first, |h, b| (h << 5).wrapping_sub(h).wrapping_add(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/icons")
+    }
+
+    fn themes() -> Themes {
+        let root = fixtures();
+        Themes::new(vec![root.join("user"), root.join("system")])
+    }
+
+    /// The found path, relative to the fixture root.
+    fn find(themes: &Themes, name: &str, size: u32, scale: u32) -> Option<String> {
+        let path = themes.find(name, size, scale, Some("Fixture"))?;
+        Some(path.strip_prefix(fixtures()).unwrap().display().to_string())
+    }
+
+    #[test]
+    fn parses_theme_directories() {
+        let theme = Theme::parse(
+            "[Icon Theme]\nName=T\nName[de]=T\nInherits=Adwaita, hicolor\n\
+             Directories=16x16/actions,48x48/apps,scalable/apps\n\
+             ScaledDirectories=48x48@2/apps\n\
+             [16x16/actions]\nSize=16\nContext=Actions\nType=Fixed\n\
+             [48x48/apps]\nSize=48\nContext=Applications\n\
+             [48x48@2/apps]\nSize=48\nScale=2\nContext=Applications\nType=Threshold\nThreshold=4\n\
+             [scalable/apps]\nSize=128\nMinSize=8\nMaxSize=512\nType=Scalable\nContext=Applications\n",
+        );
+        assert_eq!(theme.parents, ["Adwaita", "hicolor"]);
+        let dirs: Vec<(&str, u32, Kind)> = theme
+            .directories
+            .iter()
+            .map(|d| (d.path.as_str(), d.scale, d.kind))
+            .collect();
+        assert_eq!(
+            dirs,
+            [
+                ("48x48/apps", 1, Kind::Threshold(2)),
+                ("scalable/apps", 1, Kind::Scalable { min: 8, max: 512 }),
+                ("48x48@2/apps", 2, Kind::Threshold(4)),
+                ("16x16/actions", 1, Kind::Fixed),
+            ]
+        );
+
+        let threshold = &theme.directories[0];
+        assert!(threshold.matches(50, 1) && !threshold.matches(51, 1) && !threshold.matches(48, 2));
+        assert_eq!(
+            (threshold.distance(32, 1), threshold.distance(64, 1)),
+            (14, 14)
+        );
+        let scalable = &theme.directories[1];
+        assert_eq!(
+            (scalable.distance(4, 1), scalable.distance(600, 1)),
+            (4, 88)
+        );
+        assert_eq!(theme.directories[2].distance(48, 1), 40);
+    }
+
+    #[test]
+    fn finds_the_closest_size_and_scale_across_inherited_themes() {
+        let themes = themes();
+        // Exact matches, per size and scale.
+        assert_eq!(
+            find(&themes, "editor", 16, 1).as_deref(),
+            Some("system/Fixture/16x16/apps/editor.png")
+        );
+        assert_eq!(
+            find(&themes, "editor", 48, 1).as_deref(),
+            Some("system/Fixture/48x48/apps/editor.png")
+        );
+        assert_eq!(
+            find(&themes, "editor", 48, 2).as_deref(),
+            Some("system/Fixture/48x48@2/apps/editor.png")
+        );
+        // 256px: only the scalable directory covers it.
+        assert_eq!(
+            find(&themes, "editor", 256, 1).as_deref(),
+            Some("system/Fixture/scalable/apps/editor.svg")
+        );
+        // No match: the closest directory wins.
+        assert_eq!(
+            find(&themes, "small-only", 64, 1).as_deref(),
+            Some("system/Fixture/16x16/apps/small-only.png")
+        );
+        // Files in the user's base directory join the theme.
+        assert_eq!(
+            find(&themes, "user-app", 48, 1).as_deref(),
+            Some("user/Fixture/48x48/apps/user-app.png")
+        );
+        // Inherited: Fixture → Parent, then hicolor.
+        assert_eq!(
+            find(&themes, "parent-app", 48, 1).as_deref(),
+            Some("system/Parent/48x48/apps/parent-app.xpm")
+        );
+        assert_eq!(
+            find(&themes, "hicolor-app", 48, 1).as_deref(),
+            Some("system/hicolor/256x256/apps/hicolor-app.png")
+        );
+        assert_eq!(find(&themes, "missing", 48, 1), None);
+    }
+
+    /// A copy of the fixture theme `name` in a fresh temporary base directory.
+    fn copy_theme(name: &str) -> PathBuf {
+        fn copy(from: &Path, to: &Path) {
+            fs::create_dir_all(to).unwrap();
+            for entry in fs::read_dir(from).unwrap().flatten() {
+                let target = to.join(entry.file_name());
+                if entry.file_type().unwrap().is_dir() {
+                    copy(&entry.path(), &target);
+                } else {
+                    fs::copy(entry.path(), target).unwrap();
+                }
+            }
+        }
+        let base = std::env::temp_dir().join(format!("scope-theme-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        copy(&fixtures().join("system").join(name), &base.join(name));
+        base
+    }
+
+    fn set_modified(path: &Path, ago: u64) {
+        let time = std::time::SystemTime::now() - std::time::Duration::from_secs(ago);
+        fs::File::open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn reads_gtk_icon_caches() {
+        let base = copy_theme("Cached");
+        let root = base.join("Cached");
+        let cache = IconCache::parse(fs::read(root.join("icon-theme.cache")).unwrap()).unwrap();
+        assert_eq!(
+            cache.lookup("cached-app"),
+            HashMap::from([("32x32/apps", 4), ("scalable/apps", 2)])
+        );
+        assert_eq!(cache.lookup("tiny"), HashMap::from([("32x32/apps", 4)]));
+        assert!(cache.lookup("missing").is_empty());
+        assert_eq!(name_hash("firefox"), 3_445_514_969);
+
+        // A cache older than its directory is ignored.
+        set_modified(&root.join("icon-theme.cache"), 120);
+        set_modified(&root, 60);
+        assert!(Theme::load(std::slice::from_ref(&base), "Cached")
+            .unwrap()
+            .roots[0]
+            .1
+            .is_none());
+
+        // A cache at least as new as its directory is trusted.
+        set_modified(&root.join("icon-theme.cache"), 0);
+        let theme = Theme::load(std::slice::from_ref(&base), "Cached").unwrap();
+        assert!(theme.roots[0].1.is_some());
+        assert_eq!(
+            theme.lookup("cached-app", 32, 1).unwrap(),
+            root.join("32x32/apps/cached-app.png")
+        );
+        assert_eq!(
+            theme.lookup("cached-app", 96, 1).unwrap(),
+            root.join("scalable/apps/cached-app.svg")
+        );
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn reads_corrupt_icon_caches_as_no_cache() {
+        let data = fs::read(fixtures().join("system/Cached/icon-theme.cache")).unwrap();
+        for len in 0..data.len() {
+            if let Some(cache) = IconCache::parse(data[..len].to_vec()) {
+                let _ = cache.lookup("cached-app");
+            }
+        }
+        // Offsets that overflow: 2^32 - 1 buckets, and a chain pointing at the
+        // end of the address space.
+        let header = |buckets: u32, bucket: u32| {
+            [
+                &[0, 1, 0, 0][..],
+                &12u32.to_be_bytes(),
+                &20u32.to_be_bytes(),
+                &buckets.to_be_bytes(),
+                &bucket.to_be_bytes(),
+                &0u32.to_be_bytes(),
+            ]
+            .concat()
+        };
+        for data in [header(u32::MAX, 0), header(1, u32::MAX - 1)] {
+            let cache = IconCache::parse(data).unwrap();
+            assert!(cache.lookup("firefox").is_empty());
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Size-specific PNG thumbnails of resolved icons.
+//!
+//! Strategy: icon themes ship anything from 16px PNGs to 512px PNGs and
+//! multi-megabyte SVGs, and XPM (still common under `/usr/share/pixmaps`)
+//! does not render in the webview at all. When a `scope-icon://` request asks
+//! for `?size=N`, [`thumbnail`] renders the icon into an N×N PNG instead:
+//! SVG through `resvg`, PNG decoded and rescaled with `tiny-skia`, XPM through
+//! the small decoder below. Sizes snap up to a few [`SIZES`] so the cache stays
+//! small. Results live under `$XDG_CACHE_HOME/scope/icons`, keyed by the
+//! source path and its mtime: an icon that changes on disk gets a new entry,
+//! and an unchanged one is rendered once across runs. Writing an entry
+//! prunes the ones rendered from the file's older mtimes.
+//!
+//! Other formats (JPEG, WebP, ...) render in the webview as they are and are
+//! served unchanged.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{bail, Context, Result};
+use resvg::tiny_skia::{self, FilterQuality, Pixmap, PixmapPaint, Transform};
+use resvg::usvg;
+
+/// Thumbnail sizes in pixels; a request is rounded up to the next one.
+const SIZES: &[u32] = &[16, 24, 32, 48, 64, 96, 128, 256];
+/// Largest XPM width or height decoded; pixmaps are small, and the header is
+/// trusted for the allocation.
+const MAX_XPM: usize = 1024;
+/// Most characters per XPM pixel; real files use one or two.
+const MAX_XPM_CPP: usize = 8;
+
+/// The thumbnail size for a requested size.
+pub fn snap(requested: u32) -> u32 {
+    SIZES
+        .iter()
+        .copied()
+        .find(|s| *s >= requested)
+        .unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+/// Whether [`thumbnail`] can render the file at `path`.
+pub fn supported(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "svg" | "svgz" | "png" | "xpm")
+}
+
+/// A `size`×`size` PNG of the icon at `path`, from the cache when an entry for
+/// the file's current mtime exists.
+pub fn thumbnail(path: &Path, size: u32) -> Result<Vec<u8>> {
+    let dir = crate::system::cache_dir()
+        .context("no cache directory")?
+        .join("icons");
+    thumbnail_in(&dir, path, size)
+}
+
+fn thumbnail_in(dir: &Path, path: &Path, size: u32) -> Result<Vec<u8>> {
+    let size = snap(size);
+    let key = file_key(path)?;
+    let entry = dir.join(format!("{key}-{size}.png"));
+    if let Ok(png) = fs::read(&entry) {
+        return Ok(png);
+    }
+    let png = render(path, size)?
+        .encode_png()
+        .context("encoding thumbnail")?;
+    // A failed write only costs a re-render next time.
+    if fs::create_dir_all(dir).is_ok() {
+        prune(dir, &key);
+        let tmp = entry.with_extension("tmp");
+        if fs::write(&tmp, &png).is_ok() {
+            let _ = fs::rename(&tmp, &entry);
+        }
+    }
+    Ok(png)
+}
+
+/// `<hash of path>-<hash of mtime>` for `path`, naming cache entries that
+/// must be redone when the file changes. Entries of the file's older mtimes
+/// share the first part, so [`prune`] can find them.
+pub(super) fn file_key(path: &Path) -> Result<String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .with_context(|| format!("reading {}", path.display()))?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok(format!(
+        "{:016x}-{:016x}",
+        fnv1a(path.as_os_str().as_encoded_bytes()),
+        fnv1a(&since_epoch.as_nanos().to_le_bytes())
+    ))
+}
+
+/// FNV-1a: stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Remove the entries in `dir` for the file [`file_key`] gave `key`, left
+/// from its older mtimes. Entries for its current mtime stay.
+pub(super) fn prune(dir: &Path, key: &str) {
+    let Some((file, _)) = key.split_once('-') else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let file = format!("{file}-");
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&file) && !name.starts_with(key) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn render(path: &Path, size: u32) -> Result<Pixmap> {
+    let data = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    match extension(path).as_str() {
+        "svg" | "svgz" => render_svg(&data, size),
+        "png" => fit(&Pixmap::decode_png(&data).context("decoding PNG")?, size),
+        "xpm" => fit(&decode_xpm(&String::from_utf8_lossy(&data))?, size),
+        other => bail!("cannot render .{other} icons"),
+    }
+}
+
+fn render_svg(data: &[u8], size: u32) -> Result<Pixmap> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default()).context("parsing SVG")?;
+    let mut pixmap = Pixmap::new(size, size).context("allocating thumbnail")?;
+    let (scale, dx, dy) = placement(tree.size().width(), tree.size().height(), size);
+    let transform = Transform::from_row(scale, 0.0, 0.0, scale, dx, dy);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Scale `image` to fit a `size`×`size` square, centred, keeping its aspect.
+fn fit(image: &Pixmap, size: u32) -> Result<Pixmap> {
+    let mut pixmap = Pixmap::new(size, size).context("allocating thumbnail")?;
+    let (scale, dx, dy) = placement(image.width() as f32, image.height() as f32, size);
+    let paint = PixmapPaint {
+        quality: FilterQuality::Bicubic,
+        ..PixmapPaint::default()
+    };
+    let transform = Transform::from_row(scale, 0.0, 0.0, scale, dx, dy);
+    pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+    Ok(pixmap)
+}
+
+/// Scale and offset that fit a `width`×`height` image into the square.
+fn placement(width: f32, height: f32, size: u32) -> (f32, f32, f32) {
+    let size = size as f32;
+    let scale = size / width.max(height).max(1.0);
+    (
+        scale,
+        (size - width * scale) / 2.0,
+        (size - height * scale) / 2.0,
+    )
+}
+
+/// Decode an XPM (version 3) image: the C strings hold the
+/// `width height colors chars-per-pixel` header, the color table, then rows.
+fn decode_xpm(source: &str) -> Result<Pixmap> {
+    let strings = c_strings(source);
+    let mut strings = strings.iter().map(String::as_str);
+    let header: Vec<usize> = strings
+        .next()
+        .context("XPM without header")?
+        .split_whitespace()
+        .take(4)
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .context("bad XPM header")?;
+    let [width, height, colors, cpp] = header[..] else {
+        bail!("bad XPM header");
+    };
+    if cpp == 0 || width == 0 || height == 0 {
+        bail!("bad XPM header");
+    }
+    if width > MAX_XPM || height > MAX_XPM || cpp > MAX_XPM_CPP {
+        bail!("XPM of {width}x{height} with {cpp} characters per pixel is too large");
+    }
+
+    let mut table = HashMap::with_capacity(colors.min(256));
+    for _ in 0..colors {
+        let line = strings.next().context("truncated XPM color table")?;
+        let (key, spec) = (line.get(..cpp), line.get(cpp..));
+        let (Some(key), Some(spec)) = (key, spec) else {
+            bail!("bad XPM color line");
+        };
+        table.insert(key, xpm_color(spec));
+    }
+
+    let mut pixmap = Pixmap::new(width as u32, height as u32).context("XPM too large or empty")?;
+    let pixels = pixmap.pixels_mut();
+    for y in 0..height {
+        let row = strings.next().context("truncated XPM pixels")?;
+        for x in 0..width {
+            let key = row.get(x * cpp..(x + 1) * cpp).context("short XPM row")?;
+            let [r, g, b, a] = table.get(key).copied().unwrap_or([0, 0, 0, 0]);
+            pixels[y * width + x] = tiny_skia::ColorU8::from_rgba(r, g, b, a).premultiply();
+        }
+    }
+    Ok(pixmap)
+}
+
+/// The contents of the double-quoted strings in C source, outside comments.
+fn c_strings(source: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => s.extend(chars.next()),
+                        c => s.push(c),
+                    }
+                }
+                strings.push(s);
+            }
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// RGBA of an XPM color spec such as `c #FF0000` or `s none m white c None`;
+/// the `c` (color) key wins, unknown names are black.
+fn xpm_color(spec: &str) -> [u8; 4] {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let value = words
+        .windows(2)
+        .find(|w| w[0] == "c")
+        .or_else(|| words.windows(2).find(|w| matches!(w[0], "g" | "g4" | "m")))
+        .map(|w| w[1])
+        .unwrap_or("black");
+    if value.eq_ignore_ascii_case("none") {
+        return [0, 0, 0, 0];
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        // #RGB, #RRGGBB and #RRRRGGGGBBBB: the top byte of each channel.
+        let digits = hex.len() / 3;
+        if digits > 0 && hex.len() % 3 == 0 && hex.is_ascii() {
+            let channel = |i: usize| {
+                let part = &hex[i * digits..(i + 1) * digits];
+                let v = u16::from_str_radix(part, 16).ok()?;
+                Some(match digits {
+                    1 => (v * 17) as u8,
+                    2 => v as u8,
+                    _ => u16::from_str_radix(&part[..2], 16).ok()? as u8,
+                })
+            };
+            if let (Some(r), Some(g), Some(b)) = (channel(0), channel(1), channel(2)) {
+                return [r, g, b, 255];
+            }
+        }
+        return [0, 0, 0, 255];
+    }
+    let [r, g, b] = match value.to_lowercase().as_str() {
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "gray" | "grey" => [190, 190, 190],
+        _ => [0, 0, 0],
+    };
+    [r, g, b, 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XPM: &str = r#"/* XPM */
+static char * icon_xpm[] = {
+/* columns rows colors chars-per-pixel */
+"2 2 3 1 ",
+"  c None",
+". c #FF0000",
+"X c #00f",
+/* pixels */
+".X",
+"  "};
+"#;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("scope-thumbs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn decodes_xpm() {
+        let pixmap = decode_xpm(XPM).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (2, 2));
+        let rgba = |i: usize| {
+            let c = pixmap.pixels()[i].demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        };
+        assert_eq!(rgba(0), [255, 0, 0, 255]);
+        assert_eq!(rgba(1), [0, 0, 255, 255]);
+        assert_eq!(rgba(2)[3], 0);
+        assert!(decode_xpm("\"2 2 1\"").is_err());
+        // Refused before allocating anything.
+        let err = decode_xpm("\"99999 99999 1 1\"").unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+        assert!(decode_xpm("\"2 2 1 99999999999\"").is_err());
+        assert!(decode_xpm("\"1 1 99999999999 1\", \"a c None\", \"a\"").is_err());
+        assert_eq!(xpm_color("s bg c #ffffffff0000"), [255, 255, 0, 255]);
+    }
+
+    #[test]
+    fn renders_cached_png_thumbnails() {
+        let dir = temp_dir("render");
+        let cache = dir.join("cache");
+        let svg = dir.join("app.svg");
+        fs::write(
+            &svg,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="512" height="256">
+                <rect width="512" height="256" fill="#3366ff"/></svg>"##,
+        )
+        .unwrap();
+        let xpm = dir.join("app.xpm");
+        fs::write(&xpm, XPM).unwrap();
+        let mut big = Pixmap::new(512, 512).unwrap();
+        big.fill(tiny_skia::Color::from_rgba8(0, 128, 0, 255));
+        let png = dir.join("app.png");
+        fs::write(&png, big.encode_png().unwrap()).unwrap();
+
+        for source in [&svg, &xpm, &png] {
+            assert!(supported(source));
+            let thumb = Pixmap::decode_png(&thumbnail_in(&cache, source, 30).unwrap()).unwrap();
+            assert_eq!((thumb.width(), thumb.height()), (32, 32), "{source:?}");
+        }
+        // The wide SVG is centred: transparent above, filled in the middle.
+        let thumb = Pixmap::decode_png(&thumbnail_in(&cache, &svg, 32).unwrap()).unwrap();
+        assert_eq!(thumb.pixel(16, 2).unwrap().alpha(), 0);
+        assert_eq!(thumb.pixel(16, 16).unwrap().alpha(), 255);
+
+        // Served from the cache: the entry, not the source, is read.
+        let entry = cache.join(format!("{}-32.png", file_key(&svg).unwrap()));
+        fs::write(&entry, b"cached").unwrap();
+        assert_eq!(thumbnail_in(&cache, &svg, 32).unwrap(), b"cached");
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 3);
+
+        // A changed icon replaces the entries of its old mtime.
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&svg)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        thumbnail_in(&cache, &svg, 64).unwrap();
+        assert!(!entry.exists());
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 3);
+
+        assert!(!supported(Path::new("/usr/share/pixmaps/app.jpg")));
+        assert_eq!((snap(1), snap(40), snap(4096)), (16, 48, 256));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Following icon-theme changes while Scope runs.
+//!
+//! Strategy: GNOME keeps the theme in the `org.gnome.desktop.interface
+//! icon-theme` setting, and `gsettings monitor` prints a line each time it
+//! changes. Other desktops (and GNOME's settings daemon) write
+//! `gtk-icon-theme-name` to GTK's `settings.ini`, whose directories are
+//! watched with inotify since the file may be replaced by rename. A theme
+//! switch usually touches both, so changes are collected until things go
+//! quiet for [`QUIET`]. Then [`super::reload`] detects the theme again, and
+//! when it (or the scale) really changed the [`ScanCache`] resolves the
+//! icons of its packages again, without rescanning, and publishes the new
+//! icon URLs as a diff.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use inotify::{Inotify, WatchMask};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::scan_cache::ScanCache;
+use crate::system::Runner;
+
+/// How long no further changes must arrive before detecting the theme.
+const QUIET: Duration = Duration::from_millis(500);
+
+/// Watch the icon-theme setting and refresh the icons in `cache` when the
+/// theme changes, until neither can be watched any more. Does nothing in
+/// dry-run mode, like [`crate::watcher::run`].
+pub async fn run(runner: Runner, cache: ScanCache) -> Result<()> {
+    if runner.is_dry_run() {
+        return Ok(());
+    }
+    let (tx, mut changes) = unbounded_channel();
+    let monitor = monitor_setting(tx.clone());
+    let ini = watch_settings_ini(tx);
+    if let (Err(monitor), Err(ini)) = (&monitor, &ini) {
+        bail!("{monitor:#}; {ini:#}");
+    }
+    while changes.recv().await.is_some() {
+        while let Ok(Some(())) = tokio::time::timeout(QUIET, changes.recv()).await {}
+        let changed = tokio::task::spawn_blocking(super::reload)
+            .await
+            .unwrap_or(false);
+        if changed {
+            cache.refresh_icons().await;
+        }
+    }
+    Ok(())
+}
+
+/// Run `gsettings monitor` on the icon-theme key and send one change per
+/// line it prints. The process ends with the receiver.
+fn monitor_setting(tx: UnboundedSender<()>) -> Result<()> {
+    let mut child = tokio::process::Command::new("gsettings")
+        .args(["monitor", "org.gnome.desktop.interface", "icon-theme"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("start gsettings monitor")?;
+    let stdout = child.stdout.take().context("gsettings monitor output")?;
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(_)) = lines.next_line().await {
+            if tx.send(()).is_err() {
+                break;
+            }
+        }
+        // Dropping the child kills a monitor still running.
+        drop(child);
+    });
+    Ok(())
+}
+
+/// Watch the [`super::settings_ini_dirs`] that exist and send one change per
+/// event on a `settings.ini`, from a dedicated thread that ends when the
+/// receiver is dropped (at its next event) or reading fails.
+fn watch_settings_ini(tx: UnboundedSender<()>) -> Result<()> {
+    let inotify = Inotify::init().context("initialize inotify")?;
+    let mask = WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::CLOSE_WRITE;
+    let mut watched = 0;
+    for dir in super::settings_ini_dirs() {
+        if dir.is_dir() && inotify.watches().add(&dir, mask).is_ok() {
+            watched += 1;
+        }
+    }
+    if watched == 0 {
+        bail!("no GTK settings directory could be watched");
+    }
+    std::thread::Builder::new()
+        .name("scope-icon-theme".into())
+        .spawn(move || {
+            let mut inotify = inotify;
+            let mut buffer = [0u8; 1024];
+            loop {
+                let Ok(events) = inotify.read_events_blocking(&mut buffer) else {
+                    return;
+                };
+                for event in events {
+                    // Nameless events are overflows: anything may have changed.
+                    if event.name.is_none_or(|n| n == "settings.ini") && tx.send(()).is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+        .context("start icon-theme watcher thread")?;
+    Ok(())
+}
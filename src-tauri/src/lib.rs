@@ -1,86 +1,171 @@
 //! Scope backend wiring.
 //!
 //! This module only wires submodules, registers Tauri commands (including the
-//! `scope-icon://` URI-scheme protocol), and starts the app. Scanner/icon/
-//! update/uninstall logic lives in dedicated modules.
+//! `scope-icon://` URI-scheme protocol), starts the D-Bus service, the
+//! update-check schedule, the tray icon and desktop notifications, and
+//! starts the app. Scanner/icon/backend/operation logic lives in dedicated
+//! modules.
+//! [`cli`] is the entry point of the headless `scope-cli` binary, [`helper`]
+//! that of the privileged `scope-helper`.
 
+mod backend;
+pub mod cli;
 mod commands;
+mod config;
+mod dbus;
 mod desktop_entries;
+pub mod helper;
 mod icons;
+mod notify;
 mod operations;
 mod package;
 mod safety;
+mod scan_cache;
 mod scanner;
 mod system;
+mod tray;
+mod updates;
+mod watcher;
 
-use commands::operations::{apply_uninstall, preview_uninstall, apply_update, preview_update};
-use commands::packages::{get_cached_scan, scan_packages, scan_status, search_packages, ScanCache};
+use commands::config::config_status;
+use commands::notify::attach_notifier;
+use commands::operations::{
+    apply_operation, apply_uninstall, apply_update, package_capabilities, preview_operation,
+    preview_uninstall, preview_update,
+};
+use commands::packages::{
+    forward_scan_diffs, get_cached_scan, scan_packages, scan_status, search_packages,
+};
+use commands::updates::{check_updates, forward_update_checks};
+use dbus::{Bus, ScopeService};
+use notify::Notifier;
 use operations::PlanStore;
+use scan_cache::ScanCache;
 use tauri::http::{header, Response, StatusCode};
+use updates::UpdateChecker;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
-        .manage(ScanCache::default())
-        .manage(PlanStore::default())
-        .register_uri_scheme_protocol("scope-icon", |_app, request| {
-            // Serve only the specific local file the URI points at. The
-            // frontend never picks arbitrary paths: every URL it sees is
-            // produced by `icons::icon_url` from a resolved icon path, so this
-            // protocol grants no broad filesystem access.
-            let raw = request.uri().path();
-            let path = percent_decode_path(raw);
+    let runner = system::default_runner();
+    // Loaded before the window opens so the last run's list shows instantly.
+    let scan_cache = ScanCache::load(&runner);
+    let plans = PlanStore::default();
+    let bus = Bus::default();
+    let notifier = Notifier::default();
+    let checker = UpdateChecker::new(runner.clone(), scan_cache.clone());
+    let service = ScopeService::new(
+        runner.clone(),
+        scan_cache.clone(),
+        plans.clone(),
+        bus.clone(),
+        notifier.clone(),
+    );
 
-            let (data, mime, status) = match std::fs::read(&path) {
-                Ok(bytes) => (bytes, icons::mime_for_path(&path), StatusCode::OK),
-                Err(_) => (Vec::new(), "text/plain", StatusCode::NOT_FOUND),
-            };
+    tauri::Builder::default()
+        .manage(runner.clone())
+        .manage(scan_cache.clone())
+        .manage(plans.clone())
+        .manage(bus.clone())
+        .manage(notifier.clone())
+        .manage(checker.clone())
+        .setup(move |app| {
+            // Without a tray the window simply quits on close.
+            if let Err(e) = tray::install(
+                app,
+                runner.clone(),
+                scan_cache.clone(),
+                plans,
+                bus.clone(),
+                notifier.clone(),
+                checker.clone(),
+            ) {
+                eprintln!("scope: no tray icon: {e}");
+            }
+            attach_notifier(app, notifier.clone());
+            // The window works without the bus; only other components lose
+            // access (no session bus, or another instance owns the name).
+            let service_bus = bus.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = service_bus.serve(service).await {
+                    eprintln!("scope: D-Bus service unavailable: {e:#}");
+                }
+            });
+            tauri::async_runtime::spawn(forward_scan_diffs(
+                app.handle().clone(),
+                scan_cache.clone(),
+                bus,
+                scan_cache.subscribe(),
+            ));
+            tauri::async_runtime::spawn(forward_update_checks(
+                app.handle().clone(),
+                checker.subscribe(),
+            ));
+            tauri::async_runtime::spawn(notify::watch_security_updates(
+                notifier,
+                scan_cache.clone(),
+                checker.subscribe(),
+            ));
+            // Resolve icons again whenever the icon theme changes.
+            let (theme_runner, theme_cache) = (runner.clone(), scan_cache.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = icons::watch::run(theme_runner, theme_cache).await {
+                    eprintln!("scope: not watching the icon theme: {e:#}");
+                }
+            });
+            // Replace the stale scan loaded from disk, start checking for
+            // updates, then keep the cache current as packages change.
+            tauri::async_runtime::spawn(async move {
+                scan_cache.refresh(&runner).await;
+                if let Some(interval) = updates::interval() {
+                    tauri::async_runtime::spawn(checker.run_schedule(interval));
+                }
+                if let Err(e) = watcher::run(runner, scan_cache).await {
+                    eprintln!("scope: not watching for package changes: {e:#}");
+                }
+            });
+            Ok(())
+        })
+        .register_asynchronous_uri_scheme_protocol("scope-icon", |_app, request, responder| {
+            // Only icon paths `icons::icon_url` handed out, only as images;
+            // anything else a crafted URL names is refused. Thumbnails may
+            // need rendering, so answer off the webview's thread.
+            tauri::async_runtime::spawn_blocking(move || {
+                let uri = request.uri();
+                let (data, mime, status) = match icons::serve(uri.path(), uri.query()) {
+                    icons::IconResponse::Image(bytes, mime) => (bytes, mime, StatusCode::OK),
+                    icons::IconResponse::Forbidden => {
+                        (Vec::new(), "text/plain", StatusCode::FORBIDDEN)
+                    }
+                    icons::IconResponse::NotFound => {
+                        (Vec::new(), "text/plain", StatusCode::NOT_FOUND)
+                    }
+                };
 
-            Response::builder()
-                .status(status)
-                .header(header::CONTENT_TYPE, mime)
-                .header(header::CACHE_CONTROL, "public, max-age=86400, immutable")
-                .body(data)
-                .expect("scope-icon response is always constructable")
+                responder.respond(
+                    Response::builder()
+                        .status(status)
+                        .header(header::CONTENT_TYPE, mime)
+                        .header(header::CACHE_CONTROL, "public, max-age=86400, immutable")
+                        .body(data)
+                        .expect("scope-icon response is always constructable"),
+                );
+            });
         })
         .invoke_handler(tauri::generate_handler![
             scan_packages,
             get_cached_scan,
             scan_status,
+            config_status,
             search_packages,
+            check_updates,
             preview_uninstall,
             apply_uninstall,
             preview_update,
-            apply_update
+            apply_update,
+            preview_operation,
+            apply_operation,
+            package_capabilities
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
-
-/// Decode the percent-escaped path produced by `icons::icon_url`.
-fn percent_decode_path(input: &str) -> String {
-    let bytes = input.as_bytes();
-    let mut out = Vec::with_capacity(bytes.len());
-    let mut i = 0;
-    while i < bytes.len() {
-        if bytes[i] == b'%' && i + 2 < bytes.len() {
-            if let (Some(h), Some(l)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
-                out.push((h << 4) | l);
-                i += 3;
-                continue;
-            }
-        }
-        out.push(bytes[i]);
-        i += 1;
-    }
-    String::from_utf8_lossy(&out).into_owned()
-}
-
-fn hex(b: u8) -> Option<u8> {
-    match b {
-        b'0'..=b'9' => Some(b - b'0'),
-        b'a'..=b'f' => Some(b - b'a' + 10),
-        b'A'..=b'F' => Some(b - b'A' + 10),
-        _ => None,
-    }
-}
@@ -0,0 +1,491 @@
+//! Desktop notifications through `org.freedesktop.Notifications`.
+//!
+//! Strategy: notifications are for when Scope is not in front: operations
+//! that finish while the window is hidden or unfocused (an "Update all" from
+//! the tray) and security updates that a background check finds. While the
+//! window has focus nothing is sent, since the window already shows both.
+//! Each kind of notification is throttled to one per [`MIN_INTERVAL`]; what
+//! arrives in between is held back and sent as one summary when the interval
+//! has passed, so a batch of updates ends in one or two notifications rather
+//! than a stack. Every notification carries a default action naming the
+//! [`DialogTarget`] to reopen; clicks are handed to the callback given to
+//! [`Notifier::connect`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures_lite::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use zbus::zvariant::Value;
+
+use crate::operations::{Operation, OperationPlan, OperationResult};
+use crate::package::InstalledPackage;
+use crate::scan_cache::{CachedScan, ScanCache};
+use crate::updates::UpdateCheck;
+
+/// Shortest time between two notifications of the same kind.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Notification ids whose action targets are remembered.
+const MAX_TARGETS: usize = 32;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+/// What a notification's action opens: a package's dialog for `operation`,
+/// the package itself when `operation` is `None`, or just the window when
+/// `package_key` is `None`. Sent to the window as the `open-dialog` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DialogTarget {
+    pub package_key: Option<String>,
+    pub operation: Option<Operation>,
+}
+
+/// Something worth telling the user about.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An applied plan finished, successfully or not.
+    OperationFinished {
+        plan: Box<OperationPlan>,
+        result: OperationResult,
+    },
+    /// Security updates that were not available at the previous check.
+    SecurityUpdates(Vec<InstalledPackage>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Operation,
+    SecurityUpdates,
+}
+
+impl Event {
+    fn kind(&self) -> Kind {
+        match self {
+            Event::OperationFinished { .. } => Kind::Operation,
+            Event::SecurityUpdates(_) => Kind::SecurityUpdates,
+        }
+    }
+}
+
+/// A rendered notification.
+#[derive(Debug, PartialEq)]
+struct Notification {
+    summary: String,
+    body: String,
+    /// Failures ask for attention.
+    critical: bool,
+    target: DialogTarget,
+}
+
+type ActionHandler = Box<dyn Fn(DialogTarget) + Send + Sync>;
+
+/// Shared handle for sending notifications, managed as Tauri state. Sends
+/// nothing until [`connect`](Self::connect) succeeds.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    proxy: OnceLock<NotificationsProxy<'static>>,
+    /// Whether the window has focus.
+    foreground: AtomicBool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    last_sent: HashMap<Kind, Instant>,
+    /// Events held back by the throttle, oldest first.
+    pending: HashMap<Kind, Vec<Event>>,
+    /// Action targets by notification id, oldest first.
+    targets: Vec<(u32, DialogTarget)>,
+}
+
+impl Notifier {
+    /// Connect to the notification server on the session bus and pass the
+    /// target of every clicked notification to `on_action`.
+    pub async fn connect(
+        &self,
+        on_action: impl Fn(DialogTarget) + Send + Sync + 'static,
+    ) -> Result<()> {
+        let connection = zbus::Connection::session()
+            .await
+            .context("connect to the session bus")?;
+        let proxy = NotificationsProxy::new(&connection)
+            .await
+            .context("reach org.freedesktop.Notifications")?;
+        let mut actions = proxy.receive_action_invoked().await?;
+        let _ = self.inner.proxy.set(proxy);
+
+        let on_action: ActionHandler = Box::new(on_action);
+        let this = self.clone();
+        tokio::spawn(async move {
+            while let Some(signal) = actions.next().await {
+                let Ok(args) = signal.args() else { continue };
+                if let Some(target) = this.target(args.id) {
+                    on_action(target);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Record whether the window has focus; nothing is sent while it does.
+    pub fn set_foreground(&self, foreground: bool) {
+        self.inner.foreground.store(foreground, Ordering::SeqCst);
+    }
+
+    /// Tell the user about `event`, now or, when another notification of its
+    /// kind went out less than [`MIN_INTERVAL`] ago, together with whatever
+    /// else arrives until then.
+    pub async fn notify(&self, event: Event) {
+        if self.inner.proxy.get().is_none() || self.inner.foreground.load(Ordering::SeqCst) {
+            return;
+        }
+        let kind = event.kind();
+        let wait = {
+            let mut state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+            let pending = state.pending.entry(kind).or_default();
+            pending.push(event);
+            if pending.len() > 1 {
+                // A send is already scheduled and will include this event.
+                return;
+            }
+            state
+                .last_sent
+                .get(&kind)
+                .map(|sent| MIN_INTERVAL.saturating_sub(sent.elapsed()))
+                .unwrap_or_default()
+        };
+        if wait.is_zero() {
+            self.flush(kind).await;
+        } else {
+            let this = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(wait).await;
+                this.flush(kind).await;
+            });
+        }
+    }
+
+    /// Send everything pending for `kind` as one notification.
+    async fn flush(&self, kind: Kind) {
+        let events = {
+            let mut state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.last_sent.insert(kind, Instant::now());
+            state.pending.remove(&kind).unwrap_or_default()
+        };
+        let Some(notification) = render(&events) else {
+            return;
+        };
+        match self.send(&notification).await {
+            Ok(id) => {
+                let mut state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+                state.targets.push((id, notification.target));
+                let excess = state.targets.len().saturating_sub(MAX_TARGETS);
+                state.targets.drain(..excess);
+            }
+            Err(e) => eprintln!("scope: could not send a notification: {e}"),
+        }
+    }
+
+    async fn send(&self, notification: &Notification) -> zbus::Result<u32> {
+        let Some(proxy) = self.inner.proxy.get() else {
+            return Ok(0);
+        };
+        // Urgency: 1 normal, 2 critical.
+        let urgency = Value::U8(if notification.critical { 2 } else { 1 });
+        let desktop_entry = Value::from("com.khurram.scope");
+        let hints = HashMap::from([("urgency", &urgency), ("desktop-entry", &desktop_entry)]);
+        proxy
+            .notify(
+                "Scope",
+                0,
+                "system-software-update",
+                &notification.summary,
+                &notification.body,
+                &["default", "Open"],
+                hints,
+                -1,
+            )
+            .await
+    }
+
+    fn target(&self, id: u32) -> Option<DialogTarget> {
+        let state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .targets
+            .iter()
+            .find(|(sent, _)| *sent == id)
+            .map(|(_, target)| target.clone())
+    }
+}
+
+/// Notify about security updates that appear in the cached scan after each
+/// update check. Those already in the scan when this starts (the previous
+/// run's, loaded from disk) count as known.
+pub async fn watch_security_updates(
+    notifier: Notifier,
+    cache: ScanCache,
+    mut checks: Receiver<UpdateCheck>,
+) {
+    let mut known = security_updates(cache.get().await.as_ref());
+    loop {
+        match checks.recv().await {
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => break,
+        }
+        let Some(scan) = cache.get().await else {
+            continue;
+        };
+        let current = security_updates(Some(&scan));
+        let new: Vec<InstalledPackage> = scan
+            .packages
+            .iter()
+            .filter(|p| p.security_update && !known.contains(&update_id(p)))
+            .cloned()
+            .collect();
+        known = current;
+        if !new.is_empty() {
+            notifier.notify(Event::SecurityUpdates(new)).await;
+        }
+    }
+}
+
+/// A package's security update, told apart by the version it goes to.
+fn update_id(pkg: &InstalledPackage) -> (String, Option<String>) {
+    (pkg.key.clone(), pkg.update_version.clone())
+}
+
+fn security_updates(scan: Option<&CachedScan>) -> HashSet<(String, Option<String>)> {
+    scan.map(|scan| {
+        scan.packages
+            .iter()
+            .filter(|p| p.security_update)
+            .map(update_id)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// One notification for `events`, which are all of one kind.
+fn render(events: &[Event]) -> Option<Notification> {
+    let mut finished = Vec::new();
+    let mut security: Vec<&InstalledPackage> = Vec::new();
+    for event in events {
+        match event {
+            Event::OperationFinished { plan, result } => finished.push((plan, result)),
+            Event::SecurityUpdates(packages) => {
+                for pkg in packages {
+                    security.retain(|p| p.key != pkg.key);
+                    security.push(pkg);
+                }
+            }
+        }
+    }
+    match (finished.as_slice(), security.as_slice()) {
+        ([], []) => None,
+        ([(plan, result)], _) => Some(operation_notification(plan, result)),
+        ([_, _, ..], _) => {
+            let failed = finished.iter().filter(|(_, r)| !r.success).count();
+            let summary = if failed == 0 {
+                format!("{} operations finished", finished.len())
+            } else {
+                format!("{failed} of {} operations failed", finished.len())
+            };
+            let body = finished
+                .iter()
+                .map(|(plan, result)| format!("{}: {}", plan.display_name, result.message))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some(Notification {
+                summary,
+                body,
+                critical: failed > 0,
+                target: DialogTarget {
+                    package_key: None,
+                    operation: None,
+                },
+            })
+        }
+        ([], [pkg]) => Some(Notification {
+            summary: format!("Security update for {}", label(pkg)),
+            body: match &pkg.update_version {
+                Some(version) => format!("{} → {version}", pkg.version),
+                None => format!("An update for {} is available.", label(pkg)),
+            },
+            critical: false,
+            target: DialogTarget {
+                package_key: Some(pkg.key.clone()),
+                operation: Some(Operation::Update),
+            },
+        }),
+        ([], packages) => Some(Notification {
+            summary: format!("{} security updates available", packages.len()),
+            body: packages
+                .iter()
+                .map(|p| label(p))
+                .collect::<Vec<_>>()
+                .join(", "),
+            critical: false,
+            target: DialogTarget {
+                package_key: None,
+                operation: None,
+            },
+        }),
+    }
+}
+
+fn operation_notification(plan: &OperationPlan, result: &OperationResult) -> Notification {
+    let (verb, done) = match plan.operation {
+        Operation::Uninstall => ("remove", "Removed"),
+        Operation::Update => ("update", "Updated"),
+        Operation::Purge => ("purge", "Purged"),
+        Operation::Hold => ("hold", "Held"),
+        Operation::Unhold => ("release", "Released"),
+        Operation::Rollback => ("roll back", "Rolled back"),
+    };
+    let name = &plan.display_name;
+    let gone = matches!(plan.operation, Operation::Uninstall | Operation::Purge);
+    let target = match (result.success, gone) {
+        // Reopen the dialog to try again.
+        (false, _) => DialogTarget {
+            package_key: Some(plan.package_key()),
+            operation: Some(plan.operation),
+        },
+        (true, false) => DialogTarget {
+            package_key: Some(plan.package_key()),
+            operation: None,
+        },
+        (true, true) => DialogTarget {
+            package_key: None,
+            operation: None,
+        },
+    };
+    Notification {
+        summary: if result.success {
+            format!("{done} {name}")
+        } else {
+            format!("Could not {verb} {name}")
+        },
+        body: result.message.clone(),
+        critical: !result.success,
+        target,
+    }
+}
+
+fn label(pkg: &InstalledPackage) -> &str {
+    pkg.display_name.as_deref().unwrap_or(&pkg.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{AuthMethod, OperationResult};
+    use crate::package::PackageSource;
+
+    fn finished(name: &str, operation: Operation, success: bool) -> Event {
+        Event::OperationFinished {
+            plan: Box::new(OperationPlan {
+                plan_id: format!("plan-{name}"),
+                operation,
+                source: PackageSource::Apt,
+                package_id: name.into(),
+                install_scope: None,
+                display_name: name.into(),
+                current_version: "1".into(),
+                target_version: "2".into(),
+                requires_auth: true,
+                auth_method: AuthMethod::Pkexec,
+                protected: false,
+                protection_reason: None,
+                warnings: Vec::new(),
+                steps: Vec::new(),
+                created_at_ms: 0,
+            }),
+            result: OperationResult {
+                success,
+                message: if success { "Done." } else { "apt failed." }.into(),
+                logs: String::new(),
+                exit_code: None,
+            },
+        }
+    }
+
+    #[test]
+    fn renders_single_and_batched_operations() {
+        let failed = render(&[finished("curl", Operation::Update, false)]).unwrap();
+        assert_eq!(failed.summary, "Could not update curl");
+        assert_eq!(failed.body, "apt failed.");
+        assert!(failed.critical);
+        assert_eq!(
+            failed.target,
+            DialogTarget {
+                package_key: Some("apt:curl".into()),
+                operation: Some(Operation::Update),
+            }
+        );
+
+        let removed = render(&[finished("vlc", Operation::Uninstall, true)]).unwrap();
+        assert_eq!(removed.summary, "Removed vlc");
+        assert_eq!(removed.target.package_key, None);
+
+        let batch = render(&[
+            finished("curl", Operation::Update, true),
+            finished("vim", Operation::Update, false),
+            finished("git", Operation::Update, true),
+        ])
+        .unwrap();
+        assert_eq!(batch.summary, "1 of 3 operations failed");
+        assert_eq!(batch.body, "curl: Done.\nvim: apt failed.\ngit: Done.");
+    }
+
+    #[test]
+    fn merges_security_updates() {
+        let mut curl = InstalledPackage::new(PackageSource::Apt, "curl");
+        curl.name = "curl".into();
+        curl.version = "8.5.0-2ubuntu10.6".into();
+        curl.update_version = Some("8.5.0-2ubuntu10.7".into());
+        let one = render(&[Event::SecurityUpdates(vec![curl.clone()])]).unwrap();
+        assert_eq!(one.summary, "Security update for curl");
+        assert_eq!(one.body, "8.5.0-2ubuntu10.6 → 8.5.0-2ubuntu10.7");
+        assert_eq!(one.target.operation, Some(Operation::Update));
+
+        let mut openssl = InstalledPackage::new(PackageSource::Apt, "openssl");
+        openssl.name = "openssl".into();
+        let merged = render(&[
+            Event::SecurityUpdates(vec![curl.clone()]),
+            Event::SecurityUpdates(vec![curl, openssl]),
+        ])
+        .unwrap();
+        assert_eq!(merged.summary, "2 security updates available");
+        assert_eq!(merged.body, "curl, openssl");
+    }
+}
@@ -5,17 +5,21 @@
 //! the user confirms. The apply step only trusts a plan id that the backend
 //! itself issued (stored in [`PlanStore`]) and revalidates the system state
 //! before executing — so a stale or tampered plan is rejected.
-
-pub mod uninstall;
-pub mod update;
+//!
+//! The flow here is source-agnostic: the source-specific steps and commands
+//! come from the package's [`crate::backend::PackageBackend`].
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::package::{InstallScope, PackageSource};
+use crate::backend::{self, PlanDraft};
+use crate::package::{package_key, InstallScope, InstalledPackage, PackageSource};
+use crate::safety;
+use crate::system::Runner;
 
 /// What kind of operation a plan describes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -23,6 +27,62 @@ use crate::package::{InstallScope, PackageSource};
 pub enum Operation {
     Uninstall,
     Update,
+    /// Uninstall and also delete configuration/user data.
+    Purge,
+    /// Pin the installed version so updates skip it.
+    Hold,
+    /// Release a previous hold.
+    Unhold,
+    /// Revert to the previously installed version.
+    Rollback,
+}
+
+impl Operation {
+    /// Machine identifier, same as the serialized form.
+    pub fn id(self) -> &'static str {
+        match self {
+            Operation::Uninstall => "uninstall",
+            Operation::Update => "update",
+            Operation::Purge => "purge",
+            Operation::Hold => "hold",
+            Operation::Unhold => "unhold",
+            Operation::Rollback => "rollback",
+        }
+    }
+
+    /// Backend capability required to run this operation.
+    pub fn capability(self) -> backend::Capability {
+        use backend::Capability;
+        match self {
+            Operation::Uninstall => Capability::Uninstall,
+            Operation::Update => Capability::Update,
+            Operation::Purge => Capability::Purge,
+            Operation::Hold | Operation::Unhold => Capability::Hold,
+            Operation::Rollback => Capability::Rollback,
+        }
+    }
+
+    /// Max time the operation's command may run before we cancel it, from
+    /// the user's config (`[timeouts]`).
+    pub fn timeout(self) -> Duration {
+        let timeouts = &crate::config::current().timeouts;
+        match self {
+            Operation::Uninstall | Operation::Purge => timeouts.uninstall(),
+            Operation::Update | Operation::Rollback => timeouts.update(),
+            Operation::Hold | Operation::Unhold => timeouts.hold(),
+        }
+    }
+
+    /// Verb used in blocked-plan and error messages ("cannot be ...").
+    fn past_participle(self) -> &'static str {
+        match self {
+            Operation::Uninstall | Operation::Purge => "removed",
+            Operation::Update => "updated",
+            Operation::Hold => "held",
+            Operation::Unhold => "released",
+            Operation::Rollback => "rolled back",
+        }
+    }
 }
 
 /// How privilege escalation is handled. Scope never touches passwords — `pkexec`
@@ -32,7 +92,7 @@ pub enum Operation {
 pub enum AuthMethod {
     /// No elevation needed (user-installed flatpaks, AppImage trash).
     None,
-    /// Runs via `pkexec`, which triggers a Polkit password popup.
+    /// Runs `scope-helper` via `pkexec`, which triggers a Polkit password popup.
     Pkexec,
 }
 
@@ -63,10 +123,20 @@ pub struct OperationPlan {
     pub auth_method: AuthMethod,
     pub protected: bool,
     pub protection_reason: Option<String>,
+    /// What else the operation affects, e.g. packages a removal takes with
+    /// it. Shown before confirming; never blocks.
+    pub warnings: Vec<String>,
     pub steps: Vec<PlanStep>,
     pub created_at_ms: u64,
 }
 
+impl OperationPlan {
+    /// Key of the package the plan is for, as in the scan.
+    pub fn package_key(&self) -> String {
+        package_key(self.source, self.install_scope, &self.package_id)
+    }
+}
+
 /// Outcome of applying a plan.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OperationResult {
@@ -77,8 +147,8 @@ pub struct OperationResult {
 }
 
 /// In-memory store of issued plans, keyed by id. Plans expire after
-/// [`PLAN_TTL`] so a user who walks away cannot later apply a stale plan that
-/// no longer reflects the system.
+/// [`PLAN_TTL`] (or the configured `[timeouts] plan`) so a user who walks away
+/// cannot later apply a stale plan that no longer reflects the system.
 #[derive(Default, Clone)]
 pub struct PlanStore {
     inner: Arc<tokio::sync::Mutex<HashMap<String, StoredPlan>>>,
@@ -89,7 +159,7 @@ struct StoredPlan {
     created: Instant,
 }
 
-/// Plans are valid for 5 minutes after preview.
+/// Plans are valid for 5 minutes after preview unless configured otherwise.
 pub const PLAN_TTL: Duration = Duration::from_secs(5 * 60);
 
 impl PlanStore {
@@ -104,16 +174,18 @@ impl PlanStore {
             },
         );
         // Opportunistic cleanup of expired entries.
-        guard.retain(|_, v| v.created.elapsed() < PLAN_TTL);
+        let ttl = crate::config::current().timeouts.plan();
+        guard.retain(|_, v| v.created.elapsed() < ttl);
     }
 
     /// Take (and remove) a non-expired plan. Returns `None` if missing/expired,
     /// which the apply command treats as a stale-plan rejection.
     pub async fn take(&self, plan_id: &str) -> Option<OperationPlan> {
+        let ttl = crate::config::current().timeouts.plan();
         let mut guard = self.inner.lock().await;
         let exists = guard
             .get(plan_id)
-            .map(|v| v.created.elapsed() < PLAN_TTL)
+            .map(|v| v.created.elapsed() < ttl)
             .unwrap_or(false);
         if exists {
             guard.remove(plan_id).map(|v| v.plan)
@@ -144,3 +216,211 @@ pub fn now_ms() -> u64 {
         .map(|d| d.as_millis() as u64)
         .unwrap_or(0)
 }
+
+/// Build a preview plan for one operation on a package from the cached scan.
+/// The package must come from a scan so the frontend can never nominate an
+/// arbitrary id we haven't seen.
+///
+/// Fails when the package's backend lacks the capability, or when the
+/// operation does not apply to the package's current state (no update,
+/// already held, ...). Protected packages get a blocked plan instead, so the
+/// UI can explain why.
+pub async fn preview(
+    runner: &Runner,
+    pkg: &InstalledPackage,
+    operation: Operation,
+) -> Result<OperationPlan> {
+    let name = pkg.display_name.clone().unwrap_or_else(|| pkg.name.clone());
+    let backend = backend::for_source(pkg.source);
+    if !backend
+        .package_capabilities(pkg)
+        .contains(&operation.capability())
+    {
+        anyhow::bail!("'{name}' cannot be {}.", operation.past_participle());
+    }
+    match operation {
+        Operation::Update if !pkg.has_update => {
+            anyhow::bail!("'{name}' has no updates available.")
+        }
+        Operation::Hold if pkg.held => anyhow::bail!("'{name}' is already held."),
+        Operation::Unhold if !pkg.held => anyhow::bail!("'{name}' is not held."),
+        _ => {}
+    }
+
+    let mut protection = safety::check_installed(pkg, operation, &runner.dirs());
+    let mut warnings = Vec::new();
+    if !protection.protected && matches!(operation, Operation::Uninstall | Operation::Purge) {
+        let dependents = safety::dependents::check(runner.as_ref(), pkg).await;
+        if let Some(reason) = dependents.blocked {
+            protection = safety::Protection::denied(reason);
+        }
+        warnings = dependents.warnings;
+    }
+    let draft = if protection.protected {
+        PlanDraft::blocked(
+            protection.reason.unwrap_or_else(|| "protected".into()),
+            format!(
+                "Blocked: this package is protected and cannot be {}.",
+                operation.past_participle()
+            ),
+        )
+    } else {
+        backend.plan(runner, pkg, operation).await
+    };
+
+    let target_version = draft.target_version.unwrap_or_else(|| match operation {
+        Operation::Update => pkg
+            .update_version
+            .clone()
+            .unwrap_or_else(|| "latest".into()),
+        Operation::Rollback => "previous version".into(),
+        _ => String::new(),
+    });
+    Ok(OperationPlan {
+        plan_id: new_plan_id(),
+        operation,
+        source: pkg.source,
+        package_id: pkg.package_id.clone(),
+        install_scope: pkg.install_scope,
+        display_name: name,
+        current_version: pkg.version.clone(),
+        target_version,
+        requires_auth: draft.requires_auth,
+        auth_method: draft.auth,
+        protected: draft.blocked.is_some(),
+        protection_reason: draft.blocked,
+        warnings,
+        steps: draft.steps,
+        created_at_ms: now_ms(),
+    })
+}
+
+/// Re-validate a plan against a fresh scan before applying: the package must
+/// still be installed, still pass the safety check, and the operation must
+/// still apply to it.
+pub fn revalidate(runner: &Runner, plan: &OperationPlan, scan: &[InstalledPackage]) -> Result<()> {
+    let Some(pkg) = scan.iter().find(|p| {
+        p.source == plan.source
+            && p.package_id == plan.package_id
+            && p.install_scope == plan.install_scope
+    }) else {
+        anyhow::bail!(
+            "This {} plan is stale: '{}' is no longer installed. Rescan and try again.",
+            plan.operation.id(),
+            plan.display_name
+        );
+    };
+    // Re-run the safety check in case state changed since preview.
+    let protection = safety::check_installed(pkg, plan.operation, &runner.dirs());
+    if protection.protected {
+        anyhow::bail!(
+            "Refusing to change protected package: {}",
+            protection.reason.unwrap_or_else(|| "protected".into())
+        );
+    }
+    let stale = match plan.operation {
+        Operation::Update => !pkg.has_update,
+        Operation::Hold => pkg.held,
+        Operation::Unhold => !pkg.held,
+        _ => false,
+    };
+    if stale {
+        anyhow::bail!(
+            "'{}' changed since the preview. Rescan and try again.",
+            plan.display_name
+        );
+    }
+    Ok(())
+}
+
+/// Re-run the reverse-dependency check of an uninstall or purge plan right
+/// before applying it: what a removal takes with it can change while the
+/// plan waits (a protected metapackage installed since the preview).
+/// Other operations pass.
+pub async fn revalidate_dependents(
+    runner: &Runner,
+    plan: &OperationPlan,
+    scan: &[InstalledPackage],
+) -> Result<()> {
+    if !matches!(plan.operation, Operation::Uninstall | Operation::Purge) {
+        return Ok(());
+    }
+    let Some(pkg) = scan.iter().find(|p| {
+        p.source == plan.source
+            && p.package_id == plan.package_id
+            && p.install_scope == plan.install_scope
+    }) else {
+        return Ok(());
+    };
+    if let Some(reason) = safety::dependents::check(runner.as_ref(), pkg)
+        .await
+        .blocked
+    {
+        anyhow::bail!("Refusing to change protected package: {reason}");
+    }
+    Ok(())
+}
+
+/// Execute a revalidated plan through its source's backend.
+pub async fn apply(runner: &Runner, plan: &OperationPlan) -> OperationResult {
+    backend::for_source(plan.source).apply(runner, plan).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture_runner;
+
+    /// preview → revalidate → apply against the recorded Ubuntu system: the
+    /// apply step is recorded, never run, and scripted failures come back as
+    /// real ones would.
+    #[tokio::test]
+    async fn dry_run_pipeline_records_apply_steps() {
+        let fixtures = fixture_runner("ubuntu-24.04");
+        let runner: Runner = fixtures.clone();
+        let (mut scan, availability) = crate::scanner::scan_all(&runner).await;
+        assert!(crate::updates::check_packages(&runner, &mut scan)
+            .await
+            .is_empty());
+        assert!(availability.apt && availability.snap && availability.flatpak);
+        assert!(availability.appimage && availability.steam);
+        let curl = scan.iter().find(|p| p.key == "apt:curl").unwrap();
+
+        let plan = preview(&runner, curl, Operation::Update).await.unwrap();
+        assert_eq!(plan.target_version, "8.5.0-2ubuntu10.7");
+        revalidate(&runner, &plan, &scan).unwrap();
+        let res = apply(&runner, &plan).await;
+        assert!(res.success, "{}", res.message);
+        let calls = fixtures.calls();
+        let last = calls.last().unwrap();
+        assert_eq!(last[0], "pkexec");
+        assert!(last.ends_with(&["update".into(), "apt".into(), "curl".into()]));
+
+        // vlc is held; the corpus scripts apt's refusal to remove it.
+        let vlc = scan.iter().find(|p| p.key == "apt:vlc").unwrap();
+        let plan = preview(&runner, vlc, Operation::Uninstall).await.unwrap();
+        let res = apply(&runner, &plan).await;
+        assert!(!res.success);
+        assert_eq!(res.exit_code, Some(100));
+        assert!(res.message.contains("Held packages were changed"));
+    }
+
+    #[tokio::test]
+    async fn apply_rechecks_what_a_removal_takes_along() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+        let htop = InstalledPackage::new(PackageSource::Apt, "htop");
+        let mut plan = preview(&runner, &htop, Operation::Uninstall).await.unwrap();
+        assert!(!plan.protected);
+        revalidate_dependents(&runner, &plan, std::slice::from_ref(&htop))
+            .await
+            .unwrap();
+
+        // The recorded simulation removes `ubuntu-desktop` along with it.
+        let portal = InstalledPackage::new(PackageSource::Apt, "xdg-desktop-portal-custom");
+        plan.package_id = portal.package_id.clone();
+        let err = revalidate_dependents(&runner, &plan, &[portal])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'ubuntu-desktop'"), "{err}");
+    }
+}
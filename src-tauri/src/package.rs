@@ -14,6 +14,10 @@ pub enum PackageSource {
     Snap,
     Flatpak,
     AppImage,
+    Container,
+    Steam,
+    Extension,
+    Plugin,
 }
 
 impl PackageSource {
@@ -24,17 +28,39 @@ impl PackageSource {
             PackageSource::Snap => "snap",
             PackageSource::Flatpak => "flatpak",
             PackageSource::AppImage => "appimage",
+            PackageSource::Container => "container",
+            PackageSource::Steam => "steam",
+            PackageSource::Extension => "extension",
+            PackageSource::Plugin => "plugin",
+        }
+    }
+
+    /// Parse a machine identifier (case-insensitive), the inverse of [`Self::id`].
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id.to_lowercase().as_str() {
+            "apt" => Some(PackageSource::Apt),
+            "snap" => Some(PackageSource::Snap),
+            "flatpak" => Some(PackageSource::Flatpak),
+            "appimage" => Some(PackageSource::AppImage),
+            "container" => Some(PackageSource::Container),
+            "steam" => Some(PackageSource::Steam),
+            "extension" => Some(PackageSource::Extension),
+            "plugin" => Some(PackageSource::Plugin),
+            _ => None,
         }
     }
 
     /// Human label shown in the UI.
-    #[allow(dead_code)]
     pub fn label(self) -> &'static str {
         match self {
             PackageSource::Apt => "APT",
             PackageSource::Snap => "Snap",
             PackageSource::Flatpak => "Flatpak",
             PackageSource::AppImage => "AppImage",
+            PackageSource::Container => "Container",
+            PackageSource::Steam => "Steam",
+            PackageSource::Extension => "Extension",
+            PackageSource::Plugin => "Plugin",
         }
     }
 }
@@ -49,6 +75,61 @@ pub enum AppKind {
     Unknown,
 }
 
+impl AppKind {
+    /// Parse the serialized form (case-insensitive).
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id.to_lowercase().as_str() {
+            "gui" => Some(AppKind::Gui),
+            "cli" => Some(AppKind::Cli),
+            "unknown" => Some(AppKind::Unknown),
+            _ => None,
+        }
+    }
+}
+
+/// dpkg metadata that makes an APT package part of the base system. Packages
+/// carrying one are never removed through Scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BaseSystem {
+    /// `Essential: yes`: dpkg itself assumes the package is always installed.
+    Essential,
+    /// `Protected: yes` (dpkg 1.20.1+): needed to boot the system.
+    Protected,
+    /// `Priority: required`.
+    Required,
+    /// `Priority: important`.
+    Important,
+}
+
+impl BaseSystem {
+    /// The control field that marks the package, as dpkg shows it.
+    pub fn field(self) -> &'static str {
+        match self {
+            BaseSystem::Essential => "Essential: yes",
+            BaseSystem::Protected => "Protected: yes",
+            BaseSystem::Required => "Priority: required",
+            BaseSystem::Important => "Priority: important",
+        }
+    }
+
+    /// The strongest mark among dpkg's `Essential`, `Protected` and
+    /// `Priority` values, if any.
+    pub fn from_fields(essential: &str, protected: &str, priority: &str) -> Option<Self> {
+        if essential.trim() == "yes" {
+            Some(BaseSystem::Essential)
+        } else if protected.trim() == "yes" {
+            Some(BaseSystem::Protected)
+        } else {
+            match priority.trim() {
+                "required" => Some(BaseSystem::Required),
+                "important" => Some(BaseSystem::Important),
+                _ => None,
+            }
+        }
+    }
+}
+
 /// Where a package is installed when the package manager has multiple scopes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -71,14 +152,16 @@ impl InstallScope {
 /// GUI metadata (`display_name`, `icon`, `categories`, `terminal`) is an
 /// enrichment layer filled in by the desktop-entry merge step. Non-GUI packages
 /// keep `AppKind::Cli`/`Unknown` and still appear in the unified list.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InstalledPackage {
     /// Backend-side stable key: `<source>:<package id>`.
     pub key: String,
     /// Source package manager.
     pub source: PackageSource,
     /// Package id as the package manager knows it (dpkg name, snap name,
-    /// flatpak application id, or AppImage absolute path).
+    /// flatpak application id, AppImage absolute path, the host-side export
+    /// file of a container app, a Steam app id, an extension directory, or
+    /// `<plugin>:<id>` for external plugins).
     pub package_id: String,
     /// Install scope for package managers that can install the same id in more
     /// than one place, such as Flatpak user/system installations.
@@ -114,12 +197,36 @@ pub struct InstalledPackage {
     /// The version string of the available update, if known.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update_version: Option<String>,
+    /// True when the available update fixes security issues (APT updates
+    /// published to a `-security` suite).
+    #[serde(default)]
+    pub security_update: bool,
+    /// True when the package is pinned at its current version (`apt-mark
+    /// hold`, `snap refresh --hold`, `flatpak mask`).
+    pub held: bool,
+    /// Enabled state for items that can be switched off without being
+    /// removed (GNOME Shell extensions). `None` when not applicable/unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// For APT packages, the dpkg metadata that makes it part of the base
+    /// system, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_system: Option<BaseSystem>,
+}
+
+/// The key of a package: `source:id`, or `source:scope:id` for sources with
+/// several installations.
+pub fn package_key(source: PackageSource, scope: Option<InstallScope>, package_id: &str) -> String {
+    match scope {
+        Some(scope) => format!("{}:{}:{}", source.id(), scope.id(), package_id),
+        None => format!("{}:{}", source.id(), package_id),
+    }
 }
 
 impl InstalledPackage {
     pub fn new(source: PackageSource, package_id: impl Into<String>) -> Self {
         let package_id = package_id.into();
-        let key = format!("{}:{}", source.id(), package_id);
+        let key = package_key(source, None, &package_id);
         Self {
             key,
             source,
@@ -136,6 +243,10 @@ impl InstalledPackage {
             terminal: false,
             has_update: false,
             update_version: None,
+            security_update: false,
+            held: false,
+            enabled: None,
+            base_system: None,
         }
     }
 
@@ -145,7 +256,7 @@ impl InstalledPackage {
         scope: InstallScope,
     ) -> Self {
         let package_id = package_id.into();
-        let key = format!("{}:{}:{}", source.id(), scope.id(), package_id);
+        let key = package_key(source, Some(scope), &package_id);
         Self {
             key,
             source,
@@ -162,7 +273,47 @@ impl InstalledPackage {
             terminal: false,
             has_update: false,
             update_version: None,
+            security_update: false,
+            held: false,
+            enabled: None,
+            base_system: None,
+        }
+    }
+
+    /// Take `other`'s update annotations (`has_update`, `update_version`,
+    /// `security_update`): the same package, as an update check saw it.
+    pub fn copy_update_from(&mut self, other: &InstalledPackage) {
+        self.has_update = other.has_update;
+        self.update_version = other.update_version.clone();
+        self.security_update = other.security_update;
+    }
+
+    /// Forget the known update, before checking again.
+    pub fn clear_update(&mut self) {
+        self.has_update = false;
+        self.update_version = None;
+        self.security_update = false;
+    }
+
+    /// Case-insensitive search match against name, display name, description,
+    /// package id, install scope, categories and version. `query` must already
+    /// be lowercased; an empty query matches everything.
+    pub fn matches_query(&self, query: &str) -> bool {
+        if query.is_empty() {
+            return true;
         }
+        format!(
+            "{} {} {} {} {} {} {}",
+            self.name,
+            self.display_name.as_deref().unwrap_or(""),
+            self.description.as_deref().unwrap_or(""),
+            self.package_id,
+            self.install_scope.map(|s| s.id()).unwrap_or(""),
+            self.categories.as_deref().unwrap_or(""),
+            self.version
+        )
+        .to_lowercase()
+        .contains(query)
     }
 }
 
@@ -173,5 +324,8 @@ pub struct ScanStatus {
     pub snap_available: bool,
     pub flatpak_available: bool,
     pub appimage_available: bool,
+    pub container_available: bool,
+    pub steam_available: bool,
+    pub extension_available: bool,
     pub appimage_dirs: Vec<String>,
 }
@@ -0,0 +1,305 @@
+//! Reverse dependencies: what else a removal would take out or break.
+//!
+//! Strategy: the static rules in [`super`] only know names. Before an
+//! uninstall or purge, [`check`] asks the package manager what depends on the
+//! target, from local state only:
+//!
+//! - APT: `apt-get -s remove` simulates the removal against the local cache;
+//!   every `Remv` line is a package that goes with the target. If one of them
+//!   is protected (a metapackage such as `ubuntu-desktop`, a critical package)
+//!   the plan is blocked; other packages are listed as a warning, manually
+//!   installed ones by name.
+//! - Flatpak: an installed ref whose runtime is the target blocks it.
+//! - Snap: other snaps whose content plugs are connected to the target's
+//!   slots are a warning; snapd disconnects them rather than refusing.
+//!
+//! A query that fails blocks nothing: the static rules still apply and the
+//! package manager has the final say.
+
+use std::collections::HashSet;
+
+use crate::package::{InstallScope, InstalledPackage, PackageSource};
+use crate::system::{scan_timeout, CommandRunner, Dirs};
+
+use super::check_package;
+
+/// At most this many package names are spelled out in one warning.
+const LISTED: usize = 8;
+
+/// What depends on a package about to be removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependents {
+    /// Set when the removal must not happen.
+    pub blocked: Option<String>,
+    /// Shown with the plan; the user decides.
+    pub warnings: Vec<String>,
+}
+
+/// Reverse dependencies of `pkg`, for a plan that removes it.
+pub async fn check(runner: &dyn CommandRunner, pkg: &InstalledPackage) -> Dependents {
+    match pkg.source {
+        PackageSource::Apt => check_apt(runner, &pkg.package_id).await,
+        PackageSource::Flatpak => check_flatpak(runner, pkg).await,
+        PackageSource::Snap => check_snap(runner, &pkg.package_id).await,
+        _ => Dependents::default(),
+    }
+}
+
+async fn check_apt(runner: &dyn CommandRunner, package_id: &str) -> Dependents {
+    let Ok(simulation) = runner
+        .capture_stdout("apt-get", &["-s", "remove", package_id], scan_timeout())
+        .await
+    else {
+        return Dependents::default();
+    };
+    let manual: HashSet<String> = runner
+        .capture_stdout("apt-mark", &["showmanual"], scan_timeout())
+        .await
+        .map(|out| {
+            out.lines()
+                .map(|l| without_arch(l.trim()).to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    apt_dependents(package_id, &parse_simulation(&simulation), &manual)
+}
+
+/// Package names on the `Remv` lines of `apt-get -s remove` output.
+fn parse_simulation(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.strip_prefix("Remv "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// An APT package name without its `:arch` qualifier.
+fn without_arch(name: &str) -> &str {
+    name.split(':').next().unwrap_or(name)
+}
+
+fn apt_dependents(package_id: &str, removed: &[String], manual: &HashSet<String>) -> Dependents {
+    let target = without_arch(package_id);
+    let others: Vec<&str> = removed
+        .iter()
+        .map(String::as_str)
+        .filter(|name| without_arch(name) != target)
+        .collect();
+    let protected = others.iter().find_map(|name| {
+        let protection = check_package(PackageSource::Apt, name, &Dirs::Host);
+        protection.protected.then_some((name, protection.reason))
+    });
+    if let Some((name, reason)) = protected {
+        return Dependents {
+            blocked: Some(format!(
+                "Removing '{target}' would also remove '{name}'. {}",
+                reason.unwrap_or_default()
+            )),
+            warnings: Vec::new(),
+        };
+    }
+    let (manual, auto): (Vec<&str>, Vec<&str>) = others
+        .into_iter()
+        .partition(|name| manual.contains(without_arch(name)));
+    let mut warnings = Vec::new();
+    if !manual.is_empty() {
+        warnings.push(format!(
+            "Also removes {} you installed: {}.",
+            plural(manual.len(), "package"),
+            listed(&manual)
+        ));
+    }
+    if !auto.is_empty() {
+        warnings.push(format!(
+            "Also removes {}: {}.",
+            plural(auto.len(), "automatically installed package"),
+            listed(&auto)
+        ));
+    }
+    Dependents {
+        blocked: None,
+        warnings,
+    }
+}
+
+async fn check_flatpak(runner: &dyn CommandRunner, pkg: &InstalledPackage) -> Dependents {
+    let scope = match pkg.install_scope {
+        Some(InstallScope::User) => "--user",
+        Some(InstallScope::System) | None => "--system",
+    };
+    let Ok(list) = runner
+        .capture_stdout(
+            "flatpak",
+            &["list", scope, "--columns=application,runtime"],
+            scan_timeout(),
+        )
+        .await
+    else {
+        return Dependents::default();
+    };
+    let users = runtime_users(&list, &pkg.package_id);
+    Dependents {
+        blocked: (!users.is_empty()).then(|| {
+            format!(
+                "'{}' is the runtime of {}: {}.",
+                pkg.package_id,
+                plural(users.len(), "installed app"),
+                listed(&users)
+            )
+        }),
+        warnings: Vec::new(),
+    }
+}
+
+/// Refs in tab-separated `flatpak list --columns=application,runtime` output
+/// whose runtime (`id/arch/branch`) is `id`.
+fn runtime_users<'a>(list: &'a str, id: &str) -> Vec<&'a str> {
+    list.lines()
+        .filter_map(|l| l.split_once('\t'))
+        .filter(|(app, runtime)| *app != id && runtime.split('/').next() == Some(id))
+        .map(|(app, _)| app)
+        .collect()
+}
+
+async fn check_snap(runner: &dyn CommandRunner, name: &str) -> Dependents {
+    let Ok(connections) = runner
+        .capture_stdout("snap", &["connections", name], scan_timeout())
+        .await
+    else {
+        return Dependents::default();
+    };
+    let users = content_users(&connections, name);
+    let mut warnings = Vec::new();
+    if !users.is_empty() {
+        warnings.push(format!(
+            "{} {} content from '{name}' and may stop working: {}.",
+            plural(users.len(), "snap"),
+            if users.len() == 1 { "uses" } else { "use" },
+            listed(&users)
+        ));
+    }
+    Dependents {
+        blocked: None,
+        warnings,
+    }
+}
+
+/// Snaps whose content plugs are connected to a slot of `name`, from
+/// `snap connections` output (`Interface Plug Slot Notes`).
+fn content_users<'a>(connections: &'a str, name: &str) -> Vec<&'a str> {
+    let mut users = Vec::new();
+    for line in connections.lines().skip(1) {
+        let [interface, plug, slot, ..] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        let plug_snap = plug.split(':').next().unwrap_or(plug);
+        let slot_snap = slot.split(':').next().unwrap_or(slot);
+        if (interface == "content" || interface.starts_with("content["))
+            && slot_snap == name
+            && plug_snap != name
+            && !users.contains(&plug_snap)
+        {
+            users.push(plug_snap);
+        }
+    }
+    users
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+/// The first [`LISTED`] names, then how many more.
+fn listed(names: &[&str]) -> String {
+    let shown = names[..names.len().min(LISTED)].join(", ");
+    match names.len().saturating_sub(LISTED) {
+        0 => shown,
+        more => format!("{shown} and {more} more"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn apt_removals_that_take_out_protected_packages_are_blocked() {
+        let simulation = "NOTE: This is only a simulation!\n\
+            Reading package lists...\n\
+            Remv ubuntu-desktop-minimal [1.539.1]\n\
+            Remv ubuntu-desktop [1.539.1]\n\
+            Remv xdg-desktop-portal-gnome [46.2-1]\n";
+        let removed = parse_simulation(simulation);
+        assert_eq!(
+            removed,
+            [
+                "ubuntu-desktop-minimal",
+                "ubuntu-desktop",
+                "xdg-desktop-portal-gnome"
+            ]
+        );
+        let verdict = apt_dependents("xdg-desktop-portal-gnome", &removed, &HashSet::new());
+        let reason = verdict.blocked.unwrap();
+        assert!(reason.contains("'ubuntu-desktop-minimal'"), "{reason}");
+
+        let manual = HashSet::from(["gimp".to_string()]);
+        let verdict = apt_dependents(
+            "libgimp-plugin-x:amd64",
+            &names(&["gimp:amd64", "gimp-data-extras", "libgimp-plugin-x:amd64"]),
+            &manual,
+        );
+        assert_eq!(verdict.blocked, None);
+        assert_eq!(
+            verdict.warnings,
+            [
+                "Also removes 1 package you installed: gimp:amd64.",
+                "Also removes 1 automatically installed package: gimp-data-extras."
+            ]
+        );
+        assert_eq!(
+            apt_dependents("htop", &names(&["htop"]), &manual),
+            Dependents::default()
+        );
+    }
+
+    #[test]
+    fn finds_flatpak_runtime_users_and_snap_content_plugs() {
+        let list = "org.gimp.GIMP\torg.gnome.Platform/x86_64/46\n\
+            org.gnome.Platform\t\n\
+            org.example.Plugin\torg.gimp.GIMP/x86_64/stable\n";
+        assert_eq!(runtime_users(list, "org.gimp.GIMP"), ["org.example.Plugin"]);
+        assert_eq!(runtime_users(list, "org.gnome.Platform"), ["org.gimp.GIMP"]);
+        assert!(runtime_users(list, "org.mozilla.firefox").is_empty());
+
+        let connections = "Interface                 Plug                          Slot                          Notes\n\
+            content[gnome-42-2204]    firefox:gnome-42-2204         gnome-42-2204:gnome-42-2204   -\n\
+            content[gnome-42-2204]    thunderbird:gnome-42-2204     gnome-42-2204:gnome-42-2204   -\n\
+            content                   firefox:hunspell-dictionaries gnome-42-2204:hunspell        -\n\
+            desktop                   gnome-42-2204:desktop         :desktop                      -\n";
+        assert_eq!(
+            content_users(connections, "gnome-42-2204"),
+            ["firefox", "thunderbird"]
+        );
+        assert!(content_users(connections, "firefox").is_empty());
+    }
+
+    #[tokio::test]
+    async fn simulates_removals_against_the_recorded_system() {
+        let runner = crate::system::fixture_runner("ubuntu-24.04");
+        let portal = InstalledPackage::new(PackageSource::Apt, "xdg-desktop-portal-custom");
+        let verdict = check(runner.as_ref(), &portal).await;
+        assert!(verdict.blocked.unwrap().contains("'ubuntu-desktop'"));
+        // Nothing scripted: the static rules decide alone.
+        let htop = InstalledPackage::new(PackageSource::Apt, "htop");
+        assert_eq!(check(runner.as_ref(), &htop).await, Dependents::default());
+    }
+}
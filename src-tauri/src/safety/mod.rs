@@ -3,8 +3,14 @@
 //! No destructive operation may run before passing through here. The deny-list
 //! is enforced in the backend, independent of any frontend state, so a crafted
 //! `invoke` call can never remove a system-critical package or a protected path.
+//! Removals also get a reverse-dependency check ([`dependents`]) at preview.
 
-use crate::package::PackageSource;
+pub mod dependents;
+pub mod profiles;
+
+use crate::operations::Operation;
+use crate::package::{BaseSystem, InstalledPackage, PackageSource};
+use crate::system::Dirs;
 
 /// Reason a package/path is protected, surfaced to the UI.
 #[derive(Debug, Clone, serde::Serialize)]
@@ -30,18 +36,63 @@ impl Protection {
 
 /// Decide whether a package may be removed by Scope.
 ///
-/// Essential/system-critical packages are always blocked. The check is purely
-/// backend-side and uses the canonical package id plus the source.
-pub fn check_package(source: PackageSource, package_id: &str) -> Protection {
+/// Essential/system-critical packages are always blocked, and so are the
+/// packages the user protected in `config.toml`, on top of them. The check is
+/// purely backend-side and uses the canonical package id plus the source.
+pub fn check_package(source: PackageSource, package_id: &str, dirs: &Dirs) -> Protection {
+    let builtin = check_builtin(source, package_id, dirs);
+    if !builtin.protected && crate::config::current().is_protected(source, package_id) {
+        return Protection::denied(format!(
+            "'{package_id}' is protected in your Scope configuration."
+        ));
+    }
+    builtin
+}
+
+/// [`check_package`] for a scanned package, plus the rules that need its
+/// metadata: APT packages that dpkg marks as part of the base system
+/// (`Essential`, `Protected`, `Priority: required/important`) cannot be
+/// removed. Updating them stays allowed.
+pub fn check_installed(pkg: &InstalledPackage, operation: Operation, dirs: &Dirs) -> Protection {
+    let protection = check_package(pkg.source, &pkg.package_id, dirs);
+    if protection.protected || !matches!(operation, Operation::Uninstall | Operation::Purge) {
+        return protection;
+    }
+    check_base_system(&pkg.package_id, pkg.base_system)
+}
+
+/// The removal rule of [`check_installed`] for an APT package whose dpkg
+/// metadata marks it `mark`. `scope-helper` applies it again as root.
+pub fn check_base_system(package_id: &str, mark: Option<BaseSystem>) -> Protection {
+    match mark {
+        Some(mark) => Protection::denied(format!(
+            "'{package_id}' is part of the base system (its dpkg metadata says `{}`) and \
+             cannot be removed through Scope.",
+            mark.field()
+        )),
+        None => Protection::allowed(),
+    }
+}
+
+fn check_builtin(source: PackageSource, package_id: &str, dirs: &Dirs) -> Protection {
     match source {
         PackageSource::Apt => check_apt(package_id),
         PackageSource::Snap => check_snap(package_id),
         PackageSource::Flatpak => check_flatpak(package_id),
-        PackageSource::AppImage => check_appimage(package_id),
+        PackageSource::AppImage => check_appimage(package_id, dirs),
+        PackageSource::Container => check_container_export(package_id, dirs),
+        PackageSource::Steam => check_steam(package_id),
+        PackageSource::Extension => check_extension_dir(package_id, dirs),
+        PackageSource::Plugin => check_plugin(package_id, dirs),
     }
 }
 
 fn check_apt(name: &str) -> Protection {
+    check_apt_with(name, profiles::active())
+}
+
+/// [`check_apt`] with the profiles of a given system.
+fn check_apt_with(name: &str, profiles: &[&'static profiles::Profile]) -> Protection {
     let lower = name.to_lowercase();
     let stripped = lower
         .strip_suffix(":amd64")
@@ -49,9 +100,13 @@ fn check_apt(name: &str) -> Protection {
         .unwrap_or(&lower);
     let n = stripped;
 
-    // Core system packages whose removal would break the OS or the GUI session.
+    // Core system packages whose removal would break the OS or the GUI
+    // session, protected whatever system is detected: `scope-helper` runs
+    // without the session's environment. The matching [`profiles`] add the
+    // packages of other distros, desktops and display managers on top.
     const CRITICAL: &[&str] = &[
         "ubuntu-desktop",
+        "ubuntu-desktop-minimal",
         "ubuntu-standard",
         "ubuntu-minimal",
         "ubuntu-release-upgrader-core",
@@ -105,6 +160,12 @@ fn check_apt(name: &str) -> Protection {
             "'{n}' is a system-critical package and cannot be removed through Scope."
         ));
     }
+    if let Some(profile) = profiles::protecting(profiles, n) {
+        return Protection::denied(format!(
+            "'{n}' is part of {} and cannot be removed through Scope.",
+            profile.label
+        ));
+    }
     // Kernel images and headers (any version).
     if n.starts_with("linux-image-")
         || n.starts_with("linux-headers-")
@@ -153,12 +214,87 @@ fn check_flatpak(_app_id: &str) -> Protection {
     Protection::allowed()
 }
 
-fn check_appimage(path: &str) -> Protection {
-    check_path(path)
+fn check_appimage(path: &str, dirs: &Dirs) -> Protection {
+    check_path(path, dirs)
+}
+
+fn check_steam(package_id: &str) -> Protection {
+    if crate::scanner::steam::is_custom_tool(package_id) {
+        // Custom compatibility tools are only ever removed manually.
+        return Protection::allowed();
+    }
+    if !package_id.chars().all(|c| c.is_ascii_digit()) || package_id.is_empty() {
+        return Protection::denied("Not a valid Steam app id.");
+    }
+    if crate::scanner::steam::RUNTIME_APP_IDS.contains(&package_id) {
+        return Protection::denied(format!(
+            "Steam app {package_id} is a runtime other games depend on; Steam manages it."
+        ));
+    }
+    Protection::allowed()
+}
+
+/// Plugins decide what they protect (via `preview`); the backend only checks
+/// that the id is well-formed and names an installed plugin.
+fn check_plugin(package_id: &str, dirs: &Dirs) -> Protection {
+    let Some((name, _)) = crate::scanner::plugin::split_package_id(package_id) else {
+        return Protection::denied("Not a valid plugin package id.");
+    };
+    if crate::scanner::plugin::find(name, dirs).is_none() {
+        return Protection::denied(format!("Plugin '{name}' is not installed."));
+    }
+    Protection::allowed()
+}
+
+/// Container exports are identified by their host-side file. Only real
+/// distrobox/toolbox exports inside the user's export directories qualify, so
+/// unexporting can never delete an unrelated file.
+fn check_container_export(path: &str, dirs: &Dirs) -> Protection {
+    let Ok(abs) = std::path::Path::new(path).canonicalize() else {
+        return Protection::denied("Export file does not resolve to a real file.");
+    };
+    let inside_allowed = crate::scanner::container::export_dirs(dirs)
+        .iter()
+        .filter_map(|d| d.canonicalize().ok())
+        .any(|root| abs.parent() == Some(root.as_path()));
+    if !inside_allowed {
+        return Protection::denied("File is outside the container export directories.");
+    }
+    if crate::scanner::container::load_export(&abs).is_none() {
+        return Protection::denied("File is not a Distrobox/Toolbox export.");
+    }
+    Protection::allowed()
+}
+
+/// Guard extension directories before they are deleted recursively: the
+/// directory must sit directly inside a known per-user extension root and
+/// carry that host's manifest.
+pub fn check_extension_dir(path: &str, dirs: &Dirs) -> Protection {
+    use crate::scanner::extensions::{extension_roots, ExtensionHost};
+
+    let Ok(abs) = std::path::Path::new(path).canonicalize() else {
+        return Protection::denied("Extension directory does not resolve to a real path.");
+    };
+    if !abs.is_dir() {
+        return Protection::denied("Extension path is not a directory.");
+    }
+    let host = extension_roots(dirs).into_iter().find_map(|(host, root)| {
+        let root = root.canonicalize().ok()?;
+        (abs.parent() == Some(root.as_path())).then_some(host)
+    });
+    let manifest = match host {
+        Some(ExtensionHost::VsCode) => "package.json",
+        Some(ExtensionHost::GnomeShell) => "metadata.json",
+        None => return Protection::denied("Directory is outside the per-user extension folders."),
+    };
+    if !abs.join(manifest).is_file() {
+        return Protection::denied(format!("Directory has no {manifest}; not an extension."));
+    }
+    Protection::allowed()
 }
 
 /// Guard arbitrary filesystem paths used by AppImage removal.
-pub fn check_path(path: &str) -> Protection {
+pub fn check_path(path: &str, dirs: &Dirs) -> Protection {
     let cleaned = std::path::Path::new(path);
     let Ok(abs) = cleaned.canonicalize() else {
         return Protection::denied("Path does not resolve to a real file.");
@@ -166,15 +302,7 @@ pub fn check_path(path: &str) -> Protection {
     let s = abs.to_string_lossy();
 
     // Never allow operations outside expected AppImage locations or on system dirs.
-    let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
-    let mut allowed_roots: Vec<std::path::PathBuf> = vec![std::path::PathBuf::from("/opt")];
-    if let Some(h) = &home {
-        allowed_roots.push(h.join("Applications"));
-        allowed_roots.push(h.join("apps"));
-        allowed_roots.push(h.join("AppImages"));
-        allowed_roots.push(h.join("Downloads"));
-        allowed_roots.push(h.join(".local/bin"));
-    }
+    let allowed_roots = crate::scanner::appimage::removable_dirs(dirs);
     let inside_allowed = allowed_roots.iter().any(|root| {
         s.starts_with(&format!("{}/", root.display())) || s == root.display().to_string()
     });
@@ -197,39 +325,117 @@ mod tests {
 
     #[test]
     fn blocks_critical_apt() {
-        assert!(check_package(PackageSource::Apt, "ubuntu-desktop").protected);
-        assert!(check_package(PackageSource::Apt, "systemd").protected);
-        assert!(check_package(PackageSource::Apt, "linux-image-6.8.0-45-generic").protected);
-        assert!(check_package(PackageSource::Apt, "apt").protected);
-        assert!(check_package(PackageSource::Apt, "pkexec").protected);
+        assert!(check_package(PackageSource::Apt, "ubuntu-desktop", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Apt, "systemd", &Dirs::Host).protected);
+        assert!(
+            check_package(
+                PackageSource::Apt,
+                "linux-image-6.8.0-45-generic",
+                &Dirs::Host
+            )
+            .protected
+        );
+        assert!(check_package(PackageSource::Apt, "apt", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Apt, "pkexec", &Dirs::Host).protected);
+    }
+
+    #[test]
+    fn profiles_add_to_the_core_names_on_any_system() {
+        let kde = profiles::System::parse("ID=fedora\n", "KDE", Some("sddm")).profiles();
+        for name in [
+            "gnome-shell",
+            "gdm3",
+            "ubuntu-desktop",
+            "ubuntu-desktop-minimal",
+        ] {
+            assert!(check_apt_with(name, &kde).protected, "{name}");
+            assert!(check_apt_with(name, &[]).protected, "{name}");
+        }
+        let reason = check_apt_with("plasma-desktop", &kde).reason.unwrap();
+        assert!(reason.contains("the KDE Plasma session"), "{reason}");
+        let gnome = profiles::System::parse("ID=ubuntu\n", "ubuntu:GNOME", Some("gdm3")).profiles();
+        assert!(!check_apt_with("plasma-desktop", &gnome).protected);
+        assert!(check_apt_with("mutter", &gnome).protected);
     }
 
     #[test]
     fn blocks_libraries_but_allows_libreoffice() {
-        assert!(check_package(PackageSource::Apt, "libssl3").protected);
-        assert!(check_package(PackageSource::Apt, "libgtk-3-0").protected);
-        assert!(!check_package(PackageSource::Apt, "libreoffice-writer").protected);
+        assert!(check_package(PackageSource::Apt, "libssl3", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Apt, "libgtk-3-0", &Dirs::Host).protected);
+        assert!(!check_package(PackageSource::Apt, "libreoffice-writer", &Dirs::Host).protected);
     }
 
     #[test]
     fn allows_regular_apps() {
-        assert!(!check_package(PackageSource::Apt, "firefox").protected);
-        assert!(!check_package(PackageSource::Apt, "vlc").protected);
-        assert!(!check_package(PackageSource::Snap, "firefox").protected);
-        assert!(!check_package(PackageSource::Flatpak, "org.mozilla.firefox").protected);
+        assert!(!check_package(PackageSource::Apt, "firefox", &Dirs::Host).protected);
+        assert!(!check_package(PackageSource::Apt, "vlc", &Dirs::Host).protected);
+        assert!(!check_package(PackageSource::Snap, "firefox", &Dirs::Host).protected);
+        assert!(
+            !check_package(PackageSource::Flatpak, "org.mozilla.firefox", &Dirs::Host).protected
+        );
+    }
+
+    #[test]
+    fn blocks_removing_base_system_packages_by_their_dpkg_fields() {
+        use crate::package::BaseSystem;
+
+        let mut pkg = InstalledPackage::new(PackageSource::Apt, "vim-tiny");
+        assert!(!check_installed(&pkg, Operation::Uninstall, &Dirs::Host).protected);
+        pkg.base_system = BaseSystem::from_fields("", "", "important");
+        let reason = check_installed(&pkg, Operation::Purge, &Dirs::Host)
+            .reason
+            .unwrap();
+        assert!(reason.contains("`Priority: important`"), "{reason}");
+        assert!(!check_installed(&pkg, Operation::Update, &Dirs::Host).protected);
+
+        assert_eq!(
+            BaseSystem::from_fields("yes", "", "required"),
+            Some(BaseSystem::Essential)
+        );
+        assert_eq!(
+            BaseSystem::from_fields("no", "yes", "optional"),
+            Some(BaseSystem::Protected)
+        );
+        assert_eq!(BaseSystem::from_fields("", "", "optional"), None);
     }
 
     #[test]
     fn blocks_snap_runtimes() {
-        assert!(check_package(PackageSource::Snap, "core20").protected);
-        assert!(check_package(PackageSource::Snap, "gtk-common-themes").protected);
-        assert!(check_package(PackageSource::Snap, "snapd").protected);
+        assert!(check_package(PackageSource::Snap, "core20", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Snap, "gtk-common-themes", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Snap, "snapd", &Dirs::Host).protected);
     }
 
     #[test]
     fn blocks_appimage_outside_allowed_dirs() {
-        assert!(check_path("/etc/passwd").protected);
-        assert!(check_path("/usr/bin/bash").protected);
-        assert!(check_path("/nonexistent.AppImage").protected);
+        assert!(check_path("/etc/passwd", &Dirs::Host).protected);
+        assert!(check_path("/usr/bin/bash", &Dirs::Host).protected);
+        assert!(check_path("/nonexistent.AppImage", &Dirs::Host).protected);
+    }
+
+    #[test]
+    fn blocks_steam_runtimes_and_bad_ids() {
+        assert!(check_package(PackageSource::Steam, "1628350", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Steam, "620; rm", &Dirs::Host).protected);
+        assert!(!check_package(PackageSource::Steam, "620", &Dirs::Host).protected);
+    }
+
+    #[test]
+    fn blocks_extension_removal_outside_roots() {
+        assert!(check_package(PackageSource::Extension, "/usr", &Dirs::Host).protected);
+        assert!(check_package(PackageSource::Extension, "/nonexistent/ext", &Dirs::Host).protected);
+    }
+
+    #[test]
+    fn blocks_container_removal_of_non_exports() {
+        assert!(check_package(PackageSource::Container, "/etc/passwd", &Dirs::Host).protected);
+        assert!(
+            check_package(
+                PackageSource::Container,
+                "/nonexistent.desktop",
+                &Dirs::Host
+            )
+            .protected
+        );
     }
 }
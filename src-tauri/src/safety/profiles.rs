@@ -0,0 +1,315 @@
+//! Protection profiles for the running distribution, desktop session and
+//! display manager.
+//!
+//! Strategy: the packages that hold a desktop together differ per system:
+//! `ubuntu-desktop` and `gdm3` on Ubuntu, `plasma-desktop` and `sddm` under
+//! KDE, `mint-meta-core` and `cinnamon` on Mint. Instead of protecting every
+//! desktop's packages everywhere (which would stop a GNOME user from removing
+//! a KDE they tried once), [`active`] detects the system once per process and
+//! picks the matching profiles:
+//!
+//! - the distribution from `ID` and `ID_LIKE` in `/etc/os-release`, so Mint
+//!   and Pop!_OS also get the Ubuntu and Debian profiles;
+//! - the session from `XDG_CURRENT_DESKTOP` (`ubuntu:GNOME`, `KDE`,
+//!   `X-Cinnamon`, `XFCE`, `COSMIC`, ...);
+//! - the display manager from the `display-manager.service` alias systemd
+//!   starts, or Debian's `/etc/X11/default-display-manager`.
+//!
+//! Without a session (SSH, or `scope-helper` under `pkexec`, which clears
+//! the environment) only the distribution and display manager apply. The
+//! profiles only ever add to the built-in deny-list in [`super`], which
+//! keeps the Ubuntu, GNOME and GDM core packages protected on every system.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// What a profile is matched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Match {
+    /// An `ID`/`ID_LIKE` value in os-release.
+    Distro(&'static str),
+    /// A (case-insensitive) `XDG_CURRENT_DESKTOP` entry.
+    Desktop(&'static str),
+    /// The display manager's service name.
+    DisplayManager(&'static str),
+}
+
+use Match::{Desktop, DisplayManager, Distro};
+
+/// Packages protected on systems a profile matches.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// Shown in protection reasons, e.g. "the KDE Plasma session".
+    pub label: &'static str,
+    matches: &'static [Match],
+    pub packages: &'static [&'static str],
+}
+
+static PROFILES: &[Profile] = &[
+    Profile {
+        label: "Ubuntu",
+        matches: &[Distro("ubuntu")],
+        packages: &[
+            "ubuntu-desktop",
+            "ubuntu-desktop-minimal",
+            "ubuntu-standard",
+            "ubuntu-minimal",
+            "ubuntu-release-upgrader-core",
+        ],
+    },
+    Profile {
+        label: "Debian",
+        matches: &[Distro("debian")],
+        packages: &["debian-archive-keyring", "task-desktop"],
+    },
+    Profile {
+        label: "Linux Mint",
+        matches: &[Distro("linuxmint")],
+        packages: &["mint-meta-core", "mintsystem", "mint-common", "mintupdate"],
+    },
+    Profile {
+        label: "Pop!_OS",
+        matches: &[Distro("pop")],
+        packages: &["pop-desktop", "pop-default-settings", "system76-power"],
+    },
+    Profile {
+        label: "the GNOME session",
+        matches: &[Desktop("gnome")],
+        packages: &[
+            "gnome-shell",
+            "gnome-session",
+            "gnome-session-bin",
+            "gnome-control-center",
+            "gnome-settings-daemon",
+            "mutter",
+            "ubuntu-session",
+            "task-gnome-desktop",
+            "xdg-desktop-portal-gnome",
+        ],
+    },
+    Profile {
+        label: "the KDE Plasma session",
+        matches: &[Desktop("kde")],
+        packages: &[
+            "plasma-desktop",
+            "plasma-workspace",
+            "kde-plasma-desktop",
+            "kwin-x11",
+            "kwin-wayland",
+            "systemsettings",
+            "kubuntu-desktop",
+            "task-kde-desktop",
+            "xdg-desktop-portal-kde",
+        ],
+    },
+    Profile {
+        label: "the Cinnamon session",
+        matches: &[Desktop("x-cinnamon"), Desktop("cinnamon")],
+        packages: &[
+            "cinnamon",
+            "cinnamon-core",
+            "cinnamon-session",
+            "cinnamon-desktop-environment",
+            "cinnamon-control-center",
+            "cinnamon-settings-daemon",
+            "muffin",
+            "nemo",
+            "mint-meta-cinnamon",
+            "task-cinnamon-desktop",
+        ],
+    },
+    Profile {
+        label: "the Xfce session",
+        matches: &[Desktop("xfce")],
+        packages: &[
+            "xfce4",
+            "xfce4-session",
+            "xfce4-panel",
+            "xfce4-settings",
+            "xfwm4",
+            "xfdesktop4",
+            "thunar",
+            "xubuntu-desktop",
+            "mint-meta-xfce",
+            "task-xfce-desktop",
+        ],
+    },
+    Profile {
+        label: "the COSMIC session",
+        matches: &[Desktop("cosmic")],
+        packages: &[
+            "cosmic-session",
+            "cosmic-comp",
+            "cosmic-panel",
+            "cosmic-settings",
+            "cosmic-settings-daemon",
+            "cosmic-launcher",
+            "cosmic-applets",
+            "cosmic-bg",
+            "cosmic-osd",
+            "cosmic-workspaces",
+            "xdg-desktop-portal-cosmic",
+        ],
+    },
+    Profile {
+        label: "the GDM display manager",
+        matches: &[DisplayManager("gdm3"), DisplayManager("gdm")],
+        packages: &["gdm3", "gdm"],
+    },
+    Profile {
+        label: "the SDDM display manager",
+        matches: &[DisplayManager("sddm")],
+        packages: &["sddm"],
+    },
+    Profile {
+        label: "the LightDM display manager",
+        matches: &[DisplayManager("lightdm")],
+        packages: &[
+            "lightdm",
+            "lightdm-gtk-greeter",
+            "slick-greeter",
+            "lightdm-settings",
+        ],
+    },
+    Profile {
+        label: "the COSMIC greeter",
+        matches: &[DisplayManager("cosmic-greeter")],
+        packages: &["cosmic-greeter"],
+    },
+];
+
+/// The system [`active`] profiles are picked for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct System {
+    /// `ID` followed by the `ID_LIKE` entries, lowercase.
+    distros: Vec<String>,
+    /// `XDG_CURRENT_DESKTOP` entries, lowercase.
+    desktops: Vec<String>,
+    /// Service name of the display manager, e.g. `gdm3`.
+    display_manager: Option<String>,
+}
+
+impl System {
+    fn detect() -> Self {
+        let os_release = ["/etc/os-release", "/usr/lib/os-release"]
+            .iter()
+            .find_map(|p| std::fs::read_to_string(p).ok())
+            .unwrap_or_default();
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        Self::parse(&os_release, &desktop, display_manager().as_deref())
+    }
+
+    pub(super) fn parse(os_release: &str, desktop: &str, display_manager: Option<&str>) -> Self {
+        let mut distros = Vec::new();
+        for line in os_release.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key == "ID" || key == "ID_LIKE" {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                distros.extend(value.split_whitespace().map(str::to_lowercase));
+            }
+        }
+        Self {
+            distros,
+            desktops: desktop
+                .split(':')
+                .filter(|d| !d.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+            display_manager: display_manager.map(str::to_string),
+        }
+    }
+
+    fn matches(&self, m: Match) -> bool {
+        match m {
+            Distro(id) => self.distros.iter().any(|d| d == id),
+            Desktop(name) => self.desktops.iter().any(|d| d == name),
+            DisplayManager(name) => self.display_manager.as_deref() == Some(name),
+        }
+    }
+
+    /// Profiles matching this system.
+    pub(super) fn profiles(&self) -> Vec<&'static Profile> {
+        PROFILES
+            .iter()
+            .filter(|p| p.matches.iter().any(|m| self.matches(*m)))
+            .collect()
+    }
+}
+
+/// The display manager's service name: where systemd's
+/// `display-manager.service` alias points, else the binary Debian's
+/// `/etc/X11/default-display-manager` names.
+fn display_manager() -> Option<String> {
+    let file_stem = |p: &Path| p.file_stem().and_then(|s| s.to_str()).map(str::to_string);
+    if let Ok(target) = std::fs::read_link("/etc/systemd/system/display-manager.service") {
+        return file_stem(&target);
+    }
+    let binary = std::fs::read_to_string("/etc/X11/default-display-manager").ok()?;
+    file_stem(Path::new(binary.trim()))
+}
+
+/// The profiles for the system Scope runs on, detected once.
+pub fn active() -> &'static [&'static Profile] {
+    static ACTIVE: OnceLock<Vec<&'static Profile>> = OnceLock::new();
+    ACTIVE.get_or_init(|| System::detect().profiles())
+}
+
+/// The profile among `profiles` protecting the APT package `name`, if any.
+pub fn protecting(profiles: &[&'static Profile], name: &str) -> Option<&'static Profile> {
+    profiles
+        .iter()
+        .copied()
+        .find(|p| p.packages.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(system: &System) -> Vec<&'static str> {
+        system.profiles().iter().map(|p| p.label).collect()
+    }
+
+    #[test]
+    fn picks_profiles_for_distro_session_and_display_manager() {
+        let mint = System::parse(
+            "NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\n",
+            "X-Cinnamon",
+            Some("lightdm"),
+        );
+        assert_eq!(
+            labels(&mint),
+            [
+                "Ubuntu",
+                "Debian",
+                "Linux Mint",
+                "the Cinnamon session",
+                "the LightDM display manager"
+            ]
+        );
+
+        let kde = System::parse("ID=debian\n", "KDE", Some("sddm"));
+        assert_eq!(
+            labels(&kde),
+            [
+                "Debian",
+                "the KDE Plasma session",
+                "the SDDM display manager"
+            ]
+        );
+
+        let pop = System::parse(
+            "ID=pop\nID_LIKE=\"ubuntu debian\"\n",
+            "COSMIC",
+            Some("cosmic-greeter"),
+        );
+        assert!(labels(&pop).contains(&"the COSMIC session"));
+        assert!(labels(&pop).contains(&"the COSMIC greeter"));
+
+        // Over SSH: no session, so its packages stay removable.
+        let headless = System::parse("ID=debian\n", "", None);
+        assert_eq!(labels(&headless), ["Debian"]);
+    }
+}
@@ -0,0 +1,504 @@
+//! The latest full scan, shared by the Tauri commands, the D-Bus service and
+//! the startup refresh, and persisted between launches.
+//!
+//! Strategy: every completed scan is written to
+//! `$XDG_CACHE_HOME/scope/scan.json` next to a schema version. On startup the
+//! file is loaded before the window opens and marked `stale`, so the list shows
+//! instantly while a background scan refreshes it; the difference between the
+//! two is reported as a [`ScanDiff`]. A file with another schema version, or
+//! one that does not parse, is ignored and simply overwritten by the next scan.
+//!
+//! Every change to the cached scan, full ([`ScanCache::refresh`]), limited
+//! to some sources ([`ScanCache::rescan`]) or an update check's annotations
+//! ([`ScanCache::annotate_updates`]), is published to
+//! [`ScanCache::subscribe`]rs as a diff. Scans do not look for updates, so a
+//! rescan keeps each package's update annotation while its version is the
+//! one that was checked.
+//! Dry-run mode neither reads nor writes the file, so recorded fixtures never
+//! mix with the real system's packages.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::broadcast;
+
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::{scan_all, scan_sources, sort_packages, ScanAvailability};
+use crate::system::Runner;
+
+/// Version of the on-disk format. Bump it whenever [`CachedScan`] or
+/// [`InstalledPackage`] change in a way older files cannot be read as.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Cache of the latest full scan, shared across commands and the D-Bus service.
+#[derive(Clone)]
+pub struct ScanCache {
+    inner: Arc<tokio::sync::Mutex<Option<CachedScan>>>,
+    /// Where scans are persisted. `None` keeps the cache in memory only.
+    path: Option<PathBuf>,
+    diffs: broadcast::Sender<ScanDiff>,
+}
+
+/// Diffs a slow subscriber may fall behind by before it misses some.
+const DIFF_BACKLOG: usize = 16;
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        Self::with_scan(None, None)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CachedScan {
+    pub packages: Vec<InstalledPackage>,
+    pub availability: ScanAvailability,
+    pub scanned_at_ms: u64,
+    /// True for a scan loaded from disk that has not been refreshed yet.
+    #[serde(default)]
+    pub stale: bool,
+    /// When updates were last checked; `None` until the first check.
+    #[serde(default)]
+    pub updates_checked_at_ms: Option<u64>,
+}
+
+/// On-disk envelope of a persisted scan.
+#[derive(Serialize, Deserialize)]
+struct PersistedScan {
+    schema: u32,
+    scan: CachedScan,
+}
+
+/// What changed between two scans, by package key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanDiff {
+    pub added: Vec<InstalledPackage>,
+    /// Keys of packages that are gone.
+    pub removed: Vec<String>,
+    /// New state of packages whose version, update, hold or metadata changed.
+    pub changed: Vec<InstalledPackage>,
+    pub scanned_at_ms: u64,
+}
+
+impl ScanDiff {
+    /// Compare `old` against `new`. Order within each list follows `new`
+    /// (removed keys follow `old`).
+    pub fn between(old: &[InstalledPackage], new: &CachedScan) -> Self {
+        let before: HashMap<&str, &InstalledPackage> =
+            old.iter().map(|p| (p.key.as_str(), p)).collect();
+        let mut diff = ScanDiff {
+            scanned_at_ms: new.scanned_at_ms,
+            ..Default::default()
+        };
+        for pkg in &new.packages {
+            match before.get(pkg.key.as_str()) {
+                None => diff.added.push(pkg.clone()),
+                Some(prev) if *prev != pkg => diff.changed.push(pkg.clone()),
+                Some(_) => {}
+            }
+        }
+        let after: std::collections::HashSet<&str> =
+            new.packages.iter().map(|p| p.key.as_str()).collect();
+        diff.removed = old
+            .iter()
+            .filter(|p| !after.contains(p.key.as_str()))
+            .map(|p| p.key.clone())
+            .collect();
+        diff
+    }
+}
+
+impl ScanCache {
+    /// Cache persisted at [`default_path`], pre-filled (stale) from the last
+    /// run's file when there is a readable one. In dry-run mode the cache is
+    /// in memory only.
+    pub fn load(runner: &Runner) -> Self {
+        if runner.is_dry_run() {
+            return Self::default();
+        }
+        match default_path() {
+            Some(path) => Self::load_from(path),
+            None => Self::default(),
+        }
+    }
+
+    /// Cache persisted at `path`, pre-filled (stale) from it when readable.
+    pub fn load_from(path: PathBuf) -> Self {
+        let scan = read(&path).map(|mut scan| {
+            scan.stale = true;
+            scan
+        });
+        Self::with_scan(scan, Some(path))
+    }
+
+    fn with_scan(scan: Option<CachedScan>, path: Option<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(tokio::sync::Mutex::new(scan)),
+            path,
+            diffs: broadcast::channel(DIFF_BACKLOG).0,
+        }
+    }
+
+    /// Receive the diff of every later change to the cached scan.
+    pub fn subscribe(&self) -> broadcast::Receiver<ScanDiff> {
+        self.diffs.subscribe()
+    }
+
+    /// Look up a single package by its backend key in the cached scan.
+    pub async fn find(&self, key: &str) -> Option<InstalledPackage> {
+        let guard = self.inner.lock().await;
+        let cached = guard.as_ref()?;
+        cached.packages.iter().find(|p| p.key == key).cloned()
+    }
+
+    /// The cached scan, fresh or stale.
+    pub async fn get(&self) -> Option<CachedScan> {
+        self.inner.lock().await.clone()
+    }
+
+    /// Run a full scan, replace the cached one with it, and persist it.
+    pub async fn refresh(&self, runner: &Runner) -> CachedScan {
+        let (packages, availability) = scan_all(runner).await;
+        self.replace(|previous| rescanned(previous, packages, availability))
+            .await
+    }
+
+    /// Rescan only `sources` and swap their packages and availability into
+    /// the cached scan, leaving the other sources' untouched. Before the first
+    /// scan this is a full [`refresh`](Self::refresh).
+    pub async fn rescan(&self, runner: &Runner, sources: &[PackageSource]) -> CachedScan {
+        if self.inner.lock().await.is_none() {
+            return self.refresh(runner).await;
+        }
+        let (fresh, fresh_availability) = scan_sources(runner, sources).await;
+        self.replace(|previous| {
+            let mut availability = previous.map(|p| p.availability.clone()).unwrap_or_default();
+            for source in sources {
+                availability.update_from(&fresh_availability, *source);
+            }
+            let mut packages: Vec<InstalledPackage> = previous
+                .map(|p| p.packages.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter(|p| !sources.contains(&p.source))
+                .cloned()
+                .chain(fresh)
+                .collect();
+            sort_packages(&mut packages);
+            rescanned(previous, packages, availability)
+        })
+        .await
+    }
+
+    /// Take the update annotations of the packages an update check ran on
+    /// and, when it was `complete`, i.e. no source failed, record the check's
+    /// time. Packages rescanned to another version since the check began
+    /// keep their own. `None` before the first scan.
+    pub async fn annotate_updates(
+        &self,
+        checked: &[InstalledPackage],
+        complete: bool,
+    ) -> Option<CachedScan> {
+        if self.inner.lock().await.is_none() {
+            return None;
+        }
+        let checked: HashMap<&str, &InstalledPackage> =
+            checked.iter().map(|p| (p.key.as_str(), p)).collect();
+        let scan = self
+            .replace(|previous| {
+                let mut scan = previous.cloned().expect("checked above");
+                for pkg in scan.packages.iter_mut() {
+                    if let Some(c) = checked.get(pkg.key.as_str()) {
+                        if c.version == pkg.version {
+                            pkg.copy_update_from(c);
+                        }
+                    }
+                }
+                if complete {
+                    scan.updates_checked_at_ms = Some(now_ms());
+                }
+                scan
+            })
+            .await;
+        Some(scan)
+    }
+
+    /// Resolve the icons of the cached packages again after
+    /// [`crate::icons::reload`] saw the theme change, without rescanning, and
+    /// publish the ones that changed. `None` before the first scan.
+    pub async fn refresh_icons(&self) -> Option<CachedScan> {
+        let packages = self.inner.lock().await.as_ref()?.packages.clone();
+        // Icon lookups read the disk, so they run off the async runtime.
+        let icons: HashMap<String, (Option<String>, Option<String>)> =
+            tokio::task::spawn_blocking(move || {
+                packages
+                    .into_iter()
+                    .filter_map(|pkg| {
+                        let fresh = crate::icons::reresolve(&pkg)?;
+                        (fresh != pkg.icon).then_some((pkg.key, (pkg.icon, fresh)))
+                    })
+                    .collect()
+            })
+            .await
+            .unwrap_or_default();
+        let scan = self
+            .replace(|previous| {
+                let mut scan = previous.cloned().expect("checked above");
+                for pkg in scan.packages.iter_mut() {
+                    // Packages rescanned meanwhile already have current icons.
+                    if let Some((old, fresh)) = icons.get(&pkg.key) {
+                        if pkg.icon == *old {
+                            pkg.icon = fresh.clone();
+                        }
+                    }
+                }
+                scan
+            })
+            .await;
+        Some(scan)
+    }
+
+    /// Replace the cached scan with the one `update` builds from it, persist
+    /// it, and publish the diff. The lock is held throughout so concurrent
+    /// rescans are persisted and published in the order they were applied.
+    async fn replace(&self, update: impl FnOnce(Option<&CachedScan>) -> CachedScan) -> CachedScan {
+        let mut guard = self.inner.lock().await;
+        let cached = update(guard.as_ref());
+        let previous = guard.as_ref().map(|p| p.packages.as_slice());
+        let diff = ScanDiff::between(previous.unwrap_or_default(), &cached);
+        *guard = Some(cached.clone());
+
+        if let Some(path) = &self.path {
+            // A cache that cannot be written only costs the next startup.
+            if let Err(e) = write(path, &cached).await {
+                eprintln!("scope: could not persist scan cache: {e:#}");
+            }
+        }
+        // Having no subscriber yet is fine.
+        let _ = self.diffs.send(diff);
+        cached
+    }
+
+    /// Filter the cached scan by source id, app kind and a case-insensitive
+    /// query. Unknown source/kind ids do not filter. Empty before the first scan.
+    pub async fn search(
+        &self,
+        query: Option<&str>,
+        source: Option<&str>,
+        app_kind: Option<&str>,
+    ) -> Vec<InstalledPackage> {
+        let guard = self.inner.lock().await;
+        let Some(cached) = guard.as_ref() else {
+            return Vec::new();
+        };
+
+        let q = query.map(|s| s.trim().to_lowercase()).unwrap_or_default();
+        let source_filter = source.and_then(PackageSource::from_id);
+        let kind_filter = app_kind.and_then(AppKind::from_id);
+
+        cached
+            .packages
+            .iter()
+            .filter(|p| match (source_filter, kind_filter) {
+                (Some(s), Some(k)) => p.source == s && p.app_kind == k,
+                (Some(s), None) => p.source == s,
+                (None, Some(k)) => p.app_kind == k,
+                (None, None) => true,
+            })
+            .filter(|p| p.matches_query(&q))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A scan that just finished, carrying over the update annotations of
+/// `previous` for packages whose version did not change.
+fn rescanned(
+    previous: Option<&CachedScan>,
+    mut packages: Vec<InstalledPackage>,
+    availability: ScanAvailability,
+) -> CachedScan {
+    if let Some(previous) = previous {
+        let before: HashMap<&str, &InstalledPackage> = previous
+            .packages
+            .iter()
+            .map(|p| (p.key.as_str(), p))
+            .collect();
+        for pkg in packages.iter_mut() {
+            if let Some(prev) = before.get(pkg.key.as_str()) {
+                if prev.version == pkg.version {
+                    pkg.copy_update_from(prev);
+                }
+            }
+        }
+    }
+    CachedScan {
+        packages,
+        availability,
+        scanned_at_ms: now_ms(),
+        stale: false,
+        updates_checked_at_ms: previous.and_then(|p| p.updates_checked_at_ms),
+    }
+}
+
+/// `$XDG_CACHE_HOME/scope/scan.json`, or `~/.cache/scope/scan.json`.
+pub fn default_path() -> Option<PathBuf> {
+    crate::system::cache_dir().map(|dir| dir.join("scan.json"))
+}
+
+/// Read a persisted scan. `None` when missing, unreadable, or written with
+/// another schema version.
+fn read(path: &Path) -> Option<CachedScan> {
+    let bytes = std::fs::read(path).ok()?;
+    let persisted: PersistedScan = serde_json::from_slice(&bytes).ok()?;
+    if persisted.schema != SCHEMA_VERSION {
+        return None;
+    }
+    // Icon URLs from the last run are served again only if still images.
+    for icon in persisted
+        .scan
+        .packages
+        .iter()
+        .filter_map(|p| p.icon.as_deref())
+    {
+        crate::icons::readmit(icon);
+    }
+    Some(persisted.scan)
+}
+
+/// Write a scan through a temporary file so a crash never leaves a torn one.
+async fn write(path: &Path, scan: &CachedScan) -> Result<()> {
+    let dir = path.parent().context("cache path has no parent")?;
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("create {}", dir.display()))?;
+    let json = serde_json::to_vec(&PersistedScan {
+        schema: SCHEMA_VERSION,
+        scan: scan.clone(),
+    })?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, json)
+        .await
+        .with_context(|| format!("write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("replace {}", path.display()))?;
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(id: &str, version: &str) -> InstalledPackage {
+        let mut pkg = InstalledPackage::new(PackageSource::Apt, id);
+        pkg.name = id.into();
+        pkg.version = version.into();
+        pkg
+    }
+
+    fn scan(packages: Vec<InstalledPackage>) -> CachedScan {
+        CachedScan {
+            packages,
+            availability: ScanAvailability::default(),
+            scanned_at_ms: 42,
+            stale: false,
+            updates_checked_at_ms: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("scope-scan-cache-{}-{name}", std::process::id()))
+            .join("scan.json")
+    }
+
+    #[tokio::test]
+    async fn persisted_scan_loads_stale() {
+        let path = temp_path("roundtrip");
+        write(&path, &scan(vec![package("curl", "8.5")]))
+            .await
+            .unwrap();
+
+        let cache = ScanCache::load_from(path.clone());
+        let loaded = cache.get().await.expect("persisted scan");
+        assert!(loaded.stale);
+        assert_eq!(loaded.scanned_at_ms, 42);
+        assert_eq!(cache.find("apt:curl").await.unwrap().version, "8.5");
+
+        // Another schema version is ignored rather than misread.
+        let mut other: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        other["schema"] = (SCHEMA_VERSION + 1).into();
+        std::fs::write(&path, other.to_string()).unwrap();
+        assert!(ScanCache::load_from(path.clone()).get().await.is_none());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed() {
+        let old = vec![
+            package("curl", "8.5"),
+            package("vim", "9.1"),
+            package("git", "2.43"),
+        ];
+        let new = scan(vec![
+            package("curl", "8.6"),
+            package("vim", "9.1"),
+            package("htop", "3.3"),
+        ]);
+
+        let diff = ScanDiff::between(&old, &new);
+        let keys = |pkgs: &[InstalledPackage]| -> Vec<String> {
+            pkgs.iter().map(|p| p.key.clone()).collect()
+        };
+        assert_eq!(keys(&diff.added), ["apt:htop"]);
+        assert_eq!(diff.removed, ["apt:git"]);
+        assert_eq!(keys(&diff.changed), ["apt:curl"]);
+        assert_eq!(diff.scanned_at_ms, 42);
+
+        let same = ScanDiff::between(&new.packages, &new);
+        assert!(same.added.is_empty() && same.removed.is_empty() && same.changed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn theme_change_reresolves_cached_icons() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/icons");
+        let base_dirs = vec![root.join("user"), root.join("system")];
+        let _restore = crate::icons::use_theme(base_dirs.clone(), Some("Fixture".into()), 1);
+
+        let mut themed = package("editor", "1.0");
+        let path = crate::icons::resolve("editor").expect("editor in Fixture");
+        themed.icon = Some(crate::icons::icon_url(&path));
+        let mut own = package("steam-game", "1.0");
+        own.icon = Some(crate::icons::icon_url(
+            &root.join("system/Cached/32x32/apps/tiny.png"),
+        ));
+        let cache = ScanCache::with_scan(Some(scan(vec![themed, own.clone()])), None);
+        let mut diffs = cache.subscribe();
+
+        let _parent = crate::icons::use_theme(base_dirs, Some("Parent".into()), 1);
+        let refreshed = cache.refresh_icons().await.expect("cached scan");
+
+        let icon = refreshed.packages[0].icon.as_deref().unwrap();
+        assert!(
+            icon.ends_with("/system/Parent/48x48/apps/editor.png"),
+            "{icon}"
+        );
+        assert_eq!(refreshed.packages[1].icon, own.icon);
+        let diff = diffs.recv().await.unwrap();
+        let changed: Vec<&str> = diff.changed.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(changed, ["apt:editor"]);
+    }
+}
@@ -17,20 +17,17 @@ use walkdir::WalkDir;
 
 use crate::package::{AppKind, InstalledPackage, PackageSource};
 use crate::scanner::Scanner;
+use crate::system::Dirs;
 
 pub struct AppImageScanner {
     dirs: Vec<PathBuf>,
 }
 
 impl AppImageScanner {
-    pub fn new() -> Self {
-        Self { dirs: dirs() }
-    }
-}
-
-impl Default for AppImageScanner {
-    fn default() -> Self {
-        Self::new()
+    pub fn new(dirs: &Dirs) -> Self {
+        Self {
+            dirs: directories(dirs),
+        }
     }
 }
 
@@ -50,23 +47,49 @@ impl Scanner for AppImageScanner {
     }
 }
 
+/// Scanned like the others, but its files belong to the system and are never
+/// removed.
+const SYSTEM_BIN: &str = "/usr/local/bin";
+
 /// Directories scanned for `.AppImage` files. Kept explicit and tight — Scope
 /// never scans arbitrary paths from the frontend. Exposed for status reporting.
-pub fn search_directories() -> Vec<String> {
-    dirs().iter().map(|p| p.display().to_string()).collect()
+pub fn search_directories(dirs: &Dirs) -> Vec<String> {
+    directories(dirs)
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect()
 }
 
-fn dirs() -> Vec<PathBuf> {
-    let mut out = vec![PathBuf::from("/opt"), PathBuf::from("/usr/local/bin")];
-    if let Some(home) = std::env::var_os("HOME") {
-        let home = PathBuf::from(home);
+/// Directories AppImages may be removed from: the scanned ones but
+/// [`SYSTEM_BIN`]. Used by [`crate::safety::check_path`].
+pub fn removable_dirs(dirs: &Dirs) -> Vec<PathBuf> {
+    let system_bin = dirs.system(Path::new(SYSTEM_BIN));
+    let mut out = directories(dirs);
+    out.retain(|d| Some(d) != system_bin.as_ref());
+    out
+}
+
+/// The built-in directories followed by the configured `extra_dirs`.
+fn directories(dirs: &Dirs) -> Vec<PathBuf> {
+    let mut out: Vec<PathBuf> = ["/opt", SYSTEM_BIN]
+        .into_iter()
+        .filter_map(|d| dirs.system(Path::new(d)))
+        .collect();
+    if let Some(home) = dirs.home() {
         out.push(home.join("Applications"));
         out.push(home.join("apps"));
         out.push(home.join("AppImages"));
         out.push(home.join("Downloads"));
         out.push(home.join(".local/bin"));
     }
-    out.dedup();
+    for dir in &crate::config::current().appimage.extra_dirs {
+        let Some(dir) = dirs.system(dir) else {
+            continue;
+        };
+        if !out.contains(&dir) {
+            out.push(dir);
+        }
+    }
     out
 }
 
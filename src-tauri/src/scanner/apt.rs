@@ -3,7 +3,9 @@
 //! Strategy: list *manually* installed packages via `apt-mark showmanual`, then
 //! fetch rich metadata for exactly those names with one `dpkg-query` call.
 //! Reporting only manual installs keeps the unified list focused on apps the
-//! user actually chose, instead of thousands of pulled-in dependencies.
+//! user actually chose, instead of thousands of pulled-in dependencies. The
+//! same call reads the `Essential`, `Protected` and `Priority` fields that
+//! [`crate::safety`] protects base-system packages by.
 
 use std::collections::HashSet;
 use std::future::Future;
@@ -12,11 +14,19 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 
-use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::package::{AppKind, BaseSystem, InstalledPackage, PackageSource};
 use crate::scanner::Scanner;
-use crate::system::{capture_stdout, which, SCAN_TIMEOUT};
+use crate::system::{scan_timeout, CommandRunner, Runner};
 
-pub struct AptScanner;
+pub struct AptScanner {
+    runner: Runner,
+}
+
+impl AptScanner {
+    pub fn new(runner: Runner) -> Self {
+        Self { runner }
+    }
+}
 
 impl Scanner for AptScanner {
     fn source(&self) -> PackageSource {
@@ -24,19 +34,22 @@ impl Scanner for AptScanner {
     }
 
     fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
-        Box::pin(async { which("dpkg-query") && which("apt-mark") })
+        Box::pin(async {
+            self.runner.has_program("dpkg-query") && self.runner.has_program("apt-mark")
+        })
     }
 
     fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
-        Box::pin(scan())
+        Box::pin(scan(self.runner.as_ref()))
     }
 }
 
 // Field separator unlikely to appear in package metadata.
 const SEP: &str = "\x1f";
 
-async fn scan() -> Result<Vec<InstalledPackage>> {
-    let manual = capture_stdout("apt-mark", &["showmanual"], SCAN_TIMEOUT)
+async fn scan(runner: &dyn CommandRunner) -> Result<Vec<InstalledPackage>> {
+    let manual = runner
+        .capture_stdout("apt-mark", &["showmanual"], scan_timeout())
         .await
         .context("read manually-installed packages")?;
     let manual: HashSet<String> = manual
@@ -47,20 +60,33 @@ async fn scan() -> Result<Vec<InstalledPackage>> {
     if manual.is_empty() {
         return Ok(Vec::new());
     }
+    // Held packages are pinned by `apt-mark hold`; a failure just means none.
+    let held: HashSet<String> = runner
+        .capture_stdout("apt-mark", &["showhold"], scan_timeout())
+        .await
+        .map(|out| out.lines().map(|l| l.trim().to_string()).collect())
+        .unwrap_or_default();
 
     // One dpkg-query over all manual names. Installed-Size is in KiB.
     // ${binary:Summary} truncates the description to one line; perfect for the UI.
+    // Fields a package does not have (or dpkg does not know) come out empty.
     let format = format!(
-        "${{Package}}{SEP}${{Version}}{SEP}${{Installed-Size}}{SEP}${{binary:Summary}}{SEP}\\n"
+        "${{Package}}{SEP}${{Version}}{SEP}${{Installed-Size}}{SEP}${{binary:Summary}}{SEP}\
+         ${{Essential}}{SEP}${{Protected}}{SEP}${{Priority}}{SEP}\\n"
     );
     let mut args: Vec<String> = vec!["-W".into(), format!("-f={format}")];
     args.extend(manual.into_iter());
 
     let argv: Vec<&str> = args.iter().map(String::as_str).collect();
-    let output = capture_stdout("dpkg-query", &argv, Duration::from_secs(20))
+    let output = runner
+        .capture_stdout("dpkg-query", &argv, Duration::from_secs(20))
         .await
         .context("query dpkg metadata for manual packages")?;
+    Ok(parse_dpkg_query(&output, &held))
+}
 
+/// Parse `dpkg-query -W` output in the [`SEP`]-separated format `scan` asks for.
+fn parse_dpkg_query(output: &str, held: &HashSet<String>) -> Vec<InstalledPackage> {
     let mut packages = Vec::new();
     for line in output.lines() {
         let parts: Vec<&str> = line.split(SEP).collect();
@@ -71,6 +97,7 @@ async fn scan() -> Result<Vec<InstalledPackage>> {
         let version = parts[1].to_string();
         let kib: u64 = parts[2].parse().unwrap_or(0);
         let summary = parts.get(3).copied().unwrap_or("").to_string();
+        let field = |i: usize| parts.get(i).copied().unwrap_or("");
 
         let mut pkg = InstalledPackage::new(PackageSource::Apt, name.clone());
         pkg.name = name;
@@ -80,10 +107,11 @@ async fn scan() -> Result<Vec<InstalledPackage>> {
             pkg.description = Some(summary);
         }
         pkg.app_kind = classify(&pkg.name);
+        pkg.held = held.contains(&pkg.package_id);
+        pkg.base_system = BaseSystem::from_fields(field(4), field(5), field(6));
         packages.push(pkg);
     }
-    check_updates(&mut packages).await;
-    Ok(packages)
+    packages
 }
 
 /// Best-effort GUI/CLI classification using filesystem presence, without
@@ -106,36 +134,42 @@ fn classify(name: &str) -> AppKind {
 }
 
 /// Run `apt list --upgradable` and mark packages that have available updates.
-async fn check_updates(packages: &mut Vec<InstalledPackage>) {
-    let output = match capture_stdout("apt", &["list", "--upgradable"], Duration::from_secs(30)).await
-    {
-        Ok(o) => o,
-        Err(_) => return,
-    };
-
-    // Line format: "package/suite candidate_version arch [upgradable from: old_version]"
-    // Or:          "package/arch candidate_version arch [held]"
-    let re = match regex::Regex::new(
-        r"^(\S+)/(\S+)\s+(\S+)\s+\S+\s+\[upgradable from: (\S+)\]",
-    ) {
-        Ok(r) => r,
-        Err(_) => return,
-    };
-
-    for line in output.lines() {
-        let line = line.trim();
-        if line.is_empty() || line == "Listing..." || line.starts_with("WARNING:") {
-            continue;
-        }
-        if let Some(caps) = re.captures(line) {
-            let name = caps[1].to_string();
-            let candidate = caps[3].to_string();
-            if let Some(pkg) = packages.iter_mut().find(|p| p.package_id == name) {
-                pkg.has_update = true;
-                pkg.update_version = Some(candidate);
-            }
+pub async fn check_updates(
+    runner: &dyn CommandRunner,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
+    let output = runner
+        .capture_stdout("apt", &["list", "--upgradable"], Duration::from_secs(30))
+        .await?;
+    for (name, candidate, security) in parse_upgradable(&output) {
+        if let Some(pkg) = packages.iter_mut().find(|p| p.package_id == name) {
+            pkg.has_update = true;
+            pkg.update_version = Some(candidate);
+            pkg.security_update = security;
         }
     }
+    Ok(())
+}
+
+/// `(package, candidate version, from a security suite)` triples from
+/// `apt list --upgradable`.
+///
+/// Line format: "package/suite[,suite...] candidate_version arch [upgradable from: old_version]".
+/// The bracketed note is translated (e.g. "[aktualisierbar von: ...]"), as is
+/// the "Listing..." header, so only the untranslated columns are matched.
+/// Ubuntu and Debian publish security fixes to suites ending in `-security`.
+fn parse_upgradable(output: &str) -> Vec<(String, String, bool)> {
+    let Ok(re) = regex::Regex::new(r"^([^/\s]+)/(\S+)\s+(\S+)\s+\S+\s+\[.+\]$") else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter_map(|line| re.captures(line.trim()))
+        .map(|caps| {
+            let security = caps[2].split(',').any(|suite| suite.ends_with("-security"));
+            (caps[1].to_string(), caps[3].to_string(), security)
+        })
+        .collect()
 }
 
 fn has_binary(name: &str) -> bool {
@@ -148,3 +182,60 @@ fn has_binary(name: &str) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{fixture_runner, Runner};
+
+    #[tokio::test]
+    async fn scans_recorded_apt_output() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+        let mut packages = AptScanner::new(runner.clone()).scan().await.unwrap();
+        assert_eq!(packages.len(), 7);
+
+        let vlc = packages.iter().find(|p| p.package_id == "vlc").unwrap();
+        assert!(vlc.held);
+        assert_eq!(vlc.size_bytes, 112 * 1024);
+        assert_eq!(vlc.version, "3.0.20-3build6");
+        assert_eq!(vlc.base_system, None);
+        let custom = packages
+            .iter()
+            .find(|p| p.package_id == "xdg-desktop-portal-custom")
+            .unwrap();
+        assert_eq!(custom.size_bytes, 0);
+        assert_eq!(custom.description, None);
+
+        check_updates(runner.as_ref(), &mut packages).await.unwrap();
+        let updated: Vec<(&str, Option<&str>)> = packages
+            .iter()
+            .filter(|p| p.has_update)
+            .map(|p| (p.package_id.as_str(), p.update_version.as_deref()))
+            .collect();
+        assert_eq!(
+            updated,
+            [
+                ("curl", Some("8.5.0-2ubuntu10.7")),
+                ("vlc", Some("3.0.21-1ubuntu0.1"))
+            ]
+        );
+        let curl = packages.iter().find(|p| p.package_id == "curl").unwrap();
+        let vlc = packages.iter().find(|p| p.package_id == "vlc").unwrap();
+        assert!(curl.security_update && !vlc.security_update);
+    }
+
+    #[tokio::test]
+    async fn reads_localized_upgradable_list() {
+        let runner: Runner = fixture_runner("ubuntu-24.04-de");
+        let mut packages = AptScanner::new(runner.clone()).scan().await.unwrap();
+        check_updates(runner.as_ref(), &mut packages).await.unwrap();
+        let curl = packages.iter().find(|p| p.package_id == "curl").unwrap();
+        assert!(curl
+            .description
+            .as_deref()
+            .unwrap()
+            .contains("Datenübertragung"));
+        assert_eq!(curl.update_version.as_deref(), Some("8.5.0-2ubuntu10.7"));
+        assert!(!packages.iter().any(|p| p.held));
+    }
+}
@@ -0,0 +1,655 @@
+//! Distrobox / Toolbox container-export scanner.
+//!
+//! Strategy: list the containers managed by distrobox/toolbox (by label via
+//! `podman ps -a`, falling back to `distrobox list`), then read the apps and
+//! binaries they exported onto the host: `.desktop` files under
+//! `~/.local/share/applications` whose `Exec=` enters a container, and the
+//! `# distrobox_binary` wrapper scripts under `~/.local/bin`.
+//!
+//! Each exported item is reported as one package keyed by its host-side export
+//! file (like AppImages are keyed by path). For containers that are already
+//! running, the owning in-container package and its version are resolved
+//! through the container's own package manager, which [`check_updates`] also
+//! asks for pending upgrades. Stopped containers are never started during a
+//! scan.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use anyhow::Result;
+
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::Scanner;
+use crate::system::{scan_timeout, which, CommandRunner, Dirs, Runner};
+
+pub struct ContainerScanner {
+    runner: Runner,
+}
+
+impl ContainerScanner {
+    pub fn new(runner: Runner) -> Self {
+        Self { runner }
+    }
+}
+
+impl Scanner for ContainerScanner {
+    fn source(&self) -> PackageSource {
+        PackageSource::Container
+    }
+
+    fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
+        let runner = &self.runner;
+        Box::pin(async move { runner.has_program("distrobox") || runner.has_program("toolbox") })
+    }
+
+    fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
+        Box::pin(scan(self.runner.as_ref()))
+    }
+}
+
+/// Cheap probe: a container tool that can export apps is installed.
+pub fn is_available() -> bool {
+    which("distrobox") || which("toolbox")
+}
+
+/// Which tool created a container / export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerTool {
+    Distrobox,
+    Toolbox,
+}
+
+impl ContainerTool {
+    pub fn label(self) -> &'static str {
+        match self {
+            ContainerTool::Distrobox => "Distrobox",
+            ContainerTool::Toolbox => "Toolbox",
+        }
+    }
+}
+
+/// What kind of host-side export a package represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    App,
+    Binary,
+}
+
+/// One app or binary exported from a container onto the host.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// Host-side `.desktop` file or wrapper script.
+    pub host_path: PathBuf,
+    pub kind: ExportKind,
+    pub tool: ContainerTool,
+    pub container: String,
+    /// The command the export runs inside the container (name or path).
+    pub target: String,
+}
+
+impl Export {
+    /// Absolute in-container path of the exported executable. Bare command
+    /// names are assumed to live in `/usr/bin`, which holds for the packaged
+    /// apps distrobox/toolbox export in practice.
+    pub fn target_path(&self) -> String {
+        if self.target.starts_with('/') {
+            self.target.clone()
+        } else {
+            format!("/usr/bin/{}", self.target)
+        }
+    }
+
+    /// Short name of the exported command (basename of the target).
+    pub fn command_name(&self) -> String {
+        Path::new(&self.target)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.target.clone())
+    }
+}
+
+/// A distrobox/toolbox container as reported by the container runtime.
+#[derive(Debug, Clone)]
+struct Container {
+    name: String,
+    image: String,
+    running: bool,
+}
+
+/// Package manager found inside a container, detected from `/etc/os-release`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Pacman,
+    Zypper,
+}
+
+impl PackageManager {
+    /// argv (after the program) that removes `pkg` non-interactively.
+    pub fn remove_args(self, pkg: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            PackageManager::Apt => &["apt-get", "remove", "-y"],
+            PackageManager::Dnf => &["dnf", "remove", "-y"],
+            PackageManager::Pacman => &["pacman", "-R", "--noconfirm"],
+            PackageManager::Zypper => &["zypper", "-n", "remove"],
+        };
+        let mut out: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        out.push(pkg.to_string());
+        out
+    }
+
+    /// argv that upgrades only `pkg` non-interactively.
+    pub fn upgrade_args(self, pkg: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            PackageManager::Apt => &["apt-get", "install", "--only-upgrade", "-y"],
+            PackageManager::Dnf => &["dnf", "upgrade", "-y"],
+            PackageManager::Pacman => &["pacman", "-S", "--noconfirm"],
+            PackageManager::Zypper => &["zypper", "-n", "update"],
+        };
+        let mut out: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        out.push(pkg.to_string());
+        out
+    }
+}
+
+/// Container runtime binary used by distrobox/toolbox on this host.
+pub fn runtime() -> &'static str {
+    if which("podman") || !which("docker") {
+        "podman"
+    } else {
+        "docker"
+    }
+}
+
+async fn scan(runner: &dyn CommandRunner) -> Result<Vec<InstalledPackage>> {
+    let containers = list_containers(runner).await;
+    let export_dirs = export_dirs(&runner.dirs());
+    let exports = tokio::task::spawn_blocking(move || discover_exports(&export_dirs))
+        .await
+        .unwrap_or_default();
+
+    let mut packages = Vec::new();
+    for export in exports {
+        let container = containers.iter().find(|c| c.name == export.container);
+        let mut pkg = InstalledPackage::new(
+            PackageSource::Container,
+            export.host_path.to_string_lossy().to_string(),
+        );
+        pkg.name = export.command_name();
+        pkg.app_kind = match export.kind {
+            ExportKind::App => AppKind::Gui,
+            ExportKind::Binary => AppKind::Cli,
+        };
+        pkg.description = Some(match container {
+            Some(c) => format!(
+                "Exported from {} container '{}' ({})",
+                export.tool.label(),
+                c.name,
+                c.image
+            ),
+            None => format!(
+                "Exported from {} container '{}' (container not found)",
+                export.tool.label(),
+                export.container
+            ),
+        });
+        if let Some(c) = container.filter(|c| c.running) {
+            annotate_from_container(runner, &mut pkg, &export, c).await;
+        }
+        packages.push(pkg);
+    }
+    Ok(packages)
+}
+
+/// Fill the version from the in-container package manager. Only used for
+/// containers that are already running; failures leave the package as-is.
+async fn annotate_from_container(
+    runner: &dyn CommandRunner,
+    pkg: &mut InstalledPackage,
+    export: &Export,
+    c: &Container,
+) {
+    let Some(pm) = detect_package_manager(runner, &c.name).await else {
+        return;
+    };
+    if let Some((_, version)) = owner_of(runner, &c.name, pm, &export.target_path()).await {
+        pkg.version = version;
+    }
+}
+
+/// Mark exports whose owning package has an upgrade inside the container.
+/// Like the scan, this never starts a stopped container; exports that cannot
+/// be asked about are skipped rather than failing the check.
+pub async fn check_updates(
+    runner: &dyn CommandRunner,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
+    let containers = list_containers(runner).await;
+    for pkg in packages.iter_mut() {
+        let Some(export) = load_export(Path::new(&pkg.package_id)) else {
+            continue;
+        };
+        if !containers
+            .iter()
+            .any(|c| c.name == export.container && c.running)
+        {
+            continue;
+        }
+        let Some(pm) = detect_package_manager(runner, &export.container).await else {
+            continue;
+        };
+        let Some((owner, _)) = owner_of(runner, &export.container, pm, &export.target_path()).await
+        else {
+            continue;
+        };
+        if let Some(candidate) = upgradable_version(runner, &export.container, pm, &owner).await {
+            pkg.has_update = true;
+            pkg.update_version = Some(candidate);
+        }
+    }
+    Ok(())
+}
+
+/// List distrobox and toolbox containers. Never fails: a missing runtime just
+/// yields no containers, and exports are still reported.
+async fn list_containers(runner: &dyn CommandRunner) -> Vec<Container> {
+    let rt = runtime();
+    let mut out = Vec::new();
+    if runner.has_program(rt) {
+        for label in ["manager=distrobox", "com.github.containers.toolbox=true"] {
+            let filter = format!("label={label}");
+            if let Ok(output) = runner
+                .capture_stdout(
+                    rt,
+                    &[
+                        "ps",
+                        "-a",
+                        "--filter",
+                        &filter,
+                        "--format",
+                        "{{.Names}}\t{{.Image}}\t{{.State}}",
+                    ],
+                    scan_timeout(),
+                )
+                .await
+            {
+                out.extend(parse_ps(&output));
+            }
+        }
+    } else if runner.has_program("distrobox") {
+        if let Ok(output) = runner
+            .capture_stdout("distrobox", &["list", "--no-color"], scan_timeout())
+            .await
+        {
+            out.extend(parse_distrobox_list(&output));
+        }
+    }
+    out
+}
+
+/// Parse `<runtime> ps --format "{{.Names}}\t{{.Image}}\t{{.State}}"`.
+fn parse_ps(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            let name = parts.first()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some(Container {
+                name: name.to_string(),
+                image: parts.get(1).copied().unwrap_or("").trim().to_string(),
+                running: parts
+                    .get(2)
+                    .map(|s| s.trim().eq_ignore_ascii_case("running"))
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// Parse `distrobox list --no-color`: `ID | NAME | STATUS | IMAGE` rows.
+fn parse_distrobox_list(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('|').map(str::trim).collect();
+            if parts.len() < 4 || parts[1].is_empty() {
+                return None;
+            }
+            Some(Container {
+                name: parts[1].to_string(),
+                image: parts[3].to_string(),
+                running: parts[2].starts_with("Up"),
+            })
+        })
+        .collect()
+}
+
+/// Host directories exports may live in: exported `.desktop` files, then
+/// exported binary wrappers. Used by the safety guard too.
+pub fn export_dirs(dirs: &Dirs) -> Vec<PathBuf> {
+    let applications = dirs.data_home().map(|d| d.join("applications"));
+    let bin = dirs.home().map(|h| h.join(".local/bin"));
+    applications.into_iter().chain(bin).collect()
+}
+
+/// Walk `export_dirs` for container exports (synchronous fs walk).
+fn discover_exports(export_dirs: &[PathBuf]) -> Vec<Export> {
+    let mut out = Vec::new();
+    for dir in export_dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if let Some(export) = load_export(&path) {
+                out.push(export);
+            }
+        }
+    }
+    out
+}
+
+/// Parse a host-side export file. Returns `None` for anything that is not a
+/// distrobox/toolbox export.
+pub fn load_export(path: &Path) -> Option<Export> {
+    if !path.is_file() {
+        return None;
+    }
+    // Exports are small text files; skip anything large (real binaries).
+    if std::fs::metadata(path).ok()?.len() > 64 * 1024 {
+        return None;
+    }
+    let content = std::fs::read_to_string(path).ok()?;
+    let is_desktop = path.extension().and_then(|e| e.to_str()) == Some("desktop");
+    let (kind, tool, container, target) = if is_desktop {
+        let exec = content
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("Exec="))?;
+        let (tool, container, target) = parse_exec(exec)?;
+        (ExportKind::App, tool, container, target)
+    } else {
+        if !content.lines().any(|l| l.trim() == "# distrobox_binary") {
+            return None;
+        }
+        let line = content.lines().find(|l| l.contains("distrobox-enter"))?;
+        let (tool, container, target) = parse_exec(line)?;
+        (ExportKind::Binary, tool, container, target)
+    };
+    Some(Export {
+        host_path: path.to_path_buf(),
+        kind,
+        tool,
+        container,
+        target,
+    })
+}
+
+/// Extract `(tool, container, in-container command)` from an `Exec=` value or
+/// wrapper-script line that enters a container.
+fn parse_exec(exec: &str) -> Option<(ContainerTool, String, String)> {
+    let tokens: Vec<String> = exec
+        .split_whitespace()
+        .map(|t| t.trim_matches(|c| c == '\'' || c == '"').to_string())
+        .collect();
+    let is_distrobox = tokens.iter().enumerate().any(|(i, t)| {
+        t.ends_with("distrobox-enter")
+            || (t.ends_with("distrobox") && tokens.get(i + 1).map(String::as_str) == Some("enter"))
+    });
+    let is_toolbox = tokens.iter().enumerate().any(|(i, t)| {
+        t.ends_with("toolbox") && tokens.get(i + 1).map(String::as_str) == Some("run")
+    });
+
+    let value_after = |flags: &[&str]| -> Option<String> {
+        let i = tokens.iter().position(|t| flags.contains(&t.as_str()))?;
+        tokens.get(i + 1).cloned()
+    };
+
+    if is_distrobox {
+        let container = value_after(&["-n", "--name"])?;
+        let sep = tokens.iter().position(|t| t == "--")?;
+        let target = tokens.get(sep + 1)?.clone();
+        return Some((ContainerTool::Distrobox, container, target));
+    }
+    if is_toolbox {
+        let container = value_after(&["-c", "--container"])?;
+        let i = tokens.iter().position(|t| *t == container)?;
+        let target = tokens[i + 1..]
+            .iter()
+            .find(|t| !t.starts_with('-'))?
+            .clone();
+        return Some((ContainerTool::Toolbox, container, target));
+    }
+    None
+}
+
+/// Run a command inside a container and capture its stdout.
+async fn exec_in(runner: &dyn CommandRunner, container: &str, args: &[&str]) -> Result<String> {
+    let mut argv = vec!["exec", container];
+    argv.extend_from_slice(args);
+    runner
+        .capture_stdout(runtime(), &argv, scan_timeout())
+        .await
+}
+
+/// Detect the container's package manager from its `/etc/os-release`.
+pub async fn detect_package_manager(
+    runner: &dyn CommandRunner,
+    container: &str,
+) -> Option<PackageManager> {
+    let os_release = exec_in(runner, container, &["cat", "/etc/os-release"])
+        .await
+        .ok()?;
+    package_manager_for(&os_release)
+}
+
+fn package_manager_for(os_release: &str) -> Option<PackageManager> {
+    let mut ids: Vec<String> = Vec::new();
+    for line in os_release.lines() {
+        if let Some(v) = line
+            .strip_prefix("ID=")
+            .or_else(|| line.strip_prefix("ID_LIKE="))
+        {
+            ids.extend(
+                v.trim_matches('"')
+                    .split_whitespace()
+                    .map(str::to_lowercase),
+            );
+        }
+    }
+    ids.iter().find_map(|id| match id.as_str() {
+        "debian" | "ubuntu" => Some(PackageManager::Apt),
+        "fedora" | "rhel" | "centos" | "almalinux" | "rocky" => Some(PackageManager::Dnf),
+        "arch" | "archlinux" | "manjaro" => Some(PackageManager::Pacman),
+        "opensuse" | "suse" | "opensuse-tumbleweed" | "opensuse-leap" => {
+            Some(PackageManager::Zypper)
+        }
+        _ => None,
+    })
+}
+
+/// `(package name, version)` of the in-container package owning `path`.
+pub async fn owner_of(
+    runner: &dyn CommandRunner,
+    container: &str,
+    pm: PackageManager,
+    path: &str,
+) -> Option<(String, String)> {
+    match pm {
+        PackageManager::Apt => {
+            let out = exec_in(runner, container, &["dpkg-query", "-S", path])
+                .await
+                .ok()?;
+            let name = out.lines().next()?.split(':').next()?.trim().to_string();
+            let version = exec_in(
+                runner,
+                container,
+                &["dpkg-query", "-W", "-f=${Version}", &name],
+            )
+            .await
+            .unwrap_or_default();
+            Some((name, version.trim().to_string()))
+        }
+        PackageManager::Dnf | PackageManager::Zypper => {
+            let out = exec_in(
+                runner,
+                container,
+                &[
+                    "rpm",
+                    "-qf",
+                    "--qf",
+                    "%{NAME}\t%{VERSION}-%{RELEASE}\n",
+                    path,
+                ],
+            )
+            .await
+            .ok()?;
+            let (name, version) = out.lines().next()?.split_once('\t')?;
+            Some((name.to_string(), version.to_string()))
+        }
+        PackageManager::Pacman => {
+            // "/usr/bin/htop is owned by htop 3.3.0-1"
+            let out = exec_in(runner, container, &["pacman", "-Qo", path])
+                .await
+                .ok()?;
+            let rest = out.lines().next()?.split(" is owned by ").nth(1)?;
+            let mut parts = rest.split_whitespace();
+            Some((
+                parts.next()?.to_string(),
+                parts.next().unwrap_or("").to_string(),
+            ))
+        }
+    }
+}
+
+/// Candidate version when `pkg` has a pending update inside the container.
+/// Uses only the container's local package metadata (no refresh).
+async fn upgradable_version(
+    runner: &dyn CommandRunner,
+    container: &str,
+    pm: PackageManager,
+    pkg: &str,
+) -> Option<String> {
+    match pm {
+        PackageManager::Apt => {
+            // "htop/stable 3.3.0-2 amd64 [upgradable from: 3.2.2-2]"
+            let out = exec_in(runner, container, &["apt", "list", "--upgradable"])
+                .await
+                .ok()?;
+            out.lines().find_map(|l| {
+                let (name, rest) = l.split_once('/')?;
+                (name == pkg).then(|| rest.split_whitespace().nth(1).map(str::to_string))?
+            })
+        }
+        PackageManager::Dnf => {
+            // "htop.x86_64   3.3.0-1.fc40   updates"
+            let out = exec_in(runner, container, &["dnf", "list", "--upgrades", "-q"])
+                .await
+                .ok()?;
+            out.lines().find_map(|l| {
+                let mut parts = l.split_whitespace();
+                let name = parts.next()?.rsplit_once('.')?.0;
+                (name == pkg).then(|| parts.next().map(str::to_string))?
+            })
+        }
+        PackageManager::Pacman => {
+            // "htop 3.2.2-1 -> 3.3.0-1"
+            let out = exec_in(runner, container, &["pacman", "-Qu"]).await.ok()?;
+            out.lines().find_map(|l| {
+                let parts: Vec<&str> = l.split_whitespace().collect();
+                (parts.first() == Some(&pkg)).then(|| parts.get(3).map(|s| s.to_string()))?
+            })
+        }
+        PackageManager::Zypper => None,
+    }
+}
+
+/// Whether a container with this name still exists (running or not).
+pub async fn exists(runner: &dyn CommandRunner, container: &str) -> bool {
+    runner
+        .capture_stdout(
+            runtime(),
+            &["inspect", "--format", "{{.Name}}", container],
+            scan_timeout(),
+        )
+        .await
+        .is_ok()
+}
+
+/// Whether the named container is currently running.
+pub async fn is_running(runner: &dyn CommandRunner, container: &str) -> bool {
+    runner
+        .capture_stdout(
+            runtime(),
+            &["inspect", "--format", "{{.State.Running}}", container],
+            scan_timeout(),
+        )
+        .await
+        .map(|s| s.trim() == "true")
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_distrobox_desktop_exec() {
+        let (tool, name, target) =
+            parse_exec("/usr/bin/distrobox-enter  -n fedora  --   gnome-calculator  %U").unwrap();
+        assert_eq!(tool, ContainerTool::Distrobox);
+        assert_eq!(name, "fedora");
+        assert_eq!(target, "gnome-calculator");
+    }
+
+    #[test]
+    fn parses_distrobox_binary_wrapper_line() {
+        let line = "\texec \"/usr/bin/distrobox-enter\"  -n box  --  '/usr/bin/htop'  \"$@\"";
+        let (_, name, target) = parse_exec(line).unwrap();
+        assert_eq!(name, "box");
+        assert_eq!(target, "/usr/bin/htop");
+    }
+
+    #[test]
+    fn parses_toolbox_run_exec() {
+        let (tool, name, target) = parse_exec("toolbox run -c dev code --new-window %F").unwrap();
+        assert_eq!(tool, ContainerTool::Toolbox);
+        assert_eq!(name, "dev");
+        assert_eq!(target, "code");
+    }
+
+    #[test]
+    fn ignores_regular_exec() {
+        assert!(parse_exec("firefox %u").is_none());
+    }
+
+    #[test]
+    fn detects_package_manager_from_os_release() {
+        let ubuntu = "NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\n";
+        assert_eq!(package_manager_for(ubuntu), Some(PackageManager::Apt));
+        let fedora = "NAME=\"Fedora Linux\"\nID=fedora\n";
+        assert_eq!(package_manager_for(fedora), Some(PackageManager::Dnf));
+        let alpine = "ID=alpine\n";
+        assert_eq!(package_manager_for(alpine), None);
+    }
+
+    #[test]
+    fn parses_runtime_and_distrobox_listings() {
+        let ps = parse_ps("box\tregistry.fedoraproject.org/fedora:40\trunning\n");
+        assert_eq!(ps.len(), 1);
+        assert!(ps[0].running);
+        let list = parse_distrobox_list(
+            "ID           | NAME   | STATUS        | IMAGE\n\
+             1a2b3c4d5e6f | box    | Exited (0)    | docker.io/library/ubuntu:24.04\n",
+        );
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "box");
+        assert!(!list[0].running);
+    }
+}
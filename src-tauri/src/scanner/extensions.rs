@@ -0,0 +1,320 @@
+//! Per-user editor and desktop extension scanner.
+//!
+//! Strategy: read VS Code / VSCodium's `extensions/extensions.json` manifest
+//! (plus each extension's `package.json` for display metadata) and walk
+//! `~/.local/share/gnome-shell/extensions/*/metadata.json`. GNOME Shell enabled
+//! state comes from `gsettings get org.gnome.shell enabled-extensions`.
+//!
+//! Every extension is keyed by its absolute install directory, which is also
+//! what removal deletes after the [`crate::safety`] guard confirms the
+//! directory sits directly inside one of [`extension_roots`].
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use anyhow::Result;
+use walkdir::WalkDir;
+
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::Scanner;
+use crate::system::{scan_timeout, CommandRunner, Dirs, Runner};
+
+pub struct ExtensionScanner {
+    runner: Runner,
+}
+
+impl ExtensionScanner {
+    pub fn new(runner: Runner) -> Self {
+        Self { runner }
+    }
+}
+
+impl Scanner for ExtensionScanner {
+    fn source(&self) -> PackageSource {
+        PackageSource::Extension
+    }
+
+    fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
+        Box::pin(async { is_available(&self.runner.dirs()) })
+    }
+
+    fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
+        Box::pin(scan(self.runner.as_ref()))
+    }
+}
+
+/// Cheap probe: any extension root exists for this user.
+pub fn is_available(dirs: &Dirs) -> bool {
+    extension_roots(dirs).iter().any(|(_, dir)| dir.is_dir())
+}
+
+/// Application an extension belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionHost {
+    VsCode,
+    GnomeShell,
+}
+
+impl ExtensionHost {
+    pub fn label(self) -> &'static str {
+        match self {
+            ExtensionHost::VsCode => "VS Code",
+            ExtensionHost::GnomeShell => "GNOME Shell",
+        }
+    }
+}
+
+/// Per-user directories whose direct children are extensions.
+pub fn extension_roots(dirs: &Dirs) -> Vec<(ExtensionHost, PathBuf)> {
+    let (Some(home), Some(data_home)) = (dirs.home(), dirs.data_home()) else {
+        return Vec::new();
+    };
+    vec![
+        (ExtensionHost::VsCode, home.join(".vscode/extensions")),
+        (
+            ExtensionHost::VsCode,
+            home.join(".vscode-insiders/extensions"),
+        ),
+        (ExtensionHost::VsCode, home.join(".vscode-oss/extensions")),
+        (
+            ExtensionHost::GnomeShell,
+            data_home.join("gnome-shell/extensions"),
+        ),
+    ]
+}
+
+/// Which host owns an extension directory, if it lives in a known root.
+pub fn host_for(dir: &Path, dirs: &Dirs) -> Option<ExtensionHost> {
+    let parent = dir.parent()?;
+    extension_roots(dirs)
+        .into_iter()
+        .find(|(_, root)| root == parent)
+        .map(|(host, _)| host)
+}
+
+async fn scan(runner: &dyn CommandRunner) -> Result<Vec<InstalledPackage>> {
+    let enabled = gnome_enabled_extensions(runner).await;
+    let roots = extension_roots(&runner.dirs());
+    let packages = tokio::task::spawn_blocking(move || {
+        let mut out = Vec::new();
+        for (host, root) in roots {
+            match host {
+                ExtensionHost::VsCode => out.extend(scan_vscode(&root)),
+                ExtensionHost::GnomeShell => out.extend(scan_gnome(&root, enabled.as_ref())),
+            }
+        }
+        out
+    })
+    .await
+    .unwrap_or_default();
+    Ok(packages)
+}
+
+/// Read `extensions.json` for installed VS Code extensions. Entries whose
+/// directory is gone (pending cleanup by VS Code) are skipped.
+fn scan_vscode(root: &Path) -> Vec<InstalledPackage> {
+    let Ok(content) = std::fs::read_to_string(root.join("extensions.json")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = serde_json::from_str::<Vec<serde_json::Value>>(&content) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for entry in entries {
+        let Some(id) = entry
+            .pointer("/identifier/id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let dir = entry
+            .get("relativeLocation")
+            .and_then(|v| v.as_str())
+            .map(|rel| root.join(rel))
+            .or_else(|| {
+                entry
+                    .pointer("/location/path")
+                    .and_then(|v| v.as_str())
+                    .map(PathBuf::from)
+            });
+        let Some(dir) = dir.filter(|d| d.is_dir()) else {
+            continue;
+        };
+        let manifest = read_json(&dir.join("package.json"));
+        let nls = read_json(&dir.join("package.nls.json"));
+        let localized = |key: &str| -> Option<String> {
+            let raw = manifest.as_ref()?.get(key)?.as_str()?;
+            match raw.strip_prefix('%').and_then(|r| r.strip_suffix('%')) {
+                Some(nls_key) => nls.as_ref()?.get(nls_key)?.as_str().map(str::to_string),
+                None => Some(raw.to_string()),
+            }
+        };
+
+        let mut pkg =
+            InstalledPackage::new(PackageSource::Extension, dir.to_string_lossy().to_string());
+        pkg.name = id;
+        pkg.display_name = localized("displayName");
+        pkg.version = entry
+            .get("version")
+            .and_then(|v| v.as_str())
+            .or_else(|| manifest.as_ref()?.get("version")?.as_str())
+            .unwrap_or("")
+            .to_string();
+        pkg.description = Some(match localized("description") {
+            Some(d) => format!("VS Code extension: {d}"),
+            None => "VS Code extension".into(),
+        });
+        pkg.categories = Some("VS Code extension".into());
+        pkg.size_bytes = dir_size(&dir);
+        pkg.app_kind = AppKind::Unknown;
+        // The extension's own `icon` (a path relative to its directory).
+        if let Some(icon) = manifest
+            .as_ref()
+            .and_then(|m| m.get("icon")?.as_str().map(|i| dir.join(i)))
+            .filter(|p| p.is_file())
+        {
+            pkg.icon = Some(crate::icons::icon_url(&icon));
+        }
+        out.push(pkg);
+    }
+    out
+}
+
+fn scan_gnome(root: &Path, enabled: Option<&HashSet<String>>) -> Vec<InstalledPackage> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    // GNOME extensions ship no icon of their own; use the Extensions app icon.
+    let icon = ["org.gnome.Extensions", "application-x-addon"]
+        .iter()
+        .find_map(|name| crate::icons::resolve(name))
+        .map(|p| crate::icons::icon_url(&p));
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let Some(meta) = read_json(&dir.join("metadata.json")) else {
+            continue;
+        };
+        let uuid = meta
+            .get("uuid")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
+
+        let mut pkg =
+            InstalledPackage::new(PackageSource::Extension, dir.to_string_lossy().to_string());
+        pkg.display_name = meta
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        pkg.version = meta
+            .get("version-name")
+            .and_then(|v| v.as_str().map(str::to_string))
+            .or_else(|| meta.get("version").map(|v| v.to_string()))
+            .unwrap_or_default();
+        pkg.description = Some(
+            match meta
+                .get("description")
+                .and_then(|v| v.as_str())
+                .and_then(|d| d.lines().next())
+            {
+                Some(d) => format!("GNOME Shell extension: {d}"),
+                None => "GNOME Shell extension".into(),
+            },
+        );
+        pkg.categories = Some("GNOME Shell extension".into());
+        pkg.enabled = enabled.map(|set| set.contains(&uuid));
+        pkg.name = uuid;
+        pkg.size_bytes = dir_size(&dir);
+        pkg.app_kind = AppKind::Unknown;
+        pkg.icon = icon.clone();
+        out.push(pkg);
+    }
+    out
+}
+
+/// UUIDs listed in `org.gnome.shell enabled-extensions`, or `None` when the
+/// setting can't be read (not a GNOME session, no gsettings).
+pub async fn gnome_enabled_extensions(runner: &dyn CommandRunner) -> Option<HashSet<String>> {
+    if !runner.has_program("gsettings") {
+        return None;
+    }
+    let out = runner
+        .capture_stdout(
+            "gsettings",
+            &["get", "org.gnome.shell", "enabled-extensions"],
+            scan_timeout(),
+        )
+        .await
+        .ok()?;
+    Some(parse_gvariant_strv(&out))
+}
+
+/// Parse a GVariant string array as printed by gsettings:
+/// `['a@b.org', 'c@d']` or `@as []`.
+fn parse_gvariant_strv(value: &str) -> HashSet<String> {
+    let inner = value
+        .trim()
+        .trim_start_matches("@as")
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    inner
+        .split(',')
+        .map(|s| s.trim().trim_matches(|c| c == '\'' || c == '"').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Drop an extension directory's entry from VS Code's `extensions.json` so the
+/// editor does not keep listing it after the directory is deleted.
+pub fn forget_vscode_extension(dir: &Path) -> Result<()> {
+    let root = dir
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("extension directory has no parent"))?;
+    let manifest = root.join("extensions.json");
+    let content = std::fs::read_to_string(&manifest)?;
+    let mut entries: Vec<serde_json::Value> = serde_json::from_str(&content)?;
+    let dir_name = dir.file_name().map(|n| n.to_string_lossy().to_string());
+    let dir_str = dir.to_string_lossy();
+    entries.retain(|entry| {
+        let rel = entry.get("relativeLocation").and_then(|v| v.as_str());
+        let abs = entry.pointer("/location/path").and_then(|v| v.as_str());
+        rel != dir_name.as_deref() && abs != Some(dir_str.as_ref())
+    });
+    std::fs::write(&manifest, serde_json::to_string(&entries)?)?;
+    Ok(())
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_gsettings_string_arrays() {
+        let set = parse_gvariant_strv(
+            "['dash-to-dock@micxgx.gmail.com', 'appindicatorsupport@rgcjonas.gmail.com']\n",
+        );
+        assert_eq!(set.len(), 2);
+        assert!(set.contains("dash-to-dock@micxgx.gmail.com"));
+        assert!(parse_gvariant_strv("@as []").is_empty());
+    }
+}
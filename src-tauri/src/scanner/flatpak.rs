@@ -5,6 +5,7 @@
 //! GUI-first; their `.desktop` ids equal the application id, which the desktop
 //! enrichment step matches exactly.
 
+use std::collections::HashSet;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -12,9 +13,17 @@ use anyhow::{Context, Result};
 
 use crate::package::{AppKind, InstallScope, InstalledPackage, PackageSource};
 use crate::scanner::Scanner;
-use crate::system::{capture_stdout, which, SCAN_TIMEOUT};
+use crate::system::{scan_timeout, CommandRunner, Runner};
 
-pub struct FlatpakScanner;
+pub struct FlatpakScanner {
+    runner: Runner,
+}
+
+impl FlatpakScanner {
+    pub fn new(runner: Runner) -> Self {
+        Self { runner }
+    }
+}
 
 impl Scanner for FlatpakScanner {
     fn source(&self) -> PackageSource {
@@ -22,17 +31,17 @@ impl Scanner for FlatpakScanner {
     }
 
     fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
-        Box::pin(async { which("flatpak") })
+        Box::pin(async { self.runner.has_program("flatpak") })
     }
 
     fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
-        Box::pin(scan())
+        Box::pin(scan(self.runner.as_ref()))
     }
 }
 
-async fn scan() -> Result<Vec<InstalledPackage>> {
-    let user = scan_scope(InstallScope::User).await;
-    let system = scan_scope(InstallScope::System).await;
+async fn scan(runner: &dyn CommandRunner) -> Result<Vec<InstalledPackage>> {
+    let user = scan_scope(runner, InstallScope::User).await;
+    let system = scan_scope(runner, InstallScope::System).await;
 
     match (user, system) {
         (Ok(mut user_packages), Ok(mut system_packages)) => {
@@ -46,7 +55,10 @@ async fn scan() -> Result<Vec<InstalledPackage>> {
     }
 }
 
-async fn scan_scope(scope: InstallScope) -> Result<Vec<InstalledPackage>> {
+async fn scan_scope(
+    runner: &dyn CommandRunner,
+    scope: InstallScope,
+) -> Result<Vec<InstalledPackage>> {
     // Tab-delimited column output (flatpak columns default to this separator
     // when redirected / non-tty).
     let columns = "application,name,version,origin,size,description";
@@ -54,14 +66,25 @@ async fn scan_scope(scope: InstallScope) -> Result<Vec<InstalledPackage>> {
         InstallScope::User => "--user",
         InstallScope::System => "--system",
     };
-    let output = capture_stdout(
-        "flatpak",
-        &["list", scope_arg, "--app", &format!("--columns={columns}")],
-        SCAN_TIMEOUT,
-    )
-    .await
-    .context(format!("flatpak list {scope_arg}"))?;
+    let output = runner
+        .capture_stdout(
+            "flatpak",
+            &["list", scope_arg, "--app", &format!("--columns={columns}")],
+            scan_timeout(),
+        )
+        .await
+        .context(format!("flatpak list {scope_arg}"))?;
 
+    let masked = masked_ids(runner, scope_arg).await;
+    Ok(parse_flatpak_list(&output, scope, &masked))
+}
+
+/// Parse tab-separated `flatpak list --columns=application,name,version,origin,size,description`.
+fn parse_flatpak_list(
+    output: &str,
+    scope: InstallScope,
+    masked: &HashSet<String>,
+) -> Vec<InstalledPackage> {
     let mut packages = Vec::new();
     for line in output.lines() {
         let parts: Vec<&str> = line.split('\t').collect();
@@ -91,53 +114,99 @@ async fn scan_scope(scope: InstallScope) -> Result<Vec<InstalledPackage>> {
             }
         }
         pkg.app_kind = AppKind::Gui;
+        pkg.held = masked.contains(&pkg.package_id);
         packages.push(pkg);
     }
-    // Check for updates after scanning each scope.
-    check_scope_updates(scope, &mut packages).await;
-    Ok(packages)
+    packages
+}
+
+/// Exact app ids masked with `flatpak mask` in one installation. Glob masks
+/// are ignored; they don't pin a single app.
+async fn masked_ids(runner: &dyn CommandRunner, scope_arg: &str) -> HashSet<String> {
+    runner
+        .capture_stdout("flatpak", &["mask", scope_arg], scan_timeout())
+        .await
+        .map(|out| {
+            out.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.contains('*'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
-/// Check for Flatpak updates for a given scope by running
+/// Check both installations for updates by running
 /// `flatpak remote-ls --updates` and matching against the scanned packages.
-async fn check_scope_updates(scope: InstallScope, packages: &mut Vec<InstalledPackage>) {
+pub async fn check_updates(
+    runner: &dyn CommandRunner,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
     // Refresh appstream metadata first (fast when fresh).
-    let _ = capture_stdout("flatpak", &["update", "--appstream"], SCAN_TIMEOUT).await;
+    let _ = runner
+        .capture_stdout("flatpak", &["update", "--appstream"], scan_timeout())
+        .await;
+    for scope in [InstallScope::User, InstallScope::System] {
+        check_scope_updates(runner, scope, packages).await?;
+    }
+    Ok(())
+}
 
+async fn check_scope_updates(
+    runner: &dyn CommandRunner,
+    scope: InstallScope,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
     let scope_flag = match scope {
         InstallScope::User => "--user",
         InstallScope::System => "--system",
     };
-    let output = match capture_stdout(
-        "flatpak",
-        &["remote-ls", "--updates", scope_flag, "--columns=application,version"],
-        SCAN_TIMEOUT,
-    )
-    .await
-    {
-        Ok(o) => o,
-        Err(_) => return,
-    };
+    let output = runner
+        .capture_stdout(
+            "flatpak",
+            &[
+                "remote-ls",
+                "--updates",
+                scope_flag,
+                "--columns=application,version",
+            ],
+            scan_timeout(),
+        )
+        .await?;
 
-    // Tab-delimited: application_id\tversion
-    for line in output.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.is_empty() {
-            continue;
-        }
-        let app_id = parts[0].to_string();
-        let new_version = parts.get(1).filter(|v| !v.is_empty()).map(|s| s.to_string());
-        if let Some(pkg) = packages.iter_mut().find(|p| p.package_id == app_id) {
+    for (app_id, new_version) in parse_remote_ls(&output) {
+        if let Some(pkg) = packages
+            .iter_mut()
+            .find(|p| p.package_id == app_id && p.install_scope == Some(scope))
+        {
             pkg.has_update = true;
             if let Some(ver) = new_version {
                 pkg.update_version = Some(ver);
             }
         }
     }
+    Ok(())
+}
+
+/// `(application id, new version)` pairs from tab-delimited
+/// `flatpak remote-ls --updates --columns=application,version`. Apps that
+/// don't declare a version have an empty second column.
+fn parse_remote_ls(output: &str) -> Vec<(String, Option<String>)> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut parts = line.split('\t');
+            let app_id = parts.next().unwrap_or_default().to_string();
+            let version = parts
+                .next()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string);
+            (app_id, version)
+        })
+        .collect()
 }
 
 /// Parse human sizes like "384.1 MB", "1.2 GB" into bytes.
@@ -158,3 +227,61 @@ fn parse_size(size_str: &str) -> u64 {
     };
     (number * multiplier as f64) as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{fixture_runner, Runner};
+
+    #[tokio::test]
+    async fn scans_recorded_flatpak_output() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+        let mut packages = FlatpakScanner::new(runner.clone()).scan().await.unwrap();
+        let keys: Vec<&str> = packages.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "flatpak:user:com.spotify.Client",
+                "flatpak:user:org.gimp.GIMP",
+                "flatpak:user:org.freedesktop.Piper",
+                "flatpak:system:org.gimp.GIMP",
+                "flatpak:system:org.mozilla.firefox",
+            ]
+        );
+        // Only the user installation masks GIMP; the `org.kde.*` glob is ignored.
+        let held: Vec<&str> = packages
+            .iter()
+            .filter(|p| p.held)
+            .map(|p| p.key.as_str())
+            .collect();
+        assert_eq!(held, ["flatpak:user:org.gimp.GIMP"]);
+        assert_eq!(packages[2].version, "");
+
+        check_updates(runner.as_ref(), &mut packages).await.unwrap();
+        let updated: Vec<(&str, Option<&str>)> = packages
+            .iter()
+            .filter(|p| p.has_update)
+            .map(|p| (p.key.as_str(), p.update_version.as_deref()))
+            .collect();
+        assert_eq!(
+            updated,
+            [
+                (
+                    "flatpak:user:com.spotify.Client",
+                    Some("1.2.50.335.g7a7fd2ce")
+                ),
+                ("flatpak:system:org.gimp.GIMP", Some("3.0.0")),
+                ("flatpak:system:org.mozilla.firefox", None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn parses_localized_sizes() {
+        let runner: Runner = fixture_runner("ubuntu-24.04-de");
+        let packages = FlatpakScanner::new(runner).scan().await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].size_bytes, parse_size("383.9 MB"));
+        assert!(packages[0].size_bytes > 0);
+    }
+}
@@ -1,13 +1,20 @@
 //! Package-source scanners.
 //!
-//! One module per supported source. Each scanner implements [`Scanner`] and is
-//! run in parallel by [`scan_all`]. The desktop-entry enrichment layer is
-//! applied afterwards in [`scan_all`] so all sources share one merge path.
+//! One module per supported source. Each scanner implements [`Scanner`]; the
+//! [`crate::backend`] registry says which scanners belong to which source.
+//! [`scan_all`] runs them all in parallel and then applies the desktop-entry
+//! enrichment layer so all sources share one merge path. [`scan_sources`] does
+//! the same for a subset, for incremental rescans. Scans only read local
+//! package databases; asking for updates is [`crate::updates`]' job.
 
 pub mod appimage;
 pub mod apt;
+pub mod container;
+pub mod extensions;
 pub mod flatpak;
+pub mod plugin;
 pub mod snap;
+pub mod steam;
 
 use std::future::Future;
 use std::path::PathBuf;
@@ -25,7 +32,7 @@ mod tests {
     /// CI without flatpak/snap can opt out.
     #[tokio::test]
     async fn scan_all_runs_on_live_system() {
-        let (pkgs, avail) = scan_all().await;
+        let (pkgs, avail) = scan_all(&crate::system::default_runner()).await;
         assert!(
             pkgs.iter().all(|p| !p.key.is_empty()),
             "every package needs a key"
@@ -58,27 +65,61 @@ mod tests {
         // Enrichment smoke: at least some packages should carry display names.
         let enriched = pkgs.iter().filter(|p| p.display_name.is_some()).count();
         println!(
-            "scan_all: {} packages, enriched={}, avail apt={}/snap={}/flatpak={}/appimage={}",
+            "scan_all: {} packages, enriched={}, avail apt={}/snap={}/flatpak={}/appimage={}/container={}/steam={}/extension={}",
             pkgs.len(),
             enriched,
             avail.apt,
             avail.snap,
             avail.flatpak,
-            avail.appimage
+            avail.appimage,
+            avail.container,
+            avail.steam,
+            avail.extension
         );
         let _ = AppKind::Gui;
     }
+
+    /// A dry run reads the filesystem sources from the fixture's `home/`.
+    #[tokio::test]
+    async fn dry_run_scans_the_fixture_home() {
+        let runner: crate::system::Runner = crate::system::fixture_runner("ubuntu-24.04");
+        let (pkgs, avail) = scan_all(&runner).await;
+        let found = |source, suffix: &str| {
+            pkgs.iter()
+                .any(|p| p.source == source && p.package_id.ends_with(suffix))
+        };
+        assert!(found(
+            PackageSource::AppImage,
+            "/home/Applications/Obsidian-1.6.7.AppImage"
+        ));
+        assert!(found(
+            PackageSource::Extension,
+            "/home/.local/share/gnome-shell/extensions/dash-to-dock@micxgx.gmail.com"
+        ));
+        assert!(found(PackageSource::Steam, "620"));
+        assert!(avail
+            .appimage_dirs
+            .iter()
+            .all(|d| d.contains("fixtures/commands")));
+    }
 }
 use tokio::task::JoinSet;
 
 use crate::desktop_entries::DesktopIndex;
 use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::system::Runner;
 
 /// A source-specific installed-package scanner.
 pub trait Scanner: Send + Sync {
     /// Human source this scanner reports for.
     fn source(&self) -> PackageSource;
 
+    /// Name used in availability reports. Only differs from the source id for
+    /// sources with several scanners (external plugins).
+    fn name(&self) -> String {
+        self.source().id().to_string()
+    }
+
     /// True when the backing package manager appears installed on this system.
     fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>>;
 
@@ -88,19 +129,10 @@ pub trait Scanner: Send + Sync {
 
 use std::pin::Pin;
 
-/// Build the full set of scanners (in a stable order for predictable results).
-fn scanners() -> Vec<Box<dyn Scanner>> {
-    vec![
-        Box::new(apt::AptScanner),
-        Box::new(snap::SnapScanner),
-        Box::new(flatpak::FlatpakScanner),
-        Box::new(appimage::AppImageScanner::new()),
-    ]
-}
-
 /// One scanner's outcome.
 struct ScanOutcome {
     source: PackageSource,
+    name: String,
     available: bool,
     packages: Vec<InstalledPackage>,
     error: Option<String>,
@@ -110,8 +142,22 @@ struct ScanOutcome {
 ///
 /// Returns the merged, sorted unified list plus per-source availability. Source
 /// failures are never fatal: a broken/uninstalled source simply contributes zero
-/// packages and reports `available = false`.
-pub async fn scan_all() -> (Vec<InstalledPackage>, ScanAvailability) {
+/// packages and reports `available = false`, as do sources disabled in the
+/// user's config.
+pub async fn scan_all(runner: &Runner) -> (Vec<InstalledPackage>, ScanAvailability) {
+    let sources: Vec<PackageSource> = crate::backend::registry()
+        .iter()
+        .map(|b| b.source())
+        .collect();
+    scan_sources(runner, &sources).await
+}
+
+/// [`scan_all`] restricted to `sources`. Availability fields of the other
+/// sources are left at their defaults; see [`ScanAvailability::update_from`].
+pub async fn scan_sources(
+    runner: &Runner,
+    sources: &[PackageSource],
+) -> (Vec<InstalledPackage>, ScanAvailability) {
     // Discover desktop apps on a blocking thread (synchronous fs walk).
     let desktop = tokio::task::spawn_blocking(|| {
         DesktopIndex::from_apps(crate::desktop_entries::discover_desktop_apps())
@@ -120,33 +166,14 @@ pub async fn scan_all() -> (Vec<InstalledPackage>, ScanAvailability) {
     .unwrap_or_else(|_| DesktopIndex::empty());
 
     let mut join = JoinSet::new();
-    for scanner in scanners() {
-        let source = scanner.source();
-        join.spawn(async move {
-            if scanner.is_available().await {
-                match scanner.scan().await {
-                    Ok(packages) => ScanOutcome {
-                        source,
-                        available: true,
-                        packages,
-                        error: None,
-                    },
-                    Err(e) => ScanOutcome {
-                        source,
-                        available: true,
-                        packages: Vec::new(),
-                        error: Some(e.to_string()),
-                    },
-                }
-            } else {
-                ScanOutcome {
-                    source,
-                    available: false,
-                    packages: Vec::new(),
-                    error: None,
-                }
-            }
-        });
+    let config = crate::config::current();
+    for backend in crate::backend::registry() {
+        if !sources.contains(&backend.source()) || !config.source_enabled(backend.source()) {
+            continue;
+        }
+        for scanner in backend.scanners(runner) {
+            spawn_scan(&mut join, scanner);
+        }
     }
 
     let mut merged: Vec<InstalledPackage> = Vec::new();
@@ -168,11 +195,31 @@ pub async fn scan_all() -> (Vec<InstalledPackage>, ScanAvailability) {
             }
             PackageSource::AppImage => {
                 availability.appimage = outcome.available;
-                availability.appimage_dirs = appimage::search_directories();
+                availability.appimage_dirs = appimage::search_directories(&runner.dirs());
+            }
+            PackageSource::Container => {
+                availability.container = outcome.available;
+                availability.container_error = outcome.error;
+            }
+            PackageSource::Steam => {
+                availability.steam = outcome.available;
+                availability.steam_error = outcome.error;
+            }
+            PackageSource::Extension => {
+                availability.extension = outcome.available;
+                availability.extension_error = outcome.error;
+            }
+            PackageSource::Plugin => {
+                availability.plugins.push(PluginStatus {
+                    name: outcome.name,
+                    available: outcome.available,
+                    error: outcome.error,
+                });
             }
         }
         merged.extend(outcome.packages);
     }
+    availability.plugins.sort_by(|a, b| a.name.cmp(&b.name));
 
     // Enrich + classify + resolve icons, then sort apps-first, by display
     // name. Icon resolution touches the filesystem (theme lookups), so the
@@ -181,16 +228,13 @@ pub async fn scan_all() -> (Vec<InstalledPackage>, ScanAvailability) {
     let (merged, availability) = tokio::task::spawn_blocking(move || {
         for pkg in merged.iter_mut() {
             enrich(pkg, &desktop);
+            if pkg.icon.is_none() {
+                if let Some(path) = crate::icons::resolve_bundled(pkg) {
+                    pkg.icon = Some(crate::icons::icon_url(&path));
+                }
+            }
         }
-        merged.sort_by(|a, b| {
-            let ka = kind_rank(a.app_kind);
-            let kb = kind_rank(b.app_kind);
-            ka.cmp(&kb).then_with(|| {
-                display_name(a)
-                    .to_lowercase()
-                    .cmp(&display_name(b).to_lowercase())
-            })
-        });
+        sort_packages(&mut merged);
         (merged, availability)
     })
     .await
@@ -199,6 +243,52 @@ pub async fn scan_all() -> (Vec<InstalledPackage>, ScanAvailability) {
     (merged, availability)
 }
 
+/// Run one scanner on the join set.
+fn spawn_scan(join: &mut JoinSet<ScanOutcome>, scanner: Box<dyn Scanner>) {
+    let source = scanner.source();
+    let name = scanner.name();
+    join.spawn(async move {
+        if !scanner.is_available().await {
+            return ScanOutcome {
+                source,
+                name,
+                available: false,
+                packages: Vec::new(),
+                error: None,
+            };
+        }
+        match scanner.scan().await {
+            Ok(packages) => ScanOutcome {
+                source,
+                name,
+                available: true,
+                packages,
+                error: None,
+            },
+            Err(e) => ScanOutcome {
+                source,
+                name,
+                available: true,
+                packages: Vec::new(),
+                error: Some(e.to_string()),
+            },
+        }
+    });
+}
+
+/// Unified list order: apps first, then by display name.
+pub fn sort_packages(packages: &mut [InstalledPackage]) {
+    packages.sort_by(|a, b| {
+        let ka = kind_rank(a.app_kind);
+        let kb = kind_rank(b.app_kind);
+        ka.cmp(&kb).then_with(|| {
+            display_name(a)
+                .to_lowercase()
+                .cmp(&display_name(b).to_lowercase())
+        })
+    });
+}
+
 fn kind_rank(k: AppKind) -> u8 {
     match k {
         AppKind::Gui => 0,
@@ -251,13 +341,74 @@ pub struct ScanAvailability {
     pub snap: bool,
     pub flatpak: bool,
     pub appimage: bool,
+    #[serde(default)]
+    pub container: bool,
+    #[serde(default)]
+    pub steam: bool,
+    #[serde(default)]
+    pub extension: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub apt_error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub snap_error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub flatpak_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub container_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub steam_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub extension_error: Option<String>,
     pub appimage_dirs: Vec<String>,
+    /// One entry per external plugin, sorted by name.
+    #[serde(default)]
+    pub plugins: Vec<PluginStatus>,
+}
+
+impl ScanAvailability {
+    /// Take `source`'s fields from `other`, the result of rescanning it.
+    pub fn update_from(&mut self, other: &ScanAvailability, source: PackageSource) {
+        match source {
+            PackageSource::Apt => {
+                self.apt = other.apt;
+                self.apt_error = other.apt_error.clone();
+            }
+            PackageSource::Snap => {
+                self.snap = other.snap;
+                self.snap_error = other.snap_error.clone();
+            }
+            PackageSource::Flatpak => {
+                self.flatpak = other.flatpak;
+                self.flatpak_error = other.flatpak_error.clone();
+            }
+            PackageSource::AppImage => {
+                self.appimage = other.appimage;
+                self.appimage_dirs = other.appimage_dirs.clone();
+            }
+            PackageSource::Container => {
+                self.container = other.container;
+                self.container_error = other.container_error.clone();
+            }
+            PackageSource::Steam => {
+                self.steam = other.steam;
+                self.steam_error = other.steam_error.clone();
+            }
+            PackageSource::Extension => {
+                self.extension = other.extension;
+                self.extension_error = other.extension_error.clone();
+            }
+            PackageSource::Plugin => self.plugins = other.plugins.clone(),
+        }
+    }
+}
+
+/// Health of one external scanner plugin.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PluginStatus {
+    pub name: String,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
 }
 
 #[allow(dead_code)]
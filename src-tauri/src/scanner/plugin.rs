@@ -0,0 +1,432 @@
+//! External scanner plugins.
+//!
+//! A plugin is an executable file in `~/.config/scope/plugins/` (or
+//! `$XDG_CONFIG_HOME/scope/plugins/`). Its file stem is the plugin name and
+//! must match `[A-Za-z0-9_-]+`. Files that are not executable, or that are
+//! group/world-writable, are ignored.
+//!
+//! # Protocol (version 1)
+//!
+//! Scope runs `<plugin> <command>` with a single JSON object on stdin and
+//! expects a single JSON object on stdout and exit code 0. Every request
+//! carries `"protocol": 1`. A plugin may answer any command with
+//! `{"error": "<message>"}` to report a readable failure.
+//!
+//! | command         | request fields                         | response                                                                  |
+//! |-----------------|----------------------------------------|---------------------------------------------------------------------------|
+//! | `scan`          | —                                      | `{"packages": [Package]}`                                                 |
+//! | `check-updates` | `"packages": [id]`                     | `{"updates": [{"id", "version"?}]}`                                       |
+//! | `preview`       | `"operation": "uninstall"\|"update"`, `"id"` | `{"steps": [{"description", "command_summary"}], "requires_auth"?, "protected"?, "protection_reason"?, "target_version"?}` |
+//! | `apply`         | `"operation"`, `"id"`                  | `{"success", "message", "logs"?}`                                         |
+//!
+//! `Package` is `{"id", "name", "display_name"?, "version"?, "description"?,
+//! "size_bytes"?, "app_kind"? ("gui"|"cli"|"unknown"), "icon"?, "categories"?}`.
+//! `icon` is an icon-theme name or an absolute path and is resolved like a
+//! `.desktop` `Icon=` value.
+//!
+//! Plugin packages use the package id `<plugin>:<id>` and go through the same
+//! preview → `PlanStore` → revalidate → apply flow as built-in sources; Scope
+//! only ever sends back ids the plugin itself reported.
+//!
+//! An optional `<plugin>.json` next to the executable sets a label and
+//! per-command timeouts in seconds:
+//! `{"label": "Homebrew", "timeouts": {"scan": 30, "check-updates": 60, "preview": 10, "apply": 600}}`.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::operations::{Operation, OperationResult, PlanStep};
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::Scanner;
+use crate::system::{CommandRunner, Dirs, Runner};
+
+/// Protocol version sent with every request.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Per-command timeouts, overridable per plugin in its `.json` manifest.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    pub scan: u64,
+    #[serde(rename = "check-updates")]
+    pub check_updates: u64,
+    pub preview: u64,
+    pub apply: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            scan: 30,
+            check_updates: 60,
+            preview: 10,
+            apply: 300,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    label: Option<String>,
+    #[serde(default)]
+    timeouts: Timeouts,
+}
+
+/// A discovered plugin executable.
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub name: String,
+    pub label: String,
+    pub path: PathBuf,
+    pub timeouts: Timeouts,
+}
+
+/// Directory scanned for plugin executables.
+pub fn plugins_dir(dirs: &Dirs) -> Option<PathBuf> {
+    dirs.config_home().map(|d| d.join("scope/plugins"))
+}
+
+/// Find all usable plugins, sorted by name.
+pub fn discover(dirs: &Dirs) -> Vec<Plugin> {
+    let Some(dir) = plugins_dir(dirs) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<Plugin> = entries
+        .flatten()
+        .filter_map(|e| load_plugin(&e.path()))
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins.dedup_by(|a, b| a.name == b.name);
+    plugins
+}
+
+/// Look up one plugin by name.
+pub fn find(name: &str, dirs: &Dirs) -> Option<Plugin> {
+    discover(dirs).into_iter().find(|p| p.name == name)
+}
+
+fn load_plugin(path: &Path) -> Option<Plugin> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let meta = std::fs::metadata(path).ok()?;
+    let mode = meta.permissions().mode();
+    // Executable by the owner, and not writable by anyone else.
+    if !meta.is_file() || mode & 0o100 == 0 || mode & 0o022 != 0 {
+        return None;
+    }
+    let name = path.file_stem()?.to_str()?.to_string();
+    if !is_valid_name(&name) {
+        return None;
+    }
+    let manifest: Manifest = std::fs::read_to_string(path.with_extension("json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    Some(Plugin {
+        label: manifest.label.unwrap_or_else(|| name.clone()),
+        name,
+        path: path.to_path_buf(),
+        timeouts: manifest.timeouts,
+    })
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Split a plugin package id `<plugin>:<id>` into its parts.
+pub fn split_package_id(package_id: &str) -> Option<(&str, &str)> {
+    let (plugin, id) = package_id.split_once(':')?;
+    (is_valid_name(plugin) && !id.is_empty()).then_some((plugin, id))
+}
+
+/// Adapter that exposes one plugin through the [`Scanner`] trait.
+pub struct PluginScanner {
+    runner: Runner,
+    plugin: Plugin,
+}
+
+impl PluginScanner {
+    pub fn new(runner: Runner, plugin: Plugin) -> Self {
+        Self { runner, plugin }
+    }
+}
+
+impl Scanner for PluginScanner {
+    fn source(&self) -> PackageSource {
+        PackageSource::Plugin
+    }
+
+    fn name(&self) -> String {
+        self.plugin.name.clone()
+    }
+
+    fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
+        Box::pin(async { load_plugin(&self.plugin.path).is_some() })
+    }
+
+    fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
+        Box::pin(scan(self.runner.as_ref(), &self.plugin))
+    }
+}
+
+#[derive(Deserialize)]
+struct ScanResponse {
+    packages: Vec<PluginPackage>,
+}
+
+#[derive(Deserialize)]
+struct PluginPackage {
+    id: String,
+    name: String,
+    display_name: Option<String>,
+    #[serde(default)]
+    version: String,
+    description: Option<String>,
+    #[serde(default)]
+    size_bytes: u64,
+    #[serde(default)]
+    app_kind: AppKind,
+    icon: Option<String>,
+    categories: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdatesResponse {
+    updates: Vec<PluginUpdate>,
+}
+
+#[derive(Deserialize)]
+struct PluginUpdate {
+    id: String,
+    version: Option<String>,
+}
+
+/// A plugin's answer to `preview`.
+#[derive(Debug, Deserialize)]
+pub struct PluginPreview {
+    pub steps: Vec<PlanStep>,
+    #[serde(default)]
+    pub requires_auth: bool,
+    #[serde(default)]
+    pub protected: bool,
+    pub protection_reason: Option<String>,
+    pub target_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApplyResponse {
+    success: bool,
+    message: String,
+    #[serde(default)]
+    logs: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Send one request and decode the response, surfacing `{"error": ..}`.
+async fn call<T: serde::de::DeserializeOwned>(
+    runner: &dyn CommandRunner,
+    plugin: &Plugin,
+    command: &str,
+    mut request: serde_json::Value,
+    timeout_secs: u64,
+) -> Result<T> {
+    request["protocol"] = json!(PROTOCOL_VERSION);
+    let input = serde_json::to_vec(&request)?;
+    let output = runner
+        .capture_stdout_with_input(
+            &plugin.path.to_string_lossy(),
+            &[command],
+            &input,
+            Duration::from_secs(timeout_secs),
+        )
+        .await
+        .with_context(|| format!("plugin '{}' {command}", plugin.name))?;
+    if let Ok(err) = serde_json::from_str::<ErrorResponse>(&output) {
+        anyhow::bail!("plugin '{}' {command}: {}", plugin.name, err.error);
+    }
+    serde_json::from_str(&output).with_context(|| {
+        format!(
+            "plugin '{}' returned invalid JSON for {command}",
+            plugin.name
+        )
+    })
+}
+
+async fn scan(runner: &dyn CommandRunner, plugin: &Plugin) -> Result<Vec<InstalledPackage>> {
+    let response: ScanResponse =
+        call(runner, plugin, "scan", json!({}), plugin.timeouts.scan).await?;
+
+    let packages: Vec<InstalledPackage> = response
+        .packages
+        .into_iter()
+        .filter(|p| !p.id.is_empty())
+        .map(|p| {
+            let mut pkg =
+                InstalledPackage::new(PackageSource::Plugin, format!("{}:{}", plugin.name, p.id));
+            pkg.name = p.name;
+            pkg.display_name = p.display_name;
+            pkg.version = p.version;
+            pkg.description = Some(match p.description {
+                Some(d) => format!("{d}  (via {})", plugin.label),
+                None => format!("Reported by the {} plugin", plugin.label),
+            });
+            pkg.size_bytes = p.size_bytes;
+            pkg.app_kind = p.app_kind;
+            pkg.categories = p.categories;
+            pkg.icon = p
+                .icon
+                .as_deref()
+                .and_then(crate::icons::resolve)
+                .map(|path| crate::icons::icon_url(&path));
+            pkg
+        })
+        .collect();
+    Ok(packages)
+}
+
+/// Ask each plugin about updates for the packages it reported. Update checks
+/// are optional: a plugin without them just reports none.
+pub async fn check_updates(
+    runner: &dyn CommandRunner,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
+    for plugin in discover(&runner.dirs()) {
+        let prefix = format!("{}:", plugin.name);
+        let ids: Vec<&str> = packages
+            .iter()
+            .filter_map(|p| p.package_id.strip_prefix(&prefix))
+            .collect();
+        if ids.is_empty() {
+            continue;
+        }
+        let updates = call::<UpdatesResponse>(
+            runner,
+            &plugin,
+            "check-updates",
+            json!({ "packages": ids }),
+            plugin.timeouts.check_updates,
+        )
+        .await
+        .with_context(|| format!("plugin '{}'", plugin.name))?;
+        for update in updates.updates {
+            let key_id = format!("{prefix}{}", update.id);
+            if let Some(pkg) = packages.iter_mut().find(|p| p.package_id == key_id) {
+                pkg.has_update = true;
+                pkg.update_version = update.version;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ask the owning plugin for an operation plan for one of its packages.
+pub async fn preview(
+    runner: &dyn CommandRunner,
+    package_id: &str,
+    operation: Operation,
+) -> Result<PluginPreview> {
+    let (name, id) = split_package_id(package_id)
+        .ok_or_else(|| anyhow::anyhow!("invalid plugin package id '{package_id}'"))?;
+    let plugin = find(name, &runner.dirs())
+        .ok_or_else(|| anyhow::anyhow!("plugin '{name}' is not installed"))?;
+    call(
+        runner,
+        &plugin,
+        "preview",
+        json!({ "operation": operation.id(), "id": id }),
+        plugin.timeouts.preview,
+    )
+    .await
+}
+
+/// Ask the owning plugin to carry out an operation it previously previewed.
+pub async fn apply(
+    runner: &dyn CommandRunner,
+    package_id: &str,
+    operation: Operation,
+) -> OperationResult {
+    let failed = |message: String| OperationResult {
+        success: false,
+        message,
+        logs: String::new(),
+        exit_code: None,
+    };
+    let Some((name, id)) = split_package_id(package_id) else {
+        return failed(format!("Invalid plugin package id '{package_id}'."));
+    };
+    let Some(plugin) = find(name, &runner.dirs()) else {
+        return failed(format!("Plugin '{name}' is no longer installed."));
+    };
+    let started = std::time::Instant::now();
+    match call::<ApplyResponse>(
+        runner,
+        &plugin,
+        "apply",
+        json!({ "operation": operation.id(), "id": id }),
+        plugin.timeouts.apply,
+    )
+    .await
+    {
+        Ok(res) => OperationResult {
+            success: res.success,
+            message: res.message,
+            logs: format!(
+                "{}\n[scope] plugin {} apply {} ({}ms)",
+                res.logs,
+                plugin.name,
+                id,
+                started.elapsed().as_millis()
+            ),
+            exit_code: Some(if res.success { 0 } else { 1 }),
+        },
+        Err(e) => failed(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_plugin_package_ids() {
+        assert_eq!(split_package_id("brew:wget"), Some(("brew", "wget")));
+        assert_eq!(
+            split_package_id("npm:@scope/pkg:1"),
+            Some(("npm", "@scope/pkg:1"))
+        );
+        assert_eq!(split_package_id("../x:wget"), None);
+        assert_eq!(split_package_id("brew:"), None);
+    }
+
+    #[test]
+    fn decodes_scan_and_preview_responses() {
+        let scan: ScanResponse = serde_json::from_str(
+            r#"{"packages":[{"id":"wget","name":"wget","version":"1.24","app_kind":"cli"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(scan.packages[0].app_kind, AppKind::Cli);
+        let preview: PluginPreview = serde_json::from_str(
+            r#"{"steps":[{"description":"Remove wget","command_summary":"brew uninstall wget"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(preview.steps.len(), 1);
+        assert!(!preview.protected);
+    }
+}
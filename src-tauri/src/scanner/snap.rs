@@ -12,9 +12,17 @@ use anyhow::{Context, Result};
 
 use crate::package::{AppKind, InstalledPackage, PackageSource};
 use crate::scanner::Scanner;
-use crate::system::{capture_stdout, which, SCAN_TIMEOUT};
+use crate::system::{scan_timeout, CommandRunner, Runner};
 
-pub struct SnapScanner;
+pub struct SnapScanner {
+    runner: Runner,
+}
+
+impl SnapScanner {
+    pub fn new(runner: Runner) -> Self {
+        Self { runner }
+    }
+}
 
 impl Scanner for SnapScanner {
     fn source(&self) -> PackageSource {
@@ -22,20 +30,39 @@ impl Scanner for SnapScanner {
     }
 
     fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
-        Box::pin(async { which("snap") && Path::new("/var/lib/snapd").exists() })
+        Box::pin(async {
+            self.runner.has_program("snap")
+                && (self.runner.is_dry_run() || Path::new("/var/lib/snapd").exists())
+        })
     }
 
     fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
-        Box::pin(scan())
+        Box::pin(scan(self.runner.as_ref()))
     }
 }
 
-async fn scan() -> Result<Vec<InstalledPackage>> {
-    // Whitespace columns: Name Version Rev Tracking Publisher Notes
-    let output = capture_stdout("snap", &["list"], SCAN_TIMEOUT)
+async fn scan(runner: &dyn CommandRunner) -> Result<Vec<InstalledPackage>> {
+    let output = runner
+        .capture_stdout("snap", &["list"], scan_timeout())
         .await
         .context("snap list")?;
 
+    let mut packages = parse_snap_list(&output);
+    for pkg in packages.iter_mut() {
+        pkg.size_bytes = snap_size(runner, &pkg.package_id).await;
+        pkg.app_kind = if has_snap_command(&pkg.package_id) {
+            AppKind::Cli
+        } else {
+            AppKind::Unknown
+        };
+    }
+    Ok(packages)
+}
+
+/// Parse `snap list`, skipping runtime snaps. The header row is translated,
+/// so it is skipped by position rather than matched.
+fn parse_snap_list(output: &str) -> Vec<InstalledPackage> {
+    // Whitespace columns: Name Version Rev Tracking Publisher Notes
     let mut packages = Vec::new();
     for line in output.lines().skip(1) {
         let parts: Vec<&str> = line.split_whitespace().collect();
@@ -52,46 +79,46 @@ async fn scan() -> Result<Vec<InstalledPackage>> {
         let mut pkg = InstalledPackage::new(PackageSource::Snap, name.clone());
         pkg.name = name;
         pkg.version = version;
-        pkg.size_bytes = snap_size(&pkg.package_id).await;
-        pkg.app_kind = if has_snap_command(&pkg.package_id) {
-            AppKind::Cli
-        } else {
-            AppKind::Unknown
-        };
         if notes.contains("classic") {
             // Keep classic snaps; command/desktop metadata still drives classification.
         }
+        // `snap refresh --hold` shows up as a `held` note.
+        pkg.held = notes.split(',').any(|n| n == "held");
         packages.push(pkg);
     }
-    check_updates(&mut packages).await;
-    Ok(packages)
+    packages
 }
 
 /// Run `snap refresh --list` and mark snaps that have available updates.
 /// Note: the list shows current version, not the target, so we set
 /// `has_update = true` without a specific target version for v1.
-async fn check_updates(packages: &mut Vec<InstalledPackage>) {
-    let output = match capture_stdout("snap", &["refresh", "--list"], SCAN_TIMEOUT).await {
-        Ok(o) => o,
-        Err(_) => return,
-    };
-
-    for line in output.lines().skip(1) {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 4 {
-            continue;
-        }
-        let name = parts[0].to_string();
+pub async fn check_updates(
+    runner: &dyn CommandRunner,
+    packages: &mut [InstalledPackage],
+) -> Result<()> {
+    let output = runner
+        .capture_stdout("snap", &["refresh", "--list"], scan_timeout())
+        .await?;
+    for name in parse_refresh_list(&output) {
         if let Some(pkg) = packages.iter_mut().find(|p| p.package_id == name) {
             pkg.has_update = true;
             // The Version column in refresh --list is the current version, not
             // the target. We mark the update as available without a target version.
         }
     }
+    Ok(())
+}
+
+/// Snap names listed by `snap refresh --list`. With nothing to refresh snapd
+/// prints "All snaps up to date." to stderr and stdout stays empty.
+fn parse_refresh_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|parts| parts.len() >= 4)
+        .map(|parts| parts[0].to_string())
+        .collect()
 }
 
 fn is_runtime(name: &str) -> bool {
@@ -108,25 +135,67 @@ fn has_snap_command(name: &str) -> bool {
 }
 
 /// Bytes used by a snap under /snap/<name>/current. -L follows the symlink.
-async fn snap_size(name: &str) -> u64 {
+async fn snap_size(runner: &dyn CommandRunner, name: &str) -> u64 {
     let path = format!("/snap/{name}/current");
     if !Path::new(&path).exists() {
         return 0;
     }
-    let args: [&str; 2] = ["-sbL", &path];
-    let Ok(out) = tokio::time::timeout(
-        std::time::Duration::from_secs(5),
-        tokio::process::Command::new("du").args(args).output(),
-    )
-    .await
+    // `du` exits non-zero on unreadable subdirectories but still prints a total.
+    let Ok(out) = runner
+        .run(
+            "du",
+            &["-sbL", &path],
+            None,
+            std::time::Duration::from_secs(5),
+        )
+        .await
     else {
         return 0;
     };
-    let Ok(out) = out else { return 0 };
-    let stdout = String::from_utf8_lossy(&out.stdout);
-    stdout
+    out.stdout
         .split_whitespace()
         .next()
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{fixture_runner, Runner};
+
+    #[tokio::test]
+    async fn scans_recorded_snap_output() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+        let mut packages = SnapScanner::new(runner.clone()).scan().await.unwrap();
+        let names: Vec<&str> = packages.iter().map(|p| p.package_id.as_str()).collect();
+        assert_eq!(
+            names,
+            ["code", "firefox", "snap-store", "spotify", "thunderbird"]
+        );
+        let held: Vec<&str> = packages
+            .iter()
+            .filter(|p| p.held)
+            .map(|p| p.package_id.as_str())
+            .collect();
+        assert_eq!(held, ["firefox", "spotify"]);
+
+        check_updates(runner.as_ref(), &mut packages).await.unwrap();
+        let updated: Vec<&str> = packages
+            .iter()
+            .filter(|p| p.has_update)
+            .map(|p| p.package_id.as_str())
+            .collect();
+        assert_eq!(updated, ["firefox", "thunderbird"]);
+    }
+
+    #[tokio::test]
+    async fn localized_header_and_empty_refresh_list() {
+        let runner: Runner = fixture_runner("ubuntu-24.04-de");
+        let mut packages = SnapScanner::new(runner.clone()).scan().await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].version, "128.3.1esr-1");
+        check_updates(runner.as_ref(), &mut packages).await.unwrap();
+        assert!(!packages[0].has_update);
+    }
+}
@@ -0,0 +1,289 @@
+//! Steam game library scanner.
+//!
+//! Strategy: find every Steam installation (native and Flatpak), read its
+//! `steamapps/libraryfolders.vdf` to learn all library folders, then parse each
+//! library's `appmanifest_*.acf` for name, install dir, build id and size.
+//! Proton and the Steam Linux Runtimes are regular app manifests too and are
+//! reported as compatibility tools; custom tools (e.g. GE-Proton) dropped into
+//! `compatibilitytools.d/` are listed from their `compatibilitytool.vdf`.
+//!
+//! Steam owns its library, so Scope never deletes game files itself: the
+//! uninstall plan hands off to `steam://uninstall/<appid>`.
+
+mod vdf;
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use anyhow::Result;
+use walkdir::WalkDir;
+
+use crate::package::{AppKind, InstalledPackage, PackageSource};
+use crate::scanner::Scanner;
+use crate::system::Dirs;
+
+pub struct SteamScanner {
+    dirs: Dirs,
+}
+
+impl SteamScanner {
+    pub fn new(dirs: Dirs) -> Self {
+        Self { dirs }
+    }
+}
+
+impl Scanner for SteamScanner {
+    fn source(&self) -> PackageSource {
+        PackageSource::Steam
+    }
+
+    fn is_available(&self) -> Pin<Box<dyn Future<Output = bool> + Send + '_>> {
+        Box::pin(async { is_available(&self.dirs) })
+    }
+
+    fn scan(&self) -> Pin<Box<dyn Future<Output = Result<Vec<InstalledPackage>>> + Send + '_>> {
+        // Pure file parsing plus directory walks for custom tool sizes.
+        let dirs = self.dirs.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || scan(&dirs))
+                .await
+                .map_err(|e| anyhow::anyhow!("steam scan task failed: {e}"))
+        })
+    }
+}
+
+/// Cheap probe: at least one Steam installation exists.
+pub fn is_available(dirs: &Dirs) -> bool {
+    !installations(dirs).is_empty()
+}
+
+/// A Steam client installation.
+#[derive(Debug, Clone)]
+struct Installation {
+    root: PathBuf,
+    flatpak: bool,
+}
+
+/// Candidate Steam roots, deduplicated by canonical path (`~/.steam/steam` is
+/// usually a symlink to `~/.local/share/Steam`).
+fn installations(dirs: &Dirs) -> Vec<Installation> {
+    let Some(home) = dirs.home() else {
+        return Vec::new();
+    };
+    let candidates = [
+        (home.join(".local/share/Steam"), false),
+        (home.join(".steam/steam"), false),
+        (home.join(".steam/debian-installation"), false),
+        (
+            home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            true,
+        ),
+    ];
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for (root, flatpak) in candidates {
+        let Ok(canonical) = root.canonicalize() else {
+            continue;
+        };
+        if !canonical.join("steamapps").is_dir() || !seen.insert(canonical.clone()) {
+            continue;
+        }
+        out.push(Installation {
+            root: canonical,
+            flatpak,
+        });
+    }
+    out
+}
+
+/// Well-known app ids of Steam runtimes/redistributables other games need.
+/// Exposed so the safety layer can protect them.
+pub const RUNTIME_APP_IDS: &[&str] = &[
+    "228980",  // Steamworks Common Redistributables
+    "1070560", // Steam Linux Runtime 1.0 (scout)
+    "1391110", // Steam Linux Runtime 2.0 (soldier)
+    "1628350", // Steam Linux Runtime 3.0 (sniper)
+];
+
+fn scan(dirs: &Dirs) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    let mut seen_apps = HashSet::new();
+    for install in installations(dirs) {
+        for library in library_folders(&install.root) {
+            for manifest in manifests(&library) {
+                let Some(pkg) = build_app(&install, &library, &manifest) else {
+                    continue;
+                };
+                // The same library can be registered by native and Flatpak Steam.
+                if seen_apps.insert(pkg.package_id.clone()) {
+                    packages.push(pkg);
+                }
+            }
+        }
+        packages.extend(custom_compat_tools(&install));
+    }
+    packages
+}
+
+/// Library folders declared in `libraryfolders.vdf`, always including the
+/// installation's own `steamapps` parent.
+fn library_folders(root: &Path) -> Vec<PathBuf> {
+    let mut out = vec![root.to_path_buf()];
+    let file = root.join("steamapps/libraryfolders.vdf");
+    let Ok(content) = std::fs::read_to_string(&file) else {
+        return out;
+    };
+    let Ok(doc) = vdf::parse(&content) else {
+        return out;
+    };
+    let Some(folders) = doc.get("libraryfolders") else {
+        return out;
+    };
+    for (_, folder) in folders.entries() {
+        // Old format: `"1" "/path"`; current format: `"1" { "path" "/path" }`.
+        let path = folder.as_str().or_else(|| folder.get_str("path"));
+        if let Some(path) = path {
+            let path = PathBuf::from(path);
+            let canonical = path.canonicalize().unwrap_or(path);
+            if !out.contains(&canonical) {
+                out.push(canonical);
+            }
+        }
+    }
+    out
+}
+
+fn manifests(library: &Path) -> Vec<PathBuf> {
+    let pattern = library.join("steamapps/appmanifest_*.acf");
+    let Some(pattern) = pattern.to_str() else {
+        return Vec::new();
+    };
+    glob::glob(pattern)
+        .map(|paths| paths.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+fn build_app(install: &Installation, library: &Path, manifest: &Path) -> Option<InstalledPackage> {
+    let content = std::fs::read_to_string(manifest).ok()?;
+    let doc = vdf::parse(&content).ok()?;
+    let state = doc.get("AppState")?;
+    let appid = state.get_str("appid")?.to_string();
+    let name = state.get_str("name").unwrap_or(&appid).to_string();
+    let install_dir = library
+        .join("steamapps/common")
+        .join(state.get_str("installdir").unwrap_or(""));
+
+    let mut pkg = InstalledPackage::new(PackageSource::Steam, appid.clone());
+    pkg.display_name = Some(name.clone());
+    pkg.name = name.clone();
+    pkg.version = state.get_str("buildid").unwrap_or("").to_string();
+    pkg.size_bytes = state
+        .get_str("SizeOnDisk")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    let client = if install.flatpak {
+        "Flatpak Steam"
+    } else {
+        "Steam"
+    };
+    if is_compat_tool(&appid, &name) {
+        pkg.app_kind = AppKind::Unknown;
+        pkg.categories = Some("Compatibility tool".into());
+        pkg.description = Some(format!(
+            "Compatibility tool installed by {client} in {}",
+            install_dir.display()
+        ));
+    } else {
+        pkg.app_kind = AppKind::Gui;
+        pkg.categories = Some("Game".into());
+        pkg.description = Some(format!(
+            "Installed by {client} in {}",
+            install_dir.display()
+        ));
+    }
+    if let Some(icon) = library_icon(&install.root, &appid) {
+        pkg.icon = Some(crate::icons::icon_url(&icon));
+    }
+    Some(pkg)
+}
+
+fn is_compat_tool(appid: &str, name: &str) -> bool {
+    RUNTIME_APP_IDS.contains(&appid)
+        || name.starts_with("Proton")
+        || name.starts_with("Steam Linux Runtime")
+}
+
+/// Steam's cached library icon for an app, in either the legacy flat layout
+/// (`<appid>_icon.jpg`) or the per-app directory layout.
+fn library_icon(root: &Path, appid: &str) -> Option<PathBuf> {
+    let cache = root.join("appcache/librarycache");
+    let flat = cache.join(format!("{appid}_icon.jpg"));
+    if flat.is_file() {
+        return Some(flat);
+    }
+    let nested = cache.join(appid);
+    let entries = std::fs::read_dir(nested).ok()?;
+    entries.flatten().map(|e| e.path()).find(|p| {
+        let is_jpg = p.extension().and_then(|e| e.to_str()) == Some("jpg");
+        let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        // Hash-named files are icons; the rest are artwork (header, logo...).
+        is_jpg && stem.len() == 40 && stem.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// User-installed tools under `compatibilitytools.d/`. They are keyed by
+/// their absolute directory, which the uninstall plan shows as a manual step.
+fn custom_compat_tools(install: &Installation) -> Vec<InstalledPackage> {
+    let dir = install.root.join("compatibilitytools.d");
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(content) = std::fs::read_to_string(path.join("compatibilitytool.vdf")) else {
+            continue;
+        };
+        let display = vdf::parse(&content)
+            .ok()
+            .and_then(|doc| {
+                let tools = doc.get("compatibilitytools")?.get("compat_tools")?;
+                let (internal, tool) = tools.entries().first()?;
+                Some(
+                    tool.get_str("display_name")
+                        .unwrap_or(internal.as_str())
+                        .to_string(),
+                )
+            })
+            .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
+
+        let mut pkg =
+            InstalledPackage::new(PackageSource::Steam, path.to_string_lossy().to_string());
+        pkg.name = display.clone();
+        pkg.display_name = Some(display);
+        pkg.size_bytes = dir_size(&path);
+        pkg.app_kind = AppKind::Unknown;
+        pkg.categories = Some("Compatibility tool".into());
+        pkg.description = Some(format!("Custom compatibility tool in {}", path.display()));
+        out.push(pkg);
+    }
+    out
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// True for Steam package ids that name a custom tool directory rather than
+/// an app id.
+pub fn is_custom_tool(package_id: &str) -> bool {
+    package_id.starts_with('/')
+}
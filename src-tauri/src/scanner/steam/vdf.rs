@@ -0,0 +1,241 @@
+//! Minimal parser for Valve's text KeyValues format (`.vdf` / `.acf`).
+//!
+//! Supports quoted and bare tokens, `\"`/`\\`/`\n`/`\t` escapes, `//` line
+//! comments, and nested `{ }` objects. Platform conditionals such as
+//! `[$WIN32]` are skipped. Key lookups are case-insensitive because Steam is
+//! inconsistent about casing (`SizeOnDisk` vs `sizeondisk`).
+
+use anyhow::{bail, Result};
+
+/// A KeyValues node: either a string leaf or an ordered list of children.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Obj(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Case-insensitive child lookup on an object; `None` for strings.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Obj(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Value::Str(_) => None,
+        }
+    }
+
+    /// Shorthand for `get(key)` followed by [`Value::as_str`].
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            Value::Obj(_) => None,
+        }
+    }
+
+    /// Children of an object in file order (empty for strings).
+    pub fn entries(&self) -> &[(String, Value)] {
+        match self {
+            Value::Obj(entries) => entries,
+            Value::Str(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+/// Parse a KeyValues document. The result is an object holding the top-level
+/// key(s), e.g. `"libraryfolders" { ... }` → `Obj([("libraryfolders", ..)])`.
+pub fn parse(input: &str) -> Result<Value> {
+    let tokens = tokenize(input)?;
+    let mut pos = 0;
+    let entries = parse_entries(&tokens, &mut pos, false)?;
+    Ok(Value::Obj(entries))
+}
+
+fn parse_entries(tokens: &[Token], pos: &mut usize, nested: bool) -> Result<Vec<(String, Value)>> {
+    let mut entries = Vec::new();
+    while *pos < tokens.len() {
+        let key = match &tokens[*pos] {
+            Token::Close if nested => {
+                *pos += 1;
+                return Ok(entries);
+            }
+            Token::Close => bail!("unexpected '}}' at top level"),
+            Token::Open => bail!("expected a key, found '{{'"),
+            Token::Str(s) => s.clone(),
+        };
+        *pos += 1;
+        let value = match tokens.get(*pos) {
+            Some(Token::Str(s)) => {
+                *pos += 1;
+                Value::Str(s.clone())
+            }
+            Some(Token::Open) => {
+                *pos += 1;
+                Value::Obj(parse_entries(tokens, pos, true)?)
+            }
+            Some(Token::Close) | None => bail!("key '{key}' has no value"),
+        };
+        entries.push((key, value));
+    }
+    if nested {
+        bail!("unterminated '{{' block");
+    }
+    Ok(entries)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '{' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            '}' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '/' => {
+                chars.next();
+                if chars.peek() != Some(&'/') {
+                    bail!("stray '/' outside a string");
+                }
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                // Platform conditional like `[$WIN32]`: ignored.
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(other) => s.push(other),
+                            None => break,
+                        },
+                        other => s.push(other),
+                    }
+                }
+                if !closed {
+                    bail!("unterminated string");
+                }
+                tokens.push(Token::Str(s));
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Str(s));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_libraryfolders() {
+        let doc = parse(
+            r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/u/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"228980"		"29212173"
+			"620"		"12790715234"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+	}
+}
+"#,
+        )
+        .unwrap();
+        let folders = doc.get("libraryfolders").unwrap();
+        assert_eq!(folders.entries().len(), 2);
+        assert_eq!(
+            folders.get("1").unwrap().get_str("path"),
+            Some("/mnt/games/SteamLibrary")
+        );
+        let apps = folders.get("0").unwrap().get("apps").unwrap();
+        assert_eq!(apps.get_str("620"), Some("12790715234"));
+    }
+
+    #[test]
+    fn parses_appmanifest_case_insensitively() {
+        let doc = parse(
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"620\"\n\t\"name\"\t\t\"Portal 2\"\n\
+             \t\"SizeOnDisk\"\t\t\"12790715234\"\n}\n",
+        )
+        .unwrap();
+        let state = doc.get("appstate").unwrap();
+        assert_eq!(state.get_str("name"), Some("Portal 2"));
+        assert_eq!(state.get_str("sizeondisk"), Some("12790715234"));
+    }
+
+    #[test]
+    fn handles_escapes_comments_and_conditionals() {
+        let doc = parse(
+            "// header comment\n\"root\" {\n  \"a\\\"b\" \"c\\\\d\" // trailing\n  bare value [$LINUX]\n}\n",
+        )
+        .unwrap();
+        let root = doc.get("root").unwrap();
+        assert_eq!(root.get_str("a\"b"), Some("c\\d"));
+        assert_eq!(root.get_str("bare"), Some("value"));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse("\"root\" { \"a\" \"b\"").is_err());
+        assert!(parse("\"root\" { \"a\" }").is_err());
+        assert!(parse("\"unterminated").is_err());
+        assert!(parse("}").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Where the filesystem sources (AppImages, Steam, extensions, container
+//! exports, plugins) are found.
+//!
+//! On the real system that is the home directory, the XDG base directories
+//! and a few system directories. A dry-run fixture directory mirrors the same
+//! layout: `home/` is the home directory and every other absolute directory
+//! is looked up below the fixture directory itself, so
+//! `<fixture>/home/Applications/Foo.AppImage` is found like
+//! `~/Applications/Foo.AppImage` and `<fixture>/opt/Bar.AppImage` like
+//! `/opt/Bar.AppImage`. XDG variables never apply to a fixture.
+
+use std::path::{Path, PathBuf};
+
+/// The directories a [`super::CommandRunner`]'s system reads sources from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dirs {
+    /// The real home, XDG and system directories.
+    Host,
+    /// The ones mirrored in a fixture directory; none for an empty fake system.
+    Fixture(Option<PathBuf>),
+}
+
+impl Dirs {
+    /// The home directory.
+    pub fn home(&self) -> Option<PathBuf> {
+        match self {
+            Self::Host => std::env::var_os("HOME").map(PathBuf::from),
+            Self::Fixture(dir) => dir.as_ref().map(|d| d.join("home")),
+        }
+    }
+
+    /// `$XDG_DATA_HOME`, or `~/.local/share`.
+    pub fn data_home(&self) -> Option<PathBuf> {
+        self.xdg("XDG_DATA_HOME")
+            .or_else(|| self.home().map(|h| h.join(".local/share")))
+    }
+
+    /// `$XDG_CONFIG_HOME`, or `~/.config`.
+    pub fn config_home(&self) -> Option<PathBuf> {
+        self.xdg("XDG_CONFIG_HOME")
+            .or_else(|| self.home().map(|h| h.join(".config")))
+    }
+
+    /// The absolute directory `path` of this system.
+    pub fn system(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            Self::Host => Some(path.to_path_buf()),
+            Self::Fixture(dir) => Some(dir.as_ref()?.join(path.strip_prefix("/").unwrap_or(path))),
+        }
+    }
+
+    fn xdg(&self, name: &str) -> Option<PathBuf> {
+        match self {
+            Self::Host => std::env::var_os(name).map(PathBuf::from),
+            Self::Fixture(_) => None,
+        }
+    }
+}
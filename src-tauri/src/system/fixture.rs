@@ -0,0 +1,272 @@
+//! Replays recorded command output instead of running anything.
+//!
+//! A fixture directory holds a `commands.json` list of
+//! `{"argv": [..], "stdout_file"?, "stdout"?, "stderr"?, "exit"?}` entries
+//! plus the referenced output files. An entry matches a call when its argv is
+//! a prefix of the call's argv (programs are compared by file name, so
+//! `/usr/bin/apt` matches `apt`); the longest matching entry wins. Scan calls
+//! with no matching entry fail like a missing binary would.
+//!
+//! Apply steps are never executed: their argv is recorded, and a
+//! `pkexec scope-helper ...` call is matched as the command the helper would
+//! run, so `["apt", "remove"]` scripts a result for every privileged
+//! `apt remove`. Unscripted apply steps succeed.
+//!
+//! The filesystem sources read the directories mirrored in the fixture
+//! directory instead of the real ones (see [`super::Dirs`]): `home/` is the
+//! home directory.
+//!
+//! Setting [`DRY_RUN_ENV`] to a fixture directory makes the whole app run
+//! against it (see [`super::default_runner`]). The recorded corpus lives in
+//! `src-tauri/fixtures/commands/<system>/`.
+
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::runner::{CommandOutput, CommandRunner, RunError};
+use super::{BoxFuture, Dirs};
+use crate::helper::Request;
+
+/// Environment variable naming a fixture directory to run Scope against.
+pub const DRY_RUN_ENV: &str = "SCOPE_DRY_RUN";
+
+#[derive(Deserialize)]
+struct FixtureEntry {
+    argv: Vec<String>,
+    #[serde(default)]
+    stdout: Option<String>,
+    #[serde(default)]
+    stdout_file: Option<String>,
+    #[serde(default)]
+    stderr: String,
+    #[serde(default)]
+    exit: i32,
+}
+
+/// Replays recorded command outputs from a fixture directory.
+pub struct FixtureRunner {
+    dir: Option<PathBuf>,
+    entries: Vec<(Vec<String>, CommandOutput)>,
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+impl FixtureRunner {
+    /// Load `<dir>/commands.json` and the output files it references.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let manifest = dir.join("commands.json");
+        let content = std::fs::read_to_string(&manifest)
+            .with_context(|| format!("read {}", manifest.display()))?;
+        let raw: Vec<FixtureEntry> = serde_json::from_str(&content)
+            .with_context(|| format!("parse {}", manifest.display()))?;
+        let mut entries = Vec::with_capacity(raw.len());
+        for entry in raw {
+            let stdout = match (entry.stdout, entry.stdout_file) {
+                (Some(s), _) => s,
+                (None, Some(file)) => std::fs::read_to_string(dir.join(&file))
+                    .with_context(|| format!("read fixture {file}"))?,
+                (None, None) => String::new(),
+            };
+            entries.push((
+                entry.argv,
+                CommandOutput {
+                    code: Some(entry.exit),
+                    stdout,
+                    stderr: entry.stderr,
+                },
+            ));
+        }
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            entries,
+            calls: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// A fake system where every scan command fails.
+    pub fn empty() -> Self {
+        Self {
+            dir: None,
+            entries: Vec::new(),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every argv run so far, in order, with the program as given.
+    #[cfg(test)]
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    fn lookup(&self, argv: &[String]) -> Option<&CommandOutput> {
+        self.entries
+            .iter()
+            .filter(|(pattern, _)| {
+                pattern.len() <= argv.len()
+                    && pattern
+                        .iter()
+                        .zip(argv)
+                        .enumerate()
+                        .all(|(i, (p, a))| p == a || (i == 0 && program_name(a) == p))
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, out)| out)
+    }
+
+    fn record(&self, argv: &[String]) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(argv.to_vec());
+        }
+    }
+}
+
+fn argv(program: &str, args: &[&str]) -> Vec<String> {
+    std::iter::once(program)
+        .chain(args.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The command a `pkexec scope-helper ...` call runs as root; any other argv
+/// as is.
+fn unelevated(argv: &[String]) -> Vec<String> {
+    let command = match argv {
+        [pkexec, helper, args @ ..]
+            if program_name(pkexec) == "pkexec" && program_name(helper) == "scope-helper" =>
+        {
+            Request::parse(args)
+                .and_then(|request| request.command())
+                .ok()
+        }
+        _ => None,
+    };
+    match command {
+        Some((program, args)) => std::iter::once(program.to_string()).chain(args).collect(),
+        None => argv.to_vec(),
+    }
+}
+
+fn program_name(program: &str) -> &str {
+    Path::new(program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(program)
+}
+
+impl CommandRunner for FixtureRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        _input: Option<&'a [u8]>,
+        _timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        Box::pin(async move {
+            let argv = argv(program, args);
+            self.record(&argv);
+            self.lookup(&argv)
+                .cloned()
+                .ok_or_else(|| RunError::Spawn(format!("no fixture for `{}`", argv.join(" "))))
+        })
+    }
+
+    fn run_apply<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        _timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        Box::pin(async move {
+            let argv = argv(program, args);
+            self.record(&argv);
+            let scripted = self.lookup(&unelevated(&argv)).cloned();
+            Ok(scripted.unwrap_or_else(|| CommandOutput {
+                code: Some(0),
+                stdout: format!("[dry run] not executed: {}", argv.join(" ")),
+                stderr: String::new(),
+            }))
+        })
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+
+    fn dirs(&self) -> Dirs {
+        Dirs::Fixture(self.dir.clone())
+    }
+
+    fn has_program(&self, program: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|(pattern, _)| pattern.first().map(String::as_str) == Some(program))
+    }
+}
+
+/// One system in the recorded command-output corpus.
+#[cfg(test)]
+pub fn fixture_corpus(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/commands")
+        .join(name)
+}
+
+/// A [`FixtureRunner`] over one corpus directory.
+#[cfg(test)]
+pub fn fixture_runner(name: &str) -> Arc<FixtureRunner> {
+    Arc::new(FixtureRunner::from_dir(&fixture_corpus(name)).expect("fixture corpus loads"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper;
+    use crate::operations::Operation;
+    use crate::package::PackageSource;
+    use crate::system::Runner;
+
+    #[tokio::test]
+    async fn fixture_runner_matches_longest_prefix_and_records_calls() {
+        let runner = fixture_runner("ubuntu-24.04");
+        let runner: Runner = runner.clone() as Runner;
+        let out = runner
+            .capture_stdout("/usr/bin/snap", &["list"], Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(out.starts_with("Name"));
+        assert!(runner
+            .capture_stdout("definitely-not-recorded", &[], Duration::from_secs(1))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_steps_are_recorded_not_run() {
+        let fixtures = fixture_runner("ubuntu-24.04");
+        let runner: Runner = fixtures.clone();
+        let request = Request::new(Operation::Uninstall, PackageSource::Apt, "htop");
+        let res = runner
+            .run_privileged(&request, Duration::from_secs(1))
+            .await;
+        assert!(res.success, "{}", res.message);
+        assert!(res.logs.contains("[dry run] not executed: pkexec"));
+        let calls = fixtures.calls();
+        let last = calls.last().unwrap();
+        assert_eq!(
+            last,
+            &[
+                "pkexec".to_string(),
+                helper::path(),
+                "remove".into(),
+                "apt".into(),
+                "htop".into()
+            ]
+        );
+        assert_eq!(unelevated(last), ["apt", "remove", "-y", "htop"]);
+    }
+}
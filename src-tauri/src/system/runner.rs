@@ -0,0 +1,329 @@
+//! The seam every external command goes through.
+//!
+//! Scanners and backends never spawn processes themselves: they receive a
+//! [`Runner`] and call [`CommandRunner::run`] (usually through the
+//! `capture_stdout` / `run_step` / `run_privileged` helpers on
+//! `dyn CommandRunner`).
+//! [`SystemRunner`] executes for real; [`super::FixtureRunner`] replays
+//! recorded output for tests and dry-run mode.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::process::Command;
+
+use super::{abs, which, BoxFuture, Dirs};
+use crate::helper::{self, Request};
+use crate::operations::OperationResult;
+
+/// Shared handle to the active runner.
+pub type Runner = Arc<dyn CommandRunner>;
+
+/// Captured result of a command that ran to completion.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    /// Exit code, `None` when killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Why a command did not run to completion.
+#[derive(Debug)]
+pub enum RunError {
+    /// The program could not be started (missing binary, permissions...).
+    Spawn(String),
+    /// The program outlived its timeout and was killed.
+    TimedOut,
+}
+
+/// Executes external programs.
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args` to completion, writing `input` to its stdin
+    /// when given. Never goes through a shell.
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        input: Option<&'a [u8]>,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>>;
+
+    /// Run a state-changing command from an operation's apply step.
+    /// `program` is what gets spawned (`pkexec` or the program itself).
+    /// Dry-run runners record it instead of executing it.
+    fn run_apply<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        self.run(program, args, None, timeout)
+    }
+
+    /// Run `request` through `scope-helper` as root, as an apply step. By
+    /// default each request is its own `pkexec` call (and password prompt);
+    /// [`super::PrivilegedSession`] streams them through one.
+    fn run_helper<'a>(
+        &'a self,
+        request: &'a Request,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        Box::pin(async move {
+            let helper = helper::path();
+            let args = request.args();
+            let mut argv: Vec<&str> = vec![&helper];
+            argv.extend(args.iter().map(String::as_str));
+            self.run_apply("pkexec", &argv, timeout).await
+        })
+    }
+
+    /// True when nothing is really executed.
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Where the filesystem sources are read from: the real directories, or
+    /// a dry-run fixture's.
+    fn dirs(&self) -> Dirs {
+        Dirs::Host
+    }
+
+    /// Whether `program` can be run. Availability probe for scanners.
+    fn has_program(&self, program: &str) -> bool {
+        which(program)
+    }
+}
+
+impl dyn CommandRunner + '_ {
+    /// Capture stdout of a command as a UTF-8 string, with a timeout.
+    ///
+    /// If the command is missing, exits non-zero, or exceeds the timeout, this
+    /// returns `Err` with a readable cause.
+    pub async fn capture_stdout(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Duration,
+    ) -> Result<String> {
+        self.capture(program, args, None, timeout).await
+    }
+
+    /// Like [`capture_stdout`](Self::capture_stdout), but writes `input` to
+    /// the child's stdin first. Used for the JSON exchange with plugins.
+    pub async fn capture_stdout_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        timeout: Duration,
+    ) -> Result<String> {
+        self.capture(program, args, Some(input), timeout).await
+    }
+
+    async fn capture(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: Option<&[u8]>,
+        timeout: Duration,
+    ) -> Result<String> {
+        match self.run(program, args, input, timeout).await {
+            Ok(out) if out.success() => Ok(out.stdout),
+            Ok(out) => anyhow::bail!(
+                "{program} failed (exit {:?}): {}",
+                out.code,
+                out.stderr.trim()
+            ),
+            Err(RunError::Spawn(e)) => anyhow::bail!("failed to spawn {program}: {e}"),
+            Err(RunError::TimedOut) => anyhow::bail!("{program} timed out after {timeout:?}"),
+        }
+    }
+
+    /// Run one unprivileged step of an operation's apply, capturing
+    /// combined output and enforcing a timeout.
+    pub async fn run_step(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Duration,
+    ) -> OperationResult {
+        let program_abs = abs(program);
+        let started = std::time::Instant::now();
+        let output = self.run_apply(&program_abs, args, timeout).await;
+        step_result(output, &program_abs, args, started, timeout)
+    }
+
+    /// In dry-run mode, record a file change Scope would make itself as the
+    /// equivalent command (`rm -r <dir>`) and return its scripted or "not
+    /// executed" result. `None` when the change is to be made for real.
+    pub async fn dry_run_file_change(
+        &self,
+        program: &str,
+        args: &[&str],
+    ) -> Option<OperationResult> {
+        if !self.is_dry_run() {
+            return None;
+        }
+        Some(self.run_step(program, args, Duration::from_secs(20)).await)
+    }
+
+    /// Run `request` as root: `pkexec` starts `scope-helper`, which checks
+    /// the request again and runs the package manager. See [`crate::helper`].
+    pub async fn run_privileged(&self, request: &Request, timeout: Duration) -> OperationResult {
+        let helper = helper::path();
+        if !self.is_dry_run() && !std::path::Path::new(&helper).is_file() {
+            return OperationResult {
+                success: false,
+                message: format!(
+                    "scope-helper is not installed at {helper}. Reinstall Scope, or set {} \
+                     to a scope-helper build.",
+                    helper::HELPER_ENV
+                ),
+                logs: String::new(),
+                exit_code: None,
+            };
+        }
+        let started = std::time::Instant::now();
+        let output = self.run_helper(request, timeout).await;
+        let args = request.args();
+        let display_program = format!("pkexec {helper}");
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        step_result(output, &display_program, &args, started, timeout)
+    }
+}
+
+/// The [`OperationResult`] of an apply step that ran `display_program` with
+/// `args`.
+fn step_result(
+    output: Result<CommandOutput, RunError>,
+    display_program: &str,
+    args: &[&str],
+    started: std::time::Instant,
+    timeout: Duration,
+) -> OperationResult {
+    let logs_suffix = format!(
+        "\n[scope] ran: {} {:?} ({}ms)",
+        display_program,
+        args,
+        started.elapsed().as_millis()
+    );
+
+    match output {
+        Ok(out) => {
+            let logs = format!(
+                "--- stdout ---\n{}\n--- stderr ---\n{}{logs_suffix}",
+                out.stdout, out.stderr
+            );
+            let message = if out.success() {
+                "Operation completed successfully.".to_string()
+            } else {
+                let first_err = out
+                    .stderr
+                    .lines()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("command failed");
+                format!("Operation failed (exit {:?}): {}", out.code, first_err)
+            };
+            OperationResult {
+                success: out.success(),
+                message,
+                logs,
+                exit_code: out.code,
+            }
+        }
+        Err(RunError::Spawn(e)) => OperationResult {
+            success: false,
+            message: format!("Failed to start command: {e}"),
+            logs: format!("spawn error: {e}{logs_suffix}"),
+            exit_code: None,
+        },
+        Err(RunError::TimedOut) => OperationResult {
+            success: false,
+            message: format!("Operation timed out after {timeout:?}."),
+            logs: format!("timed out after {timeout:?}{logs_suffix}"),
+            exit_code: None,
+        },
+    }
+}
+
+/// Runs commands on the real system.
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        input: Option<&'a [u8]>,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        Box::pin(async move {
+            use std::process::Stdio;
+            use tokio::io::AsyncWriteExt;
+
+            let mut cmd = Command::new(program);
+            cmd.args(args)
+                .stdin(if input.is_some() {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true);
+            let mut child = cmd.spawn().map_err(|e| RunError::Spawn(e.to_string()))?;
+            let stdin = input.zip(child.stdin.take());
+            // Input is written while the output is read, both under the
+            // timeout: a child that reads neither would block either for good.
+            let write = async move {
+                if let Some((input, mut stdin)) = stdin {
+                    // A child that exits without reading stdin is not an error by itself.
+                    let _ = stdin.write_all(input).await;
+                    // Dropping stdin here closes it, ending the child's input.
+                }
+            };
+            let finished = async { tokio::join!(write, child.wait_with_output()).1 };
+            match tokio::time::timeout(timeout, finished).await {
+                Ok(Ok(out)) => Ok(CommandOutput {
+                    code: out.status.code(),
+                    stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&out.stderr).to_string(),
+                }),
+                Ok(Err(e)) => Err(RunError::Spawn(e.to_string())),
+                Err(_) => Err(RunError::TimedOut),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timeout_covers_input_the_child_never_reads() {
+        // Far more than a pipe buffer holds.
+        let input = vec![b'x'; 4 << 20];
+        let result = SystemRunner
+            .run("sleep", &["5"], Some(&input), Duration::from_millis(200))
+            .await;
+        assert!(matches!(result, Err(RunError::TimedOut)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn input_reaches_the_child_and_is_closed() {
+        let output = SystemRunner
+            .run("cat", &[], Some(b"ids\n"), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "ids\n");
+    }
+}
@@ -0,0 +1,287 @@
+//! One authentication for a batch of privileged steps.
+//!
+//! Strategy: [`PrivilegedSession`] wraps the active [`Runner`] and changes
+//! one thing, [`CommandRunner::run_helper`]. Instead of a `pkexec` call (and
+//! password prompt) per request it starts `pkexec scope-helper session` on the
+//! first privileged request and streams every later one through that process:
+//! one JSON array of helper arguments per line, one JSON
+//! [`Response`](crate::helper::Response) back. Nothing is sent before the
+//! helper prints [`helper::SESSION_READY`], so the password prompt does not
+//! count against the first request's timeout. The helper still parses and
+//! checks every request itself. The session ends when the wrapper is dropped,
+//! which closes the helper's stdin, or when the helper has been idle for
+//! [`helper::SESSION_IDLE`]; a request after that starts (and authenticates)
+//! a new one. Dry runs never start a helper: requests go to the wrapped
+//! runner, which records them one by one.
+
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use super::runner::{CommandOutput, CommandRunner, RunError, Runner};
+use super::{BoxFuture, Dirs};
+use crate::helper::{self, Request, Response};
+
+/// A session is not reused once it has been idle this long, so a request
+/// never races the helper's own [`helper::SESSION_IDLE`] exit.
+const REUSE_WITHIN: Duration = Duration::from_secs(helper::SESSION_IDLE.as_secs() - 10);
+
+/// Runner that sends privileged requests through one long-lived
+/// `scope-helper session` and everything else to the runner it wraps.
+pub struct PrivilegedSession {
+    inner: Runner,
+    /// Program and arguments that start the helper session.
+    launcher: Vec<String>,
+    process: Mutex<Option<HelperProcess>>,
+}
+
+struct HelperProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    last_used: Instant,
+}
+
+impl PrivilegedSession {
+    pub fn new(inner: Runner) -> Self {
+        Self::with_launcher(
+            inner,
+            vec!["pkexec".into(), helper::path(), helper::SESSION_ARG.into()],
+        )
+    }
+
+    fn with_launcher(inner: Runner, launcher: Vec<String>) -> Self {
+        Self {
+            inner,
+            launcher,
+            process: Mutex::new(None),
+        }
+    }
+
+    fn start(&self) -> Result<HelperProcess, RunError> {
+        let (program, args) = self
+            .launcher
+            .split_first()
+            .expect("the launcher names a program");
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| RunError::Spawn(e.to_string()))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(RunError::Spawn("scope-helper session has no pipes".into()));
+        };
+        Ok(HelperProcess {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            last_used: Instant::now(),
+        })
+    }
+
+    async fn send(&self, request: &Request, timeout: Duration) -> Result<CommandOutput, RunError> {
+        let mut guard = self.process.lock().await;
+        if guard
+            .as_ref()
+            .is_some_and(|p| p.last_used.elapsed() >= REUSE_WITHIN)
+        {
+            guard.take().expect("checked above").end().await;
+        }
+        if guard.is_none() {
+            match self.start()?.authenticate().await {
+                Ok(process) => *guard = Some(process),
+                // Authentication failed, or the helper refused the session.
+                Err(code) => return Ok(ended(code)),
+            }
+        }
+        let process = guard.as_mut().expect("started above");
+
+        let mut line =
+            serde_json::to_string(&request.args()).map_err(|e| RunError::Spawn(e.to_string()))?;
+        line.push('\n');
+        let sent = async {
+            process.stdin.write_all(line.as_bytes()).await?;
+            process.stdin.flush().await
+        }
+        .await;
+        let mut answer = String::new();
+        let read = match sent {
+            Ok(()) => tokio::time::timeout(timeout, process.stdout.read_line(&mut answer)).await,
+            Err(e) => Ok(Err(e)),
+        };
+        match read {
+            Ok(Ok(n)) if n > 0 => {
+                process.last_used = Instant::now();
+                match serde_json::from_str::<Response>(&answer) {
+                    Ok(response) => Ok(CommandOutput {
+                        code: response.code,
+                        stdout: response.stdout,
+                        stderr: response.stderr,
+                    }),
+                    Err(e) => {
+                        guard.take().expect("checked above").end().await;
+                        Err(RunError::Spawn(format!(
+                            "scope-helper sent an unreadable answer: {e}"
+                        )))
+                    }
+                }
+            }
+            // The helper exited.
+            Ok(_) => Ok(ended(guard.take().expect("checked above").end().await)),
+            Err(_) => {
+                guard.take().expect("checked above").end().await;
+                Err(RunError::TimedOut)
+            }
+        }
+    }
+}
+
+impl HelperProcess {
+    /// Wait for [`helper::SESSION_READY`]. `pkexec` starts the helper only
+    /// once the user has authenticated, so there is no timeout here. The
+    /// exit code when the helper ends instead.
+    async fn authenticate(mut self) -> Result<Self, Option<i32>> {
+        let mut line = String::new();
+        match self.stdout.read_line(&mut line).await {
+            Ok(_) if line.trim_end() == helper::SESSION_READY => Ok(self),
+            _ => Err(self.end().await),
+        }
+    }
+
+    /// Stop the helper and wait for it to exit; its exit code. A helper
+    /// running as root does not take the signal, but exits once its current
+    /// command is done and it finds its stdin closed, so a new session never
+    /// runs beside it.
+    async fn end(self) -> Option<i32> {
+        let Self {
+            mut child, stdin, ..
+        } = self;
+        drop(stdin);
+        let _ = child.start_kill();
+        child.wait().await.ok().and_then(|s| s.code())
+    }
+}
+
+/// The answer for a session that ended instead of answering.
+fn ended(code: Option<i32>) -> CommandOutput {
+    CommandOutput {
+        code,
+        stdout: String::new(),
+        stderr: ended_message(code).to_string(),
+    }
+}
+
+/// Why a session ended before answering, from its exit code.
+fn ended_message(code: Option<i32>) -> &'static str {
+    // pkexec's codes for a dismissed and a failed authentication.
+    match code {
+        Some(126) => "Authentication was cancelled.",
+        Some(127) => "Not authorized to change packages.",
+        _ => "scope-helper session ended unexpectedly.",
+    }
+}
+
+impl CommandRunner for PrivilegedSession {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        input: Option<&'a [u8]>,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        self.inner.run(program, args, input, timeout)
+    }
+
+    fn run_apply<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        self.inner.run_apply(program, args, timeout)
+    }
+
+    fn run_helper<'a>(
+        &'a self,
+        request: &'a Request,
+        timeout: Duration,
+    ) -> BoxFuture<'a, Result<CommandOutput, RunError>> {
+        if self.inner.is_dry_run() {
+            return self.inner.run_helper(request, timeout);
+        }
+        Box::pin(self.send(request, timeout))
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.inner.is_dry_run()
+    }
+
+    fn dirs(&self) -> Dirs {
+        self.inner.dirs()
+    }
+
+    fn has_program(&self, program: &str) -> bool {
+        self.inner.has_program(program)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::operations::Operation;
+    use crate::package::PackageSource;
+    use crate::system::SystemRunner;
+
+    fn session(script: &str) -> PrivilegedSession {
+        PrivilegedSession::with_launcher(
+            Arc::new(SystemRunner),
+            vec!["sh".into(), "-c".into(), script.into()],
+        )
+    }
+
+    #[tokio::test]
+    async fn requests_share_one_helper_until_it_exits() {
+        // Answers every line with its own pid.
+        let shared = session(
+            r#"echo ready; while read -r line; do printf '{"code":0,"stdout":"%s","stderr":""}\n' "$$"; done"#,
+        );
+        let request = Request::new(Operation::Update, PackageSource::Apt, "curl");
+        let timeout = Duration::from_secs(5);
+        let first = shared.run_helper(&request, timeout).await.unwrap();
+        let second = shared.run_helper(&request, timeout).await.unwrap();
+        assert_eq!(first.code, Some(0));
+        assert!(!first.stdout.is_empty());
+        assert_eq!(first.stdout, second.stdout);
+
+        let cancelled = session("exit 126");
+        let out = cancelled.run_helper(&request, timeout).await.unwrap();
+        assert_eq!(out.code, Some(126));
+        assert_eq!(out.stderr, "Authentication was cancelled.");
+    }
+
+    #[tokio::test]
+    async fn timeouts_start_after_authentication_and_end_the_helper() {
+        let request = Request::new(Operation::Update, PackageSource::Apt, "curl");
+        let timeout = Duration::from_millis(500);
+        // The password prompt takes longer than the request may.
+        let slow_prompt = session(
+            r#"sleep 1; echo ready; while read -r line; do echo '{"code":0,"stdout":"","stderr":""}'; done"#,
+        );
+        let out = slow_prompt.run_helper(&request, timeout).await.unwrap();
+        assert_eq!(out.code, Some(0));
+
+        let stuck = session("echo ready; read -r line; sleep 30");
+        let started = Instant::now();
+        let err = stuck.run_helper(&request, timeout).await.unwrap_err();
+        assert!(matches!(err, RunError::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(stuck.process.lock().await.is_none());
+    }
+}
@@ -0,0 +1,244 @@
+//! System tray indicator.
+//!
+//! Strategy: the tray keeps Scope reachable with its window closed, so
+//! closing the window only hides it once the tray is up (without a tray, e.g.
+//! when the desktop has no StatusNotifier host, closing still quits). The tray
+//! itself holds no logic: the outdated count is recomputed from the
+//! [`ScanCache`] on every diff (update checks, rescans, applied updates),
+//! "Check now" runs the [`UpdateChecker`] and "Update all" runs
+//! [`update_all`], all backend code that works without a window.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{App, AppHandle, Manager, WindowEvent, Wry};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::commands::operations::update_all;
+use crate::dbus::Bus;
+use crate::notify::Notifier;
+use crate::operations::PlanStore;
+use crate::scan_cache::{CachedScan, ScanCache, ScanDiff};
+use crate::system::Runner;
+use crate::updates::UpdateChecker;
+
+/// Label of the window the tray opens, Tauri's default for the first window.
+pub const MAIN_WINDOW: &str = "main";
+
+const OPEN: &str = "open";
+const CHECK_NOW: &str = "check-now";
+const UPDATE_ALL: &str = "update-all";
+const QUIT: &str = "quit";
+
+/// What the tray's menu items run.
+#[derive(Clone)]
+struct Actions {
+    runner: Runner,
+    scan_cache: ScanCache,
+    plans: PlanStore,
+    bus: Bus,
+    notifier: Notifier,
+    checker: UpdateChecker,
+}
+
+/// The parts of the tray that show the update count.
+#[derive(Clone)]
+struct Display {
+    tray: TrayIcon,
+    status: MenuItem<Wry>,
+    update_all: MenuItem<Wry>,
+    /// Set while "Update all" runs, so it is not started twice.
+    updating: Arc<AtomicBool>,
+}
+
+/// Add the tray icon and make closing the main window hide it instead.
+pub fn install(
+    app: &App,
+    runner: Runner,
+    scan_cache: ScanCache,
+    plans: PlanStore,
+    bus: Bus,
+    notifier: Notifier,
+    checker: UpdateChecker,
+) -> tauri::Result<()> {
+    let status = MenuItem::with_id(
+        app,
+        "status",
+        "Updates not checked yet",
+        false,
+        None::<&str>,
+    )?;
+    let update_all = MenuItem::with_id(app, UPDATE_ALL, "Update all", false, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &update_all,
+            &MenuItem::with_id(app, CHECK_NOW, "Check now", true, None::<&str>)?,
+            &MenuItem::with_id(app, OPEN, "Open Scope", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?,
+        ],
+    )?;
+    let mut builder = TrayIconBuilder::with_id("scope")
+        .menu(&menu)
+        .tooltip("Scope");
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let tray = builder.build(app)?;
+
+    let display = Display {
+        tray: tray.clone(),
+        status,
+        update_all,
+        updating: Arc::default(),
+    };
+    let actions = Actions {
+        runner,
+        scan_cache: scan_cache.clone(),
+        plans,
+        bus,
+        notifier,
+        checker,
+    };
+    let menu_display = display.clone();
+    tray.on_menu_event(move |app, event| {
+        on_menu(app, event.id().as_ref(), &actions, &menu_display)
+    });
+    tauri::async_runtime::spawn(follow_scan(
+        display,
+        scan_cache.clone(),
+        scan_cache.subscribe(),
+    ));
+
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let hidden = window.clone();
+        window.on_window_event(move |event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+                let _ = hidden.hide();
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Bring the main window back, wherever it is.
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn on_menu(app: &AppHandle, id: &str, actions: &Actions, display: &Display) {
+    match id {
+        OPEN => show_main_window(app),
+        CHECK_NOW => {
+            let checker = actions.checker.clone();
+            tauri::async_runtime::spawn(async move { checker.check().await });
+        }
+        UPDATE_ALL => {
+            if display.updating.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let actions = actions.clone();
+            let display = display.clone();
+            tauri::async_runtime::spawn(async move {
+                display.show(actions.scan_cache.get().await.as_ref());
+                let results = update_all(
+                    &actions.runner,
+                    &actions.scan_cache,
+                    &actions.plans,
+                    &actions.bus,
+                    &actions.notifier,
+                )
+                .await;
+                for (plan, result) in results.iter().filter(|(_, r)| !r.success) {
+                    eprintln!(
+                        "scope: updating {} failed: {}",
+                        plan.display_name, result.message
+                    );
+                }
+                display.updating.store(false, Ordering::SeqCst);
+                display.show(actions.scan_cache.get().await.as_ref());
+            });
+        }
+        QUIT => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Keep the tray's count in step with the cached scan.
+async fn follow_scan(display: Display, cache: ScanCache, mut diffs: Receiver<ScanDiff>) {
+    display.show(cache.get().await.as_ref());
+    // A lagged receiver only skipped diffs; the count is read afresh anyway.
+    while let Ok(_) | Err(RecvError::Lagged(_)) = diffs.recv().await {
+        display.show(cache.get().await.as_ref());
+    }
+}
+
+impl Display {
+    fn show(&self, scan: Option<&CachedScan>) {
+        let outdated = outdated_count(scan);
+        let updating = self.updating.load(Ordering::SeqCst);
+        let status = status_text(outdated, updating);
+        let _ = self.status.set_text(&status);
+        let _ = self
+            .update_all
+            .set_enabled(!updating && outdated.is_some_and(|n| n > 0));
+        let _ = self.tray.set_tooltip(Some(format!("Scope: {status}")));
+        // Shown next to the icon where the tray host supports labels.
+        let _ = self
+            .tray
+            .set_title(outdated.filter(|n| *n > 0).map(|n| n.to_string()));
+    }
+}
+
+/// Packages with an update, or `None` when updates were never checked.
+fn outdated_count(scan: Option<&CachedScan>) -> Option<usize> {
+    let scan = scan.filter(|scan| scan.updates_checked_at_ms.is_some())?;
+    Some(scan.packages.iter().filter(|p| p.has_update).count())
+}
+
+fn status_text(outdated: Option<usize>, updating: bool) -> String {
+    match (updating, outdated) {
+        (true, _) => "Updating…".into(),
+        (false, None) => "Updates not checked yet".into(),
+        (false, Some(0)) => "Everything is up to date".into(),
+        (false, Some(1)) => "1 update available".into(),
+        (false, Some(n)) => format!("{n} updates available"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::{InstalledPackage, PackageSource};
+    use crate::scanner::ScanAvailability;
+
+    #[test]
+    fn counts_updates_only_once_checked() {
+        let mut curl = InstalledPackage::new(PackageSource::Apt, "curl");
+        curl.has_update = true;
+        let mut scan = CachedScan {
+            packages: vec![curl, InstalledPackage::new(PackageSource::Apt, "vim")],
+            availability: ScanAvailability::default(),
+            scanned_at_ms: 1,
+            stale: false,
+            updates_checked_at_ms: None,
+        };
+        assert_eq!(outdated_count(Some(&scan)), None);
+        assert_eq!(status_text(None, false), "Updates not checked yet");
+
+        scan.updates_checked_at_ms = Some(2);
+        assert_eq!(outdated_count(Some(&scan)), Some(1));
+        assert_eq!(status_text(Some(1), false), "1 update available");
+        assert_eq!(status_text(Some(3), true), "Updating…");
+    }
+}
@@ -0,0 +1,314 @@
+//! Update checks, kept apart from scanning.
+//!
+//! Strategy: scans only read local package databases, while asking for
+//! updates may go to the network (flatpak refreshes its appstream data, snapd
+//! asks the store) and takes seconds. So update checks run on their own: on
+//! request (`check_updates`) and on a schedule, every [`DEFAULT_INTERVAL`]
+//! unless the `[updates]` config or [`INTERVAL_ENV`] says otherwise. Each
+//! check runs every backend's
+//! [`PackageBackend::check_updates`] on a copy of the cached scan and
+//! annotates the [`ScanCache`] in place, which publishes the changed packages
+//! as a diff. A source whose check fails keeps its earlier annotations, and
+//! the check's time is only recorded when every source answered, so the
+//! schedule retries after [`RETRY_AFTER`] rather than waiting out a full
+//! interval on stale results. The outdated count and the failures go to
+//! [`UpdateChecker::subscribe`]rs.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+
+use crate::backend::{Capability, PackageBackend};
+use crate::package::{InstalledPackage, PackageSource};
+use crate::scan_cache::{CachedScan, ScanCache};
+use crate::system::Runner;
+
+/// Environment variable with the minutes between scheduled update checks,
+/// overriding the config. `0` turns scheduled checks off.
+pub const INTERVAL_ENV: &str = "SCOPE_UPDATE_INTERVAL";
+/// Time between scheduled update checks when neither the config nor
+/// [`INTERVAL_ENV`] sets one.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How soon the schedule tries again after a check with failures.
+pub const RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// Checks a slow subscriber may fall behind by before it misses some.
+const CHECK_BACKLOG: usize = 4;
+
+/// Outcome of one update check, sent as the `updates-checked` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    /// Packages with an update available.
+    pub outdated: usize,
+    /// When every source was last checked; an earlier check's time when
+    /// this one had failures.
+    pub checked_at_ms: u64,
+    /// Sources whose check failed, with why. Their packages keep the
+    /// previous check's annotations.
+    pub failed: HashMap<PackageSource, String>,
+}
+
+impl UpdateCheck {
+    fn of(scan: &CachedScan, failed: HashMap<PackageSource, String>) -> Self {
+        Self {
+            outdated: scan.packages.iter().filter(|p| p.has_update).count(),
+            checked_at_ms: scan.updates_checked_at_ms.unwrap_or_default(),
+            failed,
+        }
+    }
+}
+
+/// Runs update checks against the shared [`ScanCache`], managed as Tauri state.
+#[derive(Clone)]
+pub struct UpdateChecker {
+    runner: Runner,
+    cache: ScanCache,
+    checks: broadcast::Sender<UpdateCheck>,
+    /// Held while a check runs, so concurrent requests do not overlap.
+    running: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl UpdateChecker {
+    pub fn new(runner: Runner, cache: ScanCache) -> Self {
+        Self {
+            runner,
+            cache,
+            checks: broadcast::channel(CHECK_BACKLOG).0,
+            running: Arc::default(),
+        }
+    }
+
+    /// Receive the outcome of every later check.
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateCheck> {
+        self.checks.subscribe()
+    }
+
+    /// Check the cached scan's packages for updates and annotate it with the
+    /// result. `None` before the first scan, when there is nothing to check.
+    pub async fn check(&self) -> Option<UpdateCheck> {
+        let _running = self.running.lock().await;
+        let mut packages = self.cache.get().await?.packages;
+        let failed = check_packages(&self.runner, &mut packages).await;
+        for (source, error) in &failed {
+            eprintln!("scope: {} update check failed: {error}", source.id());
+        }
+        packages.retain(|p| !failed.contains_key(&p.source));
+        let scan = self
+            .cache
+            .annotate_updates(&packages, failed.is_empty())
+            .await?;
+        let check = UpdateCheck::of(&scan, failed);
+        // Having no subscriber is fine.
+        let _ = self.checks.send(check.clone());
+        Some(check)
+    }
+
+    /// Check whenever `interval` has passed since the last check, counting
+    /// checks made on request and those recorded by a previous run.
+    pub async fn run_schedule(self, interval: Duration) {
+        loop {
+            let last = self
+                .cache
+                .get()
+                .await
+                .and_then(|scan| scan.updates_checked_at_ms);
+            let wait = due_in(last, now_ms(), interval);
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            match self.check().await {
+                // Not scanned yet; the startup scan is still running.
+                None => tokio::time::sleep(Duration::from_secs(60)).await,
+                // The check time was not recorded, so it is still due.
+                Some(check) if !check.failed.is_empty() => {
+                    tokio::time::sleep(RETRY_AFTER.min(interval)).await
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// The scheduled-check interval from [`INTERVAL_ENV`], or else the config.
+/// `None` when scheduled checks are off.
+pub fn interval() -> Option<Duration> {
+    let configured = crate::config::current().updates.interval();
+    let Some(value) = std::env::var_os(INTERVAL_ENV) else {
+        return configured;
+    };
+    match value.to_str().and_then(env_interval) {
+        Some(interval) => interval,
+        None => {
+            eprintln!(
+                "scope: {INTERVAL_ENV} must be a number of minutes; using the configured interval"
+            );
+            configured
+        }
+    }
+}
+
+/// The interval [`INTERVAL_ENV`]'s `minutes` ask for: `Some(None)` for `0`,
+/// `None` when it is not a number or too large to be a duration.
+fn env_interval(minutes: &str) -> Option<Option<Duration>> {
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    if minutes == 0 {
+        return Some(None);
+    }
+    Some(Some(Duration::from_secs(minutes.checked_mul(60)?)))
+}
+
+/// Set the update annotations of `packages` from their backends' update
+/// checks, which run in parallel. Packages of sources that cannot check for
+/// updates, and of those whose check fails, are left alone; the failures are
+/// returned by source.
+pub async fn check_packages(
+    runner: &Runner,
+    packages: &mut [InstalledPackage],
+) -> HashMap<PackageSource, String> {
+    let mut join = JoinSet::new();
+    let mut sources = HashMap::new();
+    for backend in crate::backend::registry() {
+        let backend: &'static dyn PackageBackend = *backend;
+        if !backend.capabilities().contains(&Capability::CheckUpdates) {
+            continue;
+        }
+        // Start from a clean slate so updates that went away are cleared.
+        let mut own: Vec<InstalledPackage> = packages
+            .iter()
+            .filter(|p| p.source == backend.source())
+            .cloned()
+            .map(|mut p| {
+                p.clear_update();
+                p
+            })
+            .collect();
+        if own.is_empty() {
+            continue;
+        }
+        let runner = runner.clone();
+        let task = join.spawn(async move {
+            backend.check_updates(&runner, &mut own).await?;
+            Ok(own)
+        });
+        sources.insert(task.id(), backend.source());
+    }
+
+    let mut checked: HashMap<String, InstalledPackage> = HashMap::new();
+    let mut failed = HashMap::new();
+    while let Some(res) = join.join_next_with_id().await {
+        let (id, result) = match res {
+            Ok((id, result)) => (id, result),
+            Err(e) => (e.id(), Err(anyhow::anyhow!("the check crashed: {e}"))),
+        };
+        match result {
+            Ok(own) => checked.extend(own.into_iter().map(|p| (p.key.clone(), p))),
+            Err(e) => {
+                failed.insert(sources[&id], format!("{e:#}"));
+            }
+        }
+    }
+    for pkg in packages.iter_mut() {
+        if let Some(c) = checked.get(&pkg.key) {
+            pkg.copy_update_from(c);
+        }
+    }
+    failed
+}
+
+/// How long until the next scheduled check is due.
+fn due_in(last_checked_ms: Option<u64>, now_ms: u64, interval: Duration) -> Duration {
+    let Some(last) = last_checked_ms else {
+        return Duration::ZERO;
+    };
+    let elapsed = Duration::from_millis(now_ms.saturating_sub(last));
+    interval.saturating_sub(elapsed)
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture_runner;
+
+    #[tokio::test]
+    async fn scans_are_local_and_checks_annotate_the_cache() {
+        let fixtures = fixture_runner("ubuntu-24.04");
+        let runner: Runner = fixtures.clone();
+        let cache = ScanCache::default();
+        cache.refresh(&runner).await;
+        let curl = cache.find("apt:curl").await.unwrap();
+        assert!(!curl.has_update, "scanning does not check for updates");
+        assert!(!fixtures
+            .calls()
+            .iter()
+            .any(|argv| argv.starts_with(&["flatpak".into(), "update".into()])));
+
+        let checker = UpdateChecker::new(runner.clone(), cache.clone());
+        let check = checker.check().await.unwrap();
+        assert!(check.outdated > 0);
+        let scan = cache.get().await.unwrap();
+        assert_eq!(scan.updates_checked_at_ms, Some(check.checked_at_ms));
+        let curl = cache.find("apt:curl").await.unwrap();
+        assert_eq!(curl.update_version.as_deref(), Some("8.5.0-2ubuntu10.7"));
+
+        // A rescan keeps annotations of packages whose version is unchanged.
+        cache.rescan(&runner, &[curl.source]).await;
+        assert!(cache.find("apt:curl").await.unwrap().has_update);
+    }
+
+    #[tokio::test]
+    async fn failed_checks_keep_earlier_annotations() {
+        let runner: Runner = fixture_runner("ubuntu-24.04");
+        let cache = ScanCache::default();
+        cache.refresh(&runner).await;
+        let checked = UpdateChecker::new(runner, cache.clone())
+            .check()
+            .await
+            .unwrap();
+        assert!(checked.failed.is_empty());
+
+        // Every command fails on this system.
+        let offline: Runner = Arc::new(crate::system::FixtureRunner::empty());
+        let check = UpdateChecker::new(offline, cache.clone())
+            .check()
+            .await
+            .unwrap();
+        assert!(check.failed.contains_key(&PackageSource::Apt));
+        assert!(check.failed.contains_key(&PackageSource::Flatpak));
+        assert_eq!(check.outdated, checked.outdated);
+        assert_eq!(check.checked_at_ms, checked.checked_at_ms);
+        let curl = cache.find("apt:curl").await.unwrap();
+        assert_eq!(curl.update_version.as_deref(), Some("8.5.0-2ubuntu10.7"));
+    }
+
+    #[test]
+    fn schedule_counts_from_the_last_check() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(due_in(None, 10_000, hour), Duration::ZERO);
+        assert_eq!(
+            due_in(Some(0), 600_000, hour),
+            hour - Duration::from_secs(600)
+        );
+        assert_eq!(due_in(Some(0), 7_200_000, hour), Duration::ZERO);
+    }
+
+    #[test]
+    fn interval_overrides_must_fit_a_duration() {
+        assert_eq!(env_interval(" 90 "), Some(Some(Duration::from_secs(5400))));
+        assert_eq!(env_interval("0"), Some(None));
+        assert_eq!(env_interval("soon"), None);
+        assert_eq!(env_interval(&u64::MAX.to_string()), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! Incremental rescans driven by filesystem change notifications.
+//!
+//! Strategy: each source has a few files or directories that change whenever
+//! its packages do: dpkg's `status` database, snapd's `state.json`, the
+//! `.changed` stamp flatpak touches in each installation, and the AppImage
+//! search directories. Desktop-entry directories change with the sources that
+//! export their apps there (AppImage integration, container exports, Steam
+//! shortcuts). The parent directories are watched with inotify, because these
+//! files are replaced by rename and a watch on the file itself would die with
+//! the old inode. Events are collected until things go quiet for [`QUIET`]
+//! (an `apt` run rewrites `status` many times), capped at [`MAX_DELAY`], and
+//! then only the affected sources are rescanned into the [`ScanCache`], which
+//! publishes the diff.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use inotify::{EventMask, Inotify, WatchMask};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::package::PackageSource;
+use crate::scan_cache::ScanCache;
+use crate::system::{Dirs, Runner};
+
+/// How long no further changes must arrive before rescanning.
+const QUIET: Duration = Duration::from_secs(2);
+/// Longest a rescan waits behind a steady stream of changes.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// One watched directory and the sources a change in it affects.
+#[derive(Debug, Clone)]
+struct Watch {
+    dir: PathBuf,
+    /// Entry names that matter; empty means any entry.
+    names: &'static [&'static str],
+    sources: &'static [PackageSource],
+}
+
+impl Watch {
+    fn matches(&self, name: Option<&OsStr>) -> bool {
+        self.names.is_empty() || name.is_some_and(|n| self.names.iter().any(|w| n == *w))
+    }
+}
+
+/// Watch the package databases and rescan changed sources into `cache`
+/// until the cache's owner goes away. Does nothing in dry-run mode, where the
+/// real filesystem says nothing about the recorded fixtures.
+pub async fn run(runner: Runner, cache: ScanCache) -> Result<()> {
+    if runner.is_dry_run() {
+        return Ok(());
+    }
+    let mut changes = spawn_reader(watches())?;
+    while let Some(source) = changes.recv().await {
+        let mut pending = vec![source];
+        let deadline = tokio::time::Instant::now() + MAX_DELAY;
+        loop {
+            let until = deadline.min(tokio::time::Instant::now() + QUIET);
+            match tokio::time::timeout_at(until, changes.recv()).await {
+                Ok(Some(source)) if !pending.contains(&source) => pending.push(source),
+                Ok(Some(_)) => {}
+                Ok(None) | Err(_) => break,
+            }
+        }
+        cache.rescan(&runner, &pending).await;
+    }
+    Ok(())
+}
+
+/// What to watch on this system. Missing directories are skipped when the
+/// watches are added.
+fn watches() -> Vec<Watch> {
+    const DESKTOP_SOURCES: &[PackageSource] = &[
+        PackageSource::AppImage,
+        PackageSource::Container,
+        PackageSource::Steam,
+    ];
+    let mut out = vec![
+        Watch {
+            dir: PathBuf::from("/var/lib/dpkg"),
+            names: &["status"],
+            sources: &[PackageSource::Apt],
+        },
+        Watch {
+            dir: PathBuf::from("/var/lib/snapd"),
+            names: &["state.json"],
+            sources: &[PackageSource::Snap],
+        },
+    ];
+    for dir in flatpak_installations() {
+        out.push(Watch {
+            dir,
+            names: &[".changed"],
+            sources: &[PackageSource::Flatpak],
+        });
+    }
+    for dir in crate::scanner::appimage::search_directories(&Dirs::Host) {
+        out.push(Watch {
+            dir: PathBuf::from(dir),
+            names: &[],
+            sources: &[PackageSource::AppImage],
+        });
+    }
+    for dir in crate::desktop_entries::application_dirs() {
+        out.push(Watch {
+            dir,
+            names: &[],
+            sources: DESKTOP_SOURCES,
+        });
+    }
+    out
+}
+
+fn flatpak_installations() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("/var/lib/flatpak")];
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+        dirs.push(PathBuf::from(data_home).join("flatpak"));
+    } else if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".local/share/flatpak"));
+    }
+    dirs
+}
+
+/// Add the watches and read events on a dedicated thread, sending each
+/// affected source down the returned channel. The thread ends when the
+/// receiver is dropped (at its next event) or reading fails.
+fn spawn_reader(watches: Vec<Watch>) -> Result<UnboundedReceiver<PackageSource>> {
+    let inotify = Inotify::init().context("initialize inotify")?;
+    let mask = WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MOVED_TO
+        | WatchMask::MOVED_FROM
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::ATTRIB;
+    let mut by_descriptor = HashMap::new();
+    for watch in watches {
+        if !watch.dir.is_dir() {
+            continue;
+        }
+        // One unwatchable directory (permissions, watch limit) should not
+        // cost the others.
+        match inotify.watches().add(&watch.dir, mask) {
+            Ok(wd) => {
+                by_descriptor.entry(wd).or_insert_with(Vec::new).push(watch);
+            }
+            Err(e) => eprintln!("scope: cannot watch {}: {e}", watch.dir.display()),
+        }
+    }
+    if by_descriptor.is_empty() {
+        anyhow::bail!("none of the package directories could be watched");
+    }
+
+    let (tx, rx) = unbounded_channel();
+    std::thread::Builder::new()
+        .name("scope-watcher".into())
+        .spawn(move || read_events(inotify, by_descriptor, tx))
+        .context("start watcher thread")?;
+    Ok(rx)
+}
+
+fn read_events(
+    mut inotify: Inotify,
+    by_descriptor: HashMap<inotify::WatchDescriptor, Vec<Watch>>,
+    tx: UnboundedSender<PackageSource>,
+) {
+    let mut buffer = [0u8; 4096];
+    loop {
+        let events = match inotify.read_events_blocking(&mut buffer) {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("scope: watcher stopped: {e}");
+                return;
+            }
+        };
+        for event in events {
+            let affected: Vec<PackageSource> = if event.mask.contains(EventMask::Q_OVERFLOW) {
+                // Events were dropped: anything could have changed.
+                by_descriptor
+                    .values()
+                    .flatten()
+                    .flat_map(|w| w.sources.iter().copied())
+                    .collect()
+            } else {
+                by_descriptor
+                    .get(&event.wd)
+                    .into_iter()
+                    .flatten()
+                    .filter(|w| w.matches(event.name))
+                    .flat_map(|w| w.sources.iter().copied())
+                    .collect()
+            };
+            for source in affected {
+                if tx.send(source).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_changes_to_watched_names_only() {
+        let dir = std::env::temp_dir().join(format!("scope-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut changes = spawn_reader(vec![Watch {
+            dir: dir.clone(),
+            names: &["status"],
+            sources: &[PackageSource::Apt],
+        }])
+        .unwrap();
+
+        std::fs::write(dir.join("status-old"), "ignored").unwrap();
+        // dpkg writes a new file and renames it over the database.
+        std::fs::write(dir.join("status-new"), "Package: vim").unwrap();
+        std::fs::rename(dir.join("status-new"), dir.join("status")).unwrap();
+
+        let source = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .expect("a change is reported");
+        assert_eq!(source, Some(PackageSource::Apt));
+        assert!(changes.try_recv().is_err(), "only the rename matters");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
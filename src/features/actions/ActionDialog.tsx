@@ -0,0 +1,188 @@
+import { useEffect, useState } from "react";
+import type { InstalledPackage } from "../../shared/types/package";
+import type { Operation, OperationPlan, OperationResult } from "../../shared/types/operations";
+import { SOURCE_LABELS } from "../../shared/types/package";
+import { applyOperation, previewOperation } from "../../shared/api/operations";
+
+type Phase = "loading" | "confirm" | "running" | "done" | "error";
+
+/// Operations without a dedicated dialog (uninstall/update have their own).
+export type ExtraAction = Exclude<Operation, "uninstall" | "update">;
+
+const ACTION_LABELS: Record<ExtraAction, { title: string; confirm: string; running: string }> = {
+  purge: { title: "Purge", confirm: "Confirm purge", running: "Purging" },
+  hold: { title: "Hold", confirm: "Confirm hold", running: "Holding" },
+  unhold: { title: "Release hold on", confirm: "Release hold", running: "Releasing" },
+  rollback: { title: "Roll back", confirm: "Confirm rollback", running: "Rolling back" },
+};
+
+interface Props {
+  pkg: InstalledPackage;
+  action: ExtraAction;
+  onClose: () => void;
+  onApplied: (pkg: InstalledPackage) => void;
+}
+
+export function ActionDialog({ pkg, action, onClose, onApplied }: Props) {
+  const [phase, setPhase] = useState<Phase>("loading");
+  const [plan, setPlan] = useState<OperationPlan | null>(null);
+  const [result, setResult] = useState<OperationResult | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [showLogs, setShowLogs] = useState(false);
+  const labels = ACTION_LABELS[action];
+
+  // Build the preview plan when the dialog opens.
+  useEffect(() => {
+    let cancelled = false;
+    setPhase("loading");
+    setError(null);
+    previewOperation(pkg.key, action)
+      .then((p) => {
+        if (cancelled) return;
+        setPlan(p);
+        setPhase("confirm");
+      })
+      .catch((e) => {
+        if (cancelled) return;
+        setError(String(e));
+        setPhase("error");
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [pkg.key, action]);
+
+  async function confirm() {
+    if (!plan) return;
+    setPhase("running");
+    setError(null);
+    try {
+      const res = await applyOperation(plan.plan_id);
+      setResult(res);
+      setPhase("done");
+      if (res.success) {
+        onApplied(pkg);
+      }
+    } catch (e) {
+      setError(String(e));
+      setPhase("error");
+    }
+  }
+
+  const title = plan?.display_name ?? pkg.display_name ?? pkg.name;
+
+  return (
+    <div className="modal__overlay" onClick={onClose}>
+      <div
+        className={`modal modal--${action}`}
+        role="dialog"
+        aria-modal="true"
+        aria-label={`${labels.title} ${title}`}
+        onClick={(e) => e.stopPropagation()}
+      >
+        <header className="modal__head">
+          <h2>
+            {labels.title} {title}
+          </h2>
+          <button type="button" className="modal__close" onClick={onClose} aria-label="Close">
+            ✕
+          </button>
+        </header>
+
+        {phase === "loading" && (
+          <div className="modal__body">
+            <p className="modal__muted">Preparing preview…</p>
+          </div>
+        )}
+
+        {phase === "error" && (
+          <div className="modal__body">
+            <div className="banner banner--error">{error ?? "Could not prepare the plan."}</div>
+            <div className="modal__actions">
+              <button type="button" className="btn" onClick={onClose}>
+                Close
+              </button>
+            </div>
+          </div>
+        )}
+
+        {phase === "confirm" && plan && (
+          <div className="modal__body">
+            {plan.protected ? (
+              <div className="banner banner--warn">
+                {plan.protection_reason ?? "This package is protected."}
+              </div>
+            ) : (
+              <>
+                <p className="modal__lead">
+                  <strong>{title}</strong> ({SOURCE_LABELS[plan.source]}).
+                  {plan.requires_auth && <> Linux will ask for your password to confirm.</>}
+                </p>
+                <ul className="plan__steps">
+                  {plan.steps.map((s, i) => (
+                    <li key={i}>
+                      <span className="plan__step-desc">{s.description}</span>
+                      <code className="plan__step-cmd">{s.command_summary}</code>
+                    </li>
+                  ))}
+                </ul>
+                {plan.warnings.length > 0 && (
+                  <div className="banner banner--warn">
+                    {plan.warnings.map((w, i) => (
+                      <p key={i}>{w}</p>
+                    ))}
+                  </div>
+                )}
+              </>
+            )}
+            <div className="modal__actions">
+              <button type="button" className="btn" onClick={onClose}>
+                Cancel
+              </button>
+              <button
+                type="button"
+                className={action === "purge" ? "btn btn--danger" : "btn btn--primary"}
+                onClick={confirm}
+                disabled={plan.protected}
+              >
+                {plan.protected ? "Protected" : labels.confirm}
+              </button>
+            </div>
+          </div>
+        )}
+
+        {phase === "running" && (
+          <div className="modal__body">
+            <p className="modal__muted">
+              {labels.running} {title}…{" "}
+              {plan?.requires_auth &&
+                "If a password dialog appears, enter your administrator password."}
+            </p>
+            <div className="spinner" aria-hidden />
+          </div>
+        )}
+
+        {phase === "done" && result && (
+          <div className="modal__body">
+            <div className={`banner ${result.success ? "banner--ok" : "banner--error"}`}>
+              {result.message}
+            </div>
+            <button
+              type="button"
+              className="modal__logtoggle"
+              onClick={() => setShowLogs((v) => !v)}
+            >
+              {showLogs ? "Hide" : "Show"} command output
+            </button>
+            {showLogs && <pre className="modal__logs">{result.logs}</pre>}
+            <div className="modal__actions">
+              <button type="button" className="btn" onClick={onClose}>
+                {result.success ? "Done" : "Close"}
+              </button>
+            </div>
+          </div>
+        )}
+      </div>
+    </div>
+  );
+}
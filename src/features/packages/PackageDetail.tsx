@@ -1,21 +1,70 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import type { InstalledPackage } from "../../shared/types/package";
+import type { Capability, Operation } from "../../shared/types/operations";
+import { packageCapabilities } from "../../shared/api/operations";
 import { SOURCE_COLORS, SOURCE_LABELS } from "../../shared/types/package";
 import { formatSize, kindIcon } from "./format";
 import { AppIcon } from "../../shared/components/AppIcon";
 import { UninstallDialog } from "../uninstall/UninstallDialog";
 import { UpdateDialog } from "../update/UpdateDialog";
+import { ActionDialog, type ExtraAction } from "../actions/ActionDialog";
+
+/** A dialog to open on behalf of a clicked notification. `id` tells repeated
+ * requests for the same dialog apart. */
+export interface DialogRequest {
+  id: number;
+  key: string;
+  operation: Operation;
+}
 
 export function PackageDetail({
   pkg,
+  dialog,
+  onDialogOpened,
   onUninstalled,
 }: {
   pkg: InstalledPackage | null;
+  dialog?: DialogRequest | null;
+  onDialogOpened?: () => void;
   onUninstalled?: (pkg: InstalledPackage) => void;
 }) {
   const [uninstallTarget, setUninstallTarget] = useState<InstalledPackage | null>(null);
   const [updateTarget, setUpdateTarget] = useState<InstalledPackage | null>(null);
+  const [action, setAction] = useState<ExtraAction | null>(null);
+  const [capabilities, setCapabilities] = useState<Capability[]>([]);
+
+  // Ask the backend which actions this package's source supports.
+  const key = pkg?.key;
+  useEffect(() => {
+    let cancelled = false;
+    setCapabilities([]);
+    if (!key) return;
+    packageCapabilities(key)
+      .then((caps) => {
+        if (!cancelled) setCapabilities(caps);
+      })
+      .catch(() => {
+        // Not in the current scan (e.g. just removed): offer nothing.
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [key]);
+
+  // Open the requested dialog once the package is the one shown; the
+  // request is then consumed so it does not reopen on the next mount.
+  const requestId = pkg && dialog?.key === pkg.key ? dialog.id : null;
+  useEffect(() => {
+    if (!pkg || !dialog || requestId === null) return;
+    if (dialog.operation === "uninstall") setUninstallTarget(pkg);
+    else if (dialog.operation === "update") setUpdateTarget(pkg);
+    else setAction(dialog.operation);
+    onDialogOpened?.();
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [requestId]);
+
   if (!pkg) return null;
+  const can = (c: Capability) => capabilities.includes(c);
 
   const title = pkg.display_name ?? pkg.name;
   const rows: { label: string; value: string }[] = [
@@ -27,8 +76,17 @@ export function PackageDetail({
     { label: "Kind", value: `${kindIcon(pkg.app_kind)} ${pkg.app_kind}` },
     { label: "Categories", value: pkg.categories ?? "—" },
     { label: "Runs in terminal", value: pkg.terminal ? "Yes" : "No" },
-    { label: "Update available", value: pkg.has_update ? "Yes" : "—" },
+    {
+      label: "Update available",
+      value: pkg.has_update ? (pkg.security_update ? "Yes (security)" : "Yes") : "—",
+    },
   ];
+  if (pkg.held) {
+    rows.push({ label: "Held", value: "Yes — updates skip this package" });
+  }
+  if (pkg.enabled !== undefined) {
+    rows.push({ label: "Enabled", value: pkg.enabled ? "Yes" : "No" });
+  }
 
   return (
     <div className="pkg-detail">
@@ -54,7 +112,7 @@ export function PackageDetail({
         ))}
       </dl>
       <div className="detail__actions">
-        {pkg.has_update && (
+        {pkg.has_update && can("update") && (
           <button
             type="button"
             className="btn btn--primary detail__update"
@@ -63,13 +121,34 @@ export function PackageDetail({
             Update{pkg.update_version ? ` to ${pkg.update_version}` : ""}
           </button>
         )}
-        <button
-          type="button"
-          className="btn btn--danger detail__uninstall"
-          onClick={() => setUninstallTarget(pkg)}
-        >
-          Uninstall
-        </button>
+        {can("hold") && (
+          <button
+            type="button"
+            className="btn"
+            onClick={() => setAction(pkg.held ? "unhold" : "hold")}
+          >
+            {pkg.held ? "Release hold" : "Hold version"}
+          </button>
+        )}
+        {can("rollback") && (
+          <button type="button" className="btn" onClick={() => setAction("rollback")}>
+            Roll back
+          </button>
+        )}
+        {can("purge") && (
+          <button type="button" className="btn btn--danger" onClick={() => setAction("purge")}>
+            Purge
+          </button>
+        )}
+        {can("uninstall") && (
+          <button
+            type="button"
+            className="btn btn--danger detail__uninstall"
+            onClick={() => setUninstallTarget(pkg)}
+          >
+            Uninstall
+          </button>
+        )}
         <span className="detail__actions-hint">Preview-first · protected packages are blocked</span>
       </div>
       {updateTarget && (
@@ -82,6 +161,17 @@ export function PackageDetail({
           }}
         />
       )}
+      {action && (
+        <ActionDialog
+          pkg={pkg}
+          action={action}
+          onClose={() => setAction(null)}
+          onApplied={(p) => {
+            setAction(null);
+            onUninstalled?.(p);
+          }}
+        />
+      )}
       {uninstallTarget && (
         <UninstallDialog
           pkg={uninstallTarget}
@@ -4,7 +4,7 @@ import { Select } from "../../shared/components/Select";
 
 const SOURCE_OPTIONS: { value: SourceFilter; label: string }[] = [
   { value: "all", label: "Any source" },
-  ...(["apt", "snap", "flatpak", "appimage"] as const).map((s) => ({
+  ...(["apt", "snap", "flatpak", "appimage", "container", "steam", "extension", "plugin"] as const).map((s) => ({
     value: s,
     label: SOURCE_LABELS[s],
   })),
@@ -22,20 +22,30 @@ export function PackageFilters({
   source,
   kind,
   refreshing,
+  checking,
+  updatesCheckedAt,
   onQuery,
   onSource,
   onKind,
   onRescan,
+  onCheckUpdates,
 }: {
   query: string;
   source: SourceFilter;
   kind: KindFilter;
   refreshing: boolean;
+  checking: boolean;
+  updatesCheckedAt: number | null;
   onQuery: (q: string) => void;
   onSource: (s: SourceFilter) => void;
   onKind: (k: KindFilter) => void;
   onRescan: () => void;
+  onCheckUpdates: () => void;
 }) {
+  const checkTitle = updatesCheckedAt
+    ? `Check for updates (last checked ${new Date(updatesCheckedAt).toLocaleString()})`
+    : "Check for updates";
+
   return (
     <div className="filters">
       <div className="filters__row">
@@ -72,6 +82,17 @@ export function PackageFilters({
             <path d="M20 11A8.1 8.1 0 0 0 4.5 9M4 5v4h4m-4 4a8.1 8.1 0 0 0 15.5 2m.5 4v-4h-4" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round"/>
           </svg>
         </button>
+        <button
+          type="button"
+          className="btn btn--ghost btn--icon"
+          onClick={onCheckUpdates}
+          disabled={checking}
+          title={checkTitle}
+        >
+          <svg width="16" height="16" viewBox="0 0 24 24" fill="none" aria-hidden="true" className={checking ? "spin" : ""}>
+            <path d="M12 4v12m0 0l-5-5m5 5l5-5M5 20h14" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round"/>
+          </svg>
+        </button>
       </div>
     </div>
   );
@@ -1,5 +1,6 @@
 import { Fragment } from "react";
 import type { InstalledPackage } from "../../shared/types/package";
+import type { DialogRequest } from "./PackageDetail";
 import { PackageRow } from "./PackageRow";
 import { PackageDetail } from "./PackageDetail";
 
@@ -7,12 +8,16 @@ export function PackageList({
   packages,
   selectedKey,
   selectedPkg,
+  dialog,
+  onDialogOpened,
   onSelect,
   onUninstalled,
 }: {
   packages: InstalledPackage[];
   selectedKey: string | null;
   selectedPkg: InstalledPackage | null;
+  dialog: DialogRequest | null;
+  onDialogOpened: () => void;
   onSelect: (pkg: InstalledPackage) => void;
   onUninstalled: (pkg: InstalledPackage) => void;
 }) {
@@ -29,7 +34,12 @@ export function PackageList({
             onClick={onSelect}
           />
           {p.key === selectedKey && selectedPkg && (
-            <PackageDetail pkg={selectedPkg} onUninstalled={onUninstalled} />
+            <PackageDetail
+              pkg={selectedPkg}
+              dialog={dialog}
+              onDialogOpened={onDialogOpened}
+              onUninstalled={onUninstalled}
+            />
           )}
         </Fragment>
       ))}
@@ -1,5 +1,9 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import type { InstalledPackage } from "../../shared/types/package";
+import { onOpenDialog } from "../../shared/api/operations";
+import { configStatus } from "../../shared/api/config";
+import type { ConfigStatus } from "../../shared/types/config";
+import type { DialogRequest } from "./PackageDetail";
 import { PackageList } from "./PackageList";
 import { PackageFilters } from "./PackageFilters";
 import { usePackages } from "./usePackages";
@@ -8,6 +12,7 @@ export function PackageScreen() {
   const {
     loading,
     refreshing,
+    checking,
     error,
     packages,
     lastScan,
@@ -15,11 +20,39 @@ export function PackageScreen() {
     sourceFilter,
     kindFilter,
     refresh,
+    checkUpdates,
     setQuery,
     setSourceFilter,
     setKindFilter,
+    reveal,
   } = usePackages();
   const [selected, setSelected] = useState<InstalledPackage | null>(null);
+  const [dialog, setDialog] = useState<DialogRequest | null>(null);
+  const [config, setConfig] = useState<ConfigStatus | null>(null);
+
+  // The config is read once at startup, so its problems only need fetching once.
+  useEffect(() => {
+    configStatus()
+      .then(setConfig)
+      .catch(() => {
+        // Without the status the banner simply stays hidden.
+      });
+  }, []);
+
+  // A clicked notification selects its package and opens the dialog it names.
+  useEffect(() => {
+    const unlisten = onOpenDialog((target) => {
+      const pkg = target.package_key ? reveal(target.package_key) : null;
+      if (!pkg) return;
+      setSelected(pkg);
+      setDialog(
+        target.operation ? { id: Date.now(), key: pkg.key, operation: target.operation } : null
+      );
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [reveal]);
 
   const handleSelect = (pkg: InstalledPackage) =>
     setSelected((prev) => (prev?.key === pkg.key ? null : pkg));
@@ -30,11 +63,12 @@ export function PackageScreen() {
       ? packages.find((p) => p.key === selected.key)!
       : selected;
 
+  // No rescan here: the backend rescans the package's source after every
+  // operation and the resulting `scan-diff` event updates the list.
   const handleUninstalled = (pkg: InstalledPackage) => {
     if (selected?.key === pkg.key) {
       setSelected(null);
     }
-    refresh();
   };
 
   return (
@@ -50,13 +84,21 @@ export function PackageScreen() {
         source={sourceFilter}
         kind={kindFilter}
         refreshing={refreshing}
+        checking={checking}
+        updatesCheckedAt={lastScan?.updates_checked_at_ms ?? null}
         onQuery={setQuery}
         onSource={setSourceFilter}
         onKind={setKindFilter}
         onRescan={refresh}
+        onCheckUpdates={checkUpdates}
       />
 
       {error && <div className="banner banner--error">{error}</div>}
+      {config && config.errors.length > 0 && (
+        <div className="banner banner--warn">
+          {config.path ?? "config.toml"}: {config.errors.join("; ")}
+        </div>
+      )}
       {lastScan?.availability?.apt_error && !error && (
         <div className="banner banner--warn">
           APT: {lastScan.availability.apt_error}
@@ -66,13 +108,15 @@ export function PackageScreen() {
       <div className="screen__body">
         {loading ? (
           <div className="pkg-list pkg-list--loading">
-            Scanning installed apps across APT, Snap, Flatpak, and AppImage…
+            Scanning installed apps across APT, Snap, Flatpak, AppImage, containers, Steam, extensions, and plugins…
           </div>
         ) : (
           <PackageList
             packages={packages}
             selectedKey={selectedRow?.key ?? null}
             selectedPkg={selectedRow}
+            dialog={dialog}
+            onDialogOpened={() => setDialog(null)}
             onSelect={handleSelect}
             onUninstalled={handleUninstalled}
           />
@@ -19,12 +19,25 @@ export function sourceBadgeColor(source: PackageSource): string {
     snap: "#2196f3",
     flatpak: "#4a154b",
     appimage: "#0b8a4f",
+    container: "#8b5cf6",
+    steam: "#1b2838",
+    extension: "#c2410c",
+    plugin: "#475569",
   };
   return map[source];
 }
 
 export function sourceLabel(source: PackageSource): string {
-  return { apt: "APT", snap: "Snap", flatpak: "Flatpak", appimage: "AppImage" }[source];
+  return {
+    apt: "APT",
+    snap: "Snap",
+    flatpak: "Flatpak",
+    appimage: "AppImage",
+    container: "Container",
+    steam: "Steam",
+    extension: "Extension",
+    plugin: "Plugin",
+  }[source];
 }
 
 export function kindIcon(kind: AppKind): string {
@@ -1,7 +1,9 @@
-import { useCallback, useEffect, useState } from "react";
+import { useCallback, useEffect, useRef, useState } from "react";
 import {
   scanPackages,
+  checkUpdates as checkUpdatesApi,
   getCachedScan,
+  onScanDiff,
   searchPackages,
 } from "../../shared/api/packages";
 import type {
@@ -17,6 +19,7 @@ export type KindFilter = AppKind | "all";
 interface UsePackagesState {
   loading: boolean;
   refreshing: boolean;
+  checking: boolean;
   error: string | null;
   packages: InstalledPackage[];
   lastScan: CachedScan | null;
@@ -29,6 +32,7 @@ export function usePackages() {
   const [state, setState] = useState<UsePackagesState>({
     loading: true,
     refreshing: false,
+    checking: false,
     error: null,
     packages: [],
     lastScan: null,
@@ -36,6 +40,9 @@ export function usePackages() {
     sourceFilter: "all",
     kindFilter: "all",
   });
+  // Latest state for event handlers registered once on mount.
+  const stateRef = useRef(state);
+  stateRef.current = state;
 
   // Filter happens client-side on the cached full scan (kept fast & offline).
   const applyFilters = useCallback(
@@ -88,18 +95,43 @@ export function usePackages() {
     }
   }, [applyFilters, state.query, state.sourceFilter, state.kindFilter]);
 
-  // Initial load: reuse a cached scan if present, else scan fresh.
+  // The check's results arrive as a scan diff like any other cache change.
+  const checkUpdates = useCallback(async () => {
+    setState((s) => ({ ...s, checking: true, error: null }));
+    try {
+      await checkUpdatesApi();
+      setState((s) => ({ ...s, checking: false }));
+    } catch (e) {
+      setState((s) => ({ ...s, checking: false, error: String(e) }));
+    }
+  }, []);
+
+  // Initial load: show the cached scan right away (possibly the previous
+  // run's, marked stale) while the backend's startup scan runs. Afterwards
+  // every change to the cached scan (startup refresh, rescans after
+  // operations or package changes outside Scope) arrives as a diff event.
   useEffect(() => {
     let cancelled = false;
+    let refreshed = false;
+    const unlisten = onScanDiff(async () => {
+      const fresh = await getCachedScan();
+      if (cancelled || !fresh) return;
+      refreshed = true;
+      setState((s) => ({ ...s, loading: false, refreshing: false, lastScan: fresh }));
+      const { query, sourceFilter, kindFilter } = stateRef.current;
+      applyFilters(fresh, query, sourceFilter, kindFilter);
+    });
     (async () => {
       try {
+        // Subscribe first so a refresh finishing in between is not missed.
+        await unlisten;
         const cached = await getCachedScan();
-        if (cancelled) return;
+        if (cancelled || refreshed) return;
         if (cached) {
-          setState((s) => ({ ...s, loading: false, lastScan: cached }));
+          setState((s) => ({ ...s, loading: false, refreshing: cached.stale, lastScan: cached }));
           applyFilters(cached, "", "all", "all");
         } else {
-          await refresh();
+          setState((s) => ({ ...s, refreshing: true }));
         }
       } catch (e) {
         if (!cancelled) {
@@ -109,6 +141,7 @@ export function usePackages() {
     })();
     return () => {
       cancelled = true;
+      unlisten.then((fn) => fn());
     };
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
@@ -137,6 +170,21 @@ export function usePackages() {
     [applyFilters, state.lastScan, state.query, state.sourceFilter]
   );
 
+  // Find a package in the cached scan, clearing the filters if they hide it.
+  // Stable, for event handlers registered once on mount.
+  const reveal = useCallback(
+    (key: string): InstalledPackage | null => {
+      const { lastScan, packages } = stateRef.current;
+      const pkg = lastScan?.packages.find((p) => p.key === key) ?? null;
+      if (pkg && !packages.some((p) => p.key === key)) {
+        setState((s) => ({ ...s, query: "", sourceFilter: "all", kindFilter: "all" }));
+        applyFilters(lastScan, "", "all", "all");
+      }
+      return pkg;
+    },
+    [applyFilters]
+  );
+
   // Also expose the server-side search for parity; not used by the default UI
   // flow but available for future "search anywhere" affordances.
   const serverSearch = useCallback(
@@ -149,9 +197,11 @@ export function usePackages() {
   return {
     ...state,
     refresh,
+    checkUpdates,
     setQuery,
     setSourceFilter,
     setKindFilter,
+    reveal,
     serverSearch,
   };
 }
@@ -136,6 +136,13 @@ export function UninstallDialog({ pkg, onClose, onUninstalled }: Props) {
                     </li>
                   ))}
                 </ul>
+                {plan.warnings.length > 0 && (
+                  <div className="banner banner--warn">
+                    {plan.warnings.map((w, i) => (
+                      <p key={i}>{w}</p>
+                    ))}
+                  </div>
+                )}
                 <p className="modal__warn">
                   ⚠ This removes the package from your system. AppImages go to Trash; everything else is removed by its package manager.
                 </p>
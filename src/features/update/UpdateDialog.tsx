@@ -143,9 +143,6 @@ export function UpdateDialog({ pkg, onClose, onUpdated }: Props) {
                     </li>
                   ))}
                 </ul>
-                <p className="modal__warn">
-                  AppImages cannot be auto-updated yet. Download and replace them manually.
-                </p>
               </>
             )}
             <div className="modal__actions">
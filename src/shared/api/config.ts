@@ -0,0 +1,9 @@
+// Typed Tauri invoke wrapper for the configuration command.
+
+import { invoke } from "@tauri-apps/api/core";
+import type { ConfigStatus } from "../types/config";
+
+/** Where the user's config file is and what was wrong with it. */
+export function configStatus(): Promise<ConfigStatus> {
+  return invoke<ConfigStatus>("config_status");
+}
@@ -1,7 +1,14 @@
-// Typed Tauri invoke wrappers for the uninstall and update commands.
+// Typed Tauri invoke wrappers for the operation commands.
 
 import { invoke } from "@tauri-apps/api/core";
-import type { OperationPlan, OperationResult } from "../types/operations";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type {
+  Capability,
+  DialogTarget,
+  Operation,
+  OperationPlan,
+  OperationResult,
+} from "../types/operations";
 
 /// Ask the backend to build (and store) an uninstall preview plan for the
 /// package with the given backend key (`<source>:<package_id>`).
@@ -25,3 +32,24 @@ export function previewUpdate(packageKey: string): Promise<OperationPlan> {
 export function applyUpdate(planId: string): Promise<OperationResult> {
   return invoke<OperationResult>("apply_update", { planId });
 }
+
+/// Which capabilities the backend supports for the package with the given
+/// key. The UI only offers actions listed here.
+export function packageCapabilities(packageKey: string): Promise<Capability[]> {
+  return invoke<Capability[]>("package_capabilities", { packageKey });
+}
+
+/// Build (and store) a preview plan for any supported operation.
+export function previewOperation(packageKey: string, operation: Operation): Promise<OperationPlan> {
+  return invoke<OperationPlan>("preview_operation", { packageKey, operation });
+}
+
+/// Apply a previously-issued plan by id, whatever its operation.
+export function applyOperation(planId: string): Promise<OperationResult> {
+  return invoke<OperationResult>("apply_operation", { planId });
+}
+
+/// Subscribe to clicked desktop notifications, which name the dialog to reopen.
+export function onOpenDialog(handler: (target: DialogTarget) => void): Promise<UnlistenFn> {
+  return listen<DialogTarget>("open-dialog", (event) => handler(event.payload));
+}
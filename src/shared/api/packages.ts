@@ -1,12 +1,15 @@
 // Typed Tauri invoke wrappers for the package commands.
 
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
   CachedScan,
   InstalledPackage,
   PackageSource,
   AppKind,
+  ScanDiff,
   ScanStatus,
+  UpdateCheck,
 } from "../types/package";
 
 export function scanPackages(): Promise<CachedScan> {
@@ -21,6 +24,11 @@ export function scanStatus(): Promise<ScanStatus> {
   return invoke<ScanStatus>("scan_status");
 }
 
+/** Check the cached scan for updates; the annotated packages arrive as a scan diff. */
+export function checkUpdates(): Promise<UpdateCheck | null> {
+  return invoke<UpdateCheck | null>("check_updates");
+}
+
 export function searchPackages(
   query?: string,
   source?: PackageSource,
@@ -31,4 +39,14 @@ export function searchPackages(
     source: source ?? null,
     appKind: appKind ?? null,
   });
-}
\ No newline at end of file
+}
+
+/** Subscribe to changes of the cached scan: full scans, the startup refresh, and incremental rescans. */
+export function onScanDiff(handler: (diff: ScanDiff) => void): Promise<UnlistenFn> {
+  return listen<ScanDiff>("scan-diff", (event) => handler(event.payload));
+}
+
+/** Subscribe to finished update checks, scheduled or requested. */
+export function onUpdatesChecked(handler: (check: UpdateCheck) => void): Promise<UnlistenFn> {
+  return listen<UpdateCheck>("updates-checked", (event) => handler(event.payload));
+}
@@ -11,7 +11,8 @@ import { SOURCE_COLORS } from "../../shared/types/package";
  * URL produced by the backend's icon resolver, or `undefined` for non-GUI
  * packages / unresolved icons. We never touch the filesystem from the
  * webview — the backend serves the bytes through the registered
- * `scope-icon://` URI-scheme protocol.
+ * `scope-icon://` URI-scheme protocol, as a PNG thumbnail of the rendered
+ * size (`?size=`) rather than the original file.
  */
 export function AppIcon({
   pkg,
@@ -23,7 +24,13 @@ export function AppIcon({
   size: "row" | "detail";
 }) {
   const [failed, setFailed] = useState(false);
-  const url = useMemo(() => (pkg.icon ? pkg.icon : null), [pkg.icon]);
+  const url = useMemo(
+    () =>
+      pkg.icon
+        ? `${pkg.icon}?size=${Math.ceil(ICON_PX[size] * (window.devicePixelRatio || 1))}`
+        : null,
+    [pkg.icon, size],
+  );
 
   useEffect(() => {
     setFailed(false);
@@ -56,6 +63,9 @@ export function AppIcon({
   );
 }
 
+/** CSS size of each icon variant, matching `.app-icon--*` in App.css. */
+const ICON_PX = { row: 40, detail: 56 } as const;
+
 function initials(name: string): string {
   const parts = name.trim().split(/[\s_\-]+/).filter(Boolean);
   if (parts.length === 0) return "?";
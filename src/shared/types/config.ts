@@ -0,0 +1,11 @@
+// TypeScript model matching `ConfigStatus` in src-tauri/src/config/mod.rs.
+// Keep in sync with the backend.
+
+export interface ConfigStatus {
+  /** The config file (~/.config/scope/config.toml), whether or not it exists. */
+  path: string | null;
+  /** Whether the file exists. */
+  loaded: boolean;
+  /** Problems found when it was read at startup; the values involved were ignored. */
+  errors: string[];
+}
@@ -1,11 +1,31 @@
-// TypeScript models matching the Rust DTOs in src-tauri/src/operations/mod.rs.
+// TypeScript models matching the Rust DTOs in src-tauri/src/operations/mod.rs
+// and src-tauri/src/backend/mod.rs.
 // Keep in sync with the backend.
 
 import type { InstallScope, PackageSource } from "./package";
 
-export type Operation = "uninstall" | "update";
+export type Operation = "uninstall" | "update" | "purge" | "hold" | "unhold" | "rollback";
+
+/// Mirrors `Capability` in src-tauri/src/backend/mod.rs.
+export type Capability =
+  | "scan"
+  | "check_updates"
+  | "uninstall"
+  | "update"
+  | "purge"
+  | "hold"
+  | "rollback";
 export type AuthMethod = "none" | "pkexec";
 
+/** Payload of the `open-dialog` event, sent when a desktop notification is
+ * clicked. Mirrors `DialogTarget` in src-tauri/src/notify/mod.rs: open the
+ * package's dialog for `operation`, just the package when `operation` is
+ * null, or only the window when `package_key` is null. */
+export interface DialogTarget {
+  package_key: string | null;
+  operation: Operation | null;
+}
+
 export interface PlanStep {
   description: string;
   command_summary: string;
@@ -24,6 +44,8 @@ export interface OperationPlan {
   auth_method: AuthMethod;
   protected: boolean;
   protection_reason?: string;
+  /** What else the operation affects, e.g. packages a removal takes with it. */
+  warnings: string[];
   steps: PlanStep[];
   created_at_ms: number;
 }
@@ -1,12 +1,16 @@
 // TypeScript models matching the Rust DTOs in src-tauri/src/package.rs and
 // src-tauri/src/scanner/mod.rs. Keep in sync with the backend.
 
-export type PackageSource = "apt" | "snap" | "flatpak" | "appimage";
+export type PackageSource = "apt" | "snap" | "flatpak" | "appimage" | "container" | "steam" | "extension" | "plugin";
 
 export type AppKind = "gui" | "cli" | "unknown";
 
 export type InstallScope = "user" | "system";
 
+/** Mirrors `BaseSystem` in src-tauri/src/package.rs: the dpkg field that
+ * makes an APT package part of the base system. */
+export type BaseSystem = "essential" | "protected" | "required" | "important";
+
 export interface InstalledPackage {
   key: string;
   source: PackageSource;
@@ -23,6 +27,12 @@ export interface InstalledPackage {
   terminal: boolean;
   has_update: boolean;
   update_version?: string;
+  /** The update comes from a security archive (APT `-security` suites). */
+  security_update: boolean;
+  held: boolean;
+  enabled?: boolean;
+  /** dpkg marks the APT package as part of the base system; it cannot be removed. */
+  base_system?: BaseSystem;
 }
 
 export interface ScanAvailability {
@@ -30,16 +40,51 @@ export interface ScanAvailability {
   snap: boolean;
   flatpak: boolean;
   appimage: boolean;
+  container: boolean;
+  steam: boolean;
+  extension: boolean;
   apt_error?: string;
   snap_error?: string;
   flatpak_error?: string;
+  container_error?: string;
+  steam_error?: string;
+  extension_error?: string;
   appimage_dirs: string[];
+  /** One entry per external scanner plugin, sorted by name. */
+  plugins: PluginStatus[];
+}
+
+export interface PluginStatus {
+  name: string;
+  available: boolean;
+  error?: string;
 }
 
 export interface CachedScan {
   packages: InstalledPackage[];
   availability: ScanAvailability;
   scanned_at_ms: number;
+  /** Loaded from the previous run; a background refresh is replacing it. */
+  stale: boolean;
+  /** When updates were last checked; null until the first check. */
+  updates_checked_at_ms: number | null;
+}
+
+/** Payload of the `updates-checked` event and result of `check_updates`. */
+export interface UpdateCheck {
+  outdated: number;
+  /** When every source was last checked; an earlier time if this check had failures. */
+  checked_at_ms: number;
+  /** Sources whose check failed, with the error; their packages keep earlier results. */
+  failed: Partial<Record<PackageSource, string>>;
+}
+
+/** Payload of the `scan-diff` event: what changed in the cached scan, by package key. */
+export interface ScanDiff {
+  added: InstalledPackage[];
+  removed: string[];
+  changed: InstalledPackage[];
+  scanned_at_ms: number;
 }
 
 export interface ScanStatus {
@@ -47,6 +92,9 @@ export interface ScanStatus {
   snap_available: boolean;
   flatpak_available: boolean;
   appimage_available: boolean;
+  container_available: boolean;
+  steam_available: boolean;
+  extension_available: boolean;
   appimage_dirs: string[];
 }
 
@@ -55,6 +103,10 @@ export const SOURCE_LABELS: Record<PackageSource, string> = {
   snap: "Snap",
   flatpak: "Flatpak",
   appimage: "AppImage",
+  container: "Container",
+  steam: "Steam",
+  extension: "Extension",
+  plugin: "Plugin",
 };
 
 export const SOURCE_COLORS: Record<PackageSource, string> = {
@@ -62,4 +114,8 @@ export const SOURCE_COLORS: Record<PackageSource, string> = {
   snap: "#2196f3",
   flatpak: "#4a154b",
   appimage: "#0b8a4f",
+  container: "#8b5cf6",
+  steam: "#1b2838",
+  extension: "#c2410c",
+  plugin: "#475569",
 };